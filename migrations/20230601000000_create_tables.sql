@@ -0,0 +1,104 @@
+-- Objects are stored whole as JSON in `data`, as returned by the API, with the fields
+-- worth querying on pulled out into their own columns.
+
+-- Latest known state of each agent
+CREATE TABLE agents (
+    symbol TEXT PRIMARY KEY,
+    account_id TEXT NOT NULL,
+    headquarters TEXT NOT NULL,
+    credits BIGINT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
+
+-- Credits of each agent every time it is fetched
+CREATE TABLE agent_snapshots (
+    symbol TEXT NOT NULL,
+    credits BIGINT NOT NULL,
+    fetched_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (symbol, fetched_at)
+);
+
+-- Latest known state of each of our ships
+CREATE TABLE ships (
+    symbol TEXT PRIMARY KEY,
+    data TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
+
+-- Location, fuel and cargo of each ship every time it is fetched
+CREATE TABLE ship_snapshots (
+    symbol TEXT NOT NULL,
+    status TEXT NOT NULL,
+    waypoint_symbol TEXT NOT NULL,
+    fuel INTEGER NOT NULL,
+    cargo_units INTEGER NOT NULL,
+    fetched_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (symbol, fetched_at)
+);
+
+CREATE TABLE contracts (
+    id TEXT PRIMARY KEY,
+    faction_symbol TEXT NOT NULL,
+    type TEXT NOT NULL,
+    accepted BOOLEAN NOT NULL,
+    fulfilled BOOLEAN NOT NULL,
+    data TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE factions (
+    symbol TEXT PRIMARY KEY,
+    headquarters TEXT NOT NULL,
+    data TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE systems (
+    symbol TEXT PRIMARY KEY,
+    sector_symbol TEXT NOT NULL,
+    type TEXT NOT NULL,
+    x INTEGER NOT NULL,
+    y INTEGER NOT NULL,
+    data TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE waypoints (
+    symbol TEXT PRIMARY KEY,
+    system_symbol TEXT NOT NULL,
+    type TEXT NOT NULL,
+    x INTEGER NOT NULL,
+    y INTEGER NOT NULL,
+    data TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX waypoints_system_symbol ON waypoints (system_symbol);
+
+CREATE TABLE markets (
+    waypoint_symbol TEXT PRIMARY KEY,
+    data TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE shipyards (
+    waypoint_symbol TEXT PRIMARY KEY,
+    data TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
+
+-- Purchases and sales at markets and shipyards.
+-- For ship purchases, `trade_symbol` is the type of ship bought.
+CREATE TABLE transactions (
+    waypoint_symbol TEXT NOT NULL,
+    ship_symbol TEXT NOT NULL,
+    trade_symbol TEXT NOT NULL,
+    type TEXT NOT NULL,
+    units INTEGER NOT NULL,
+    price_per_unit INTEGER NOT NULL,
+    total_price INTEGER NOT NULL,
+    timestamp TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (waypoint_symbol, ship_symbol, trade_symbol, type, timestamp)
+);
+
+CREATE INDEX transactions_timestamp ON transactions (timestamp);
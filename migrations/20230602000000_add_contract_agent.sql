@@ -0,0 +1,2 @@
+-- Agent each contract was offered to, so cached contracts can be shown for the right agent
+ALTER TABLE contracts ADD COLUMN agent_symbol TEXT NOT NULL DEFAULT '';
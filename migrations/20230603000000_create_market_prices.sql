@@ -0,0 +1,11 @@
+-- Prices of each good at a market every time it is fetched while a ship is there
+CREATE TABLE market_prices (
+    waypoint_symbol TEXT NOT NULL,
+    trade_symbol TEXT NOT NULL,
+    supply TEXT NOT NULL,
+    trade_volume INTEGER NOT NULL,
+    purchase_price INTEGER NOT NULL,
+    sell_price INTEGER NOT NULL,
+    fetched_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (waypoint_symbol, trade_symbol, fetched_at)
+);
@@ -0,0 +1,7 @@
+-- Jump gates and the systems they connect to, which don't change during a reset
+CREATE TABLE jump_gates (
+    waypoint_symbol TEXT PRIMARY KEY,
+    system_symbol TEXT NOT NULL,
+    data TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,6 @@
+-- Tokens of agents that can be switched between, added in the app rather than the config file
+CREATE TABLE agent_tokens (
+    symbol TEXT PRIMARY KEY,
+    token TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,5 @@
+-- Reset dates of the server our data was fetched during, the latest being the current one
+CREATE TABLE server_resets (
+    reset_date TEXT PRIMARY KEY,
+    recorded_at TIMESTAMPTZ NOT NULL
+);
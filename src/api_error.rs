@@ -0,0 +1,104 @@
+use std::fmt;
+
+use reqwest::StatusCode;
+use serde::Deserialize;
+
+use crate::rate_limit;
+
+/// Body of an error response from the SpaceTraders API
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: ErrorBodyInner,
+}
+
+#[derive(Deserialize)]
+struct ErrorBodyInner {
+    message: String,
+    code: i64,
+    #[serde(default)]
+    data: Option<serde_json::Value>,
+}
+
+/// An error from a SpaceTraders API request, with the error body parsed where possible
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiError {
+    /// HTTP status of the response, if one was received
+    pub status: Option<StatusCode>,
+    /// SpaceTraders error code, if the response had one
+    pub code: Option<i64>,
+    /// What went wrong
+    pub message: String,
+    /// Extra details of the error, if the response had any
+    pub data: Option<serde_json::Value>,
+    /// Whether the request failed without reaching the server
+    pub unreachable: bool,
+    /// Times the request was retried before giving up
+    pub retries: usize,
+}
+
+impl ApiError {
+    /// Whether the error was the server's fault or a connection problem,
+    /// rather than a request the game rejected
+    #[must_use]
+    pub fn is_server_error(&self) -> bool {
+        self.status.is_none_or(|status| status.is_server_error())
+    }
+}
+
+/// Whether a request failed because the server couldn't be reached, rather than answering badly
+fn is_unreachable(error: &reqwest::Error) -> bool {
+    error.is_connect() || error.is_timeout()
+}
+
+impl<T> From<spacedust::apis::Error<T>> for ApiError {
+    fn from(error: spacedust::apis::Error<T>) -> Self {
+        use spacedust::apis::Error;
+
+        let (status, message, unreachable) = match error {
+            Error::ResponseError(response) => {
+                if let Ok(body) = serde_json::from_str::<ErrorBody>(&response.content) {
+                    return Self {
+                        status: Some(response.status),
+                        code: Some(body.error.code),
+                        message: body.error.message,
+                        data: body.error.data,
+                        unreachable: false,
+                        retries: rate_limit::last_retries(),
+                    };
+                }
+                let message = if response.content.is_empty() {
+                    response.status.to_string()
+                } else {
+                    response.content
+                };
+                (Some(response.status), message, false)
+            }
+            Error::Reqwest(e) | Error::ReqwestMiddleware(reqwest_middleware::Error::Reqwest(e)) => {
+                (e.status(), e.to_string(), is_unreachable(&e))
+            }
+            Error::ReqwestMiddleware(e) => (None, e.to_string(), false),
+            Error::Serde(e) => (None, format!("Unexpected response: {e}"), false),
+            Error::Io(e) => (None, e.to_string(), false),
+        };
+        Self {
+            status,
+            code: None,
+            message,
+            data: None,
+            unreachable,
+            retries: rate_limit::last_retries(),
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.code {
+            Some(code) => write!(f, "{} (code {code})", self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
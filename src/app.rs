@@ -1,21 +1,51 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    mem,
+};
+
+use chrono::{DateTime, Utc};
 use log::error;
-use spacedust::models::{Agent, Contract, Faction};
-use strum::{Display, EnumCount, EnumIter};
+use spacedust::models::{
+    register_request, ship_refine_request::Produce, waypoint_trait, Agent, Contract, Cooldown,
+    Faction, Market, MarketTradeGood, Ship, ShipNavFlightMode, ShipNavStatus, Shipyard,
+    ShipyardShip, Survey, System, Waypoint,
+};
+use strum::{Display, EnumCount, EnumIter, IntoEnumIterator};
 use tokio::sync::mpsc;
-use tui::widgets::ListState;
+use tui::widgets::{ListState, TableState};
+
+use crate::{
+    api_error::ApiError,
+    config::SETTINGS,
+    db_util::{self, Cache, PricePoint},
+    io::{runner, AgentEvent, IoEvent},
+    profile::{self, Profile},
+    rate_limit::{self, RateLimits},
+    st_util,
+    trade::{self, TradeData, TradeRoute},
+};
 
-use crate::io::IoEvent;
+mod handle;
+
+pub use handle::{AppHandle, Update};
 
 /// Application.
 #[derive(Debug)]
 pub struct App {
     /// Is the application running?
     running: bool,
-    /// Current application state
+    /// Current application state, of the agent being shown
     pub state: State,
-    io_sender: mpsc::Sender<IoEvent>,
+    /// agent the app is acting as, or `None` to use `TOKEN` from the settings
+    pub profile: Option<Profile>,
+    /// every agent that can be switched to
+    pub profiles: Vec<Profile>,
+    /// state of the agents that aren't shown, by symbol, kept up to date in the background
+    other_states: HashMap<String, State>,
+    io_sender: mpsc::Sender<AgentEvent>,
 }
 
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Debug)]
 pub struct State {
     /// current main tab
@@ -28,6 +58,76 @@ pub struct State {
     pub contracts_list_state: ListState,
     /// current [`Faction`] data
     pub factions: Vec<Faction>,
+    /// current [`Ship`] data
+    pub ships: Vec<Ship>,
+    /// [`ListState`] for list of Ships on the fleet page
+    pub ships_list_state: ListState,
+    /// most recent [`Cooldown`] of each ship, by ship symbol
+    pub cooldowns: HashMap<String, Cooldown>,
+    /// unexpired [`Survey`]s we have made
+    pub surveys: Vec<Survey>,
+    /// activity log of each ship, by ship symbol, oldest first
+    pub activity_logs: HashMap<String, Vec<LogEntry>>,
+    /// how many entries the activity log on the fleet page is scrolled back
+    pub activity_scroll: usize,
+    /// symbol of the [`System`] shown on the systems page
+    pub system_symbol: String,
+    /// known [`System`] data
+    pub systems: Vec<System>,
+    /// current [`Waypoint`] data for the shown system
+    pub waypoints: Vec<Waypoint>,
+    /// [`TableState`] for table of Waypoints on the systems page
+    pub waypoints_table_state: TableState,
+    /// column the Waypoints table is sorted by
+    pub waypoints_sort: WaypointSort,
+    /// whether the Waypoints table is sorted in descending order
+    pub waypoints_sort_descending: bool,
+    /// whether every known [`System`] has been loaded, not just the ones viewed
+    pub all_systems_loaded: bool,
+    /// view of the star map on the systems page
+    pub map: MapState,
+    /// symbol of the waypoint whose [`Market`] is shown on the market page
+    pub market_symbol: String,
+    /// current [`Market`] data for the shown waypoint
+    pub market: Option<Market>,
+    /// [`TableState`] for table of goods on the market page
+    pub market_table_state: TableState,
+    /// recorded [`PricePoint`]s of each good at the shown market, by trade symbol, oldest first
+    pub market_history: HashMap<String, Vec<PricePoint>>,
+    /// symbol of the waypoint whose [`Shipyard`] is shown on the shipyard page
+    pub shipyard_symbol: String,
+    /// current [`Shipyard`] data for the shown waypoint
+    pub shipyard: Option<Shipyard>,
+    /// [`TableState`] for table of ships on the shipyard page
+    pub shipyard_table_state: TableState,
+    /// symbol of the ship trade routes are found for on the trade page
+    pub trade_ship: String,
+    /// flight mode trade routes are planned with
+    pub trade_flight_mode: ShipNavFlightMode,
+    /// recorded markets around the trade ship's system
+    pub trade_data: Option<TradeData>,
+    /// profitable [`TradeRoute`]s for the trade ship, most credits per hour first
+    pub trade_routes: Vec<TradeRoute>,
+    /// [`TableState`] for table of routes on the trade page
+    pub trade_table_state: TableState,
+    /// currently open text [`Prompt`], if any
+    pub prompt: Option<Prompt>,
+    /// currently open [`Picker`], if any
+    pub picker: Option<Picker>,
+    /// currently open [`Confirm`] dialog, if any
+    pub confirm: Option<Confirm>,
+    /// [`Notification`]s waiting to be or being shown in the status bar, oldest first
+    pub toasts: VecDeque<Toast>,
+    /// every [`Notification`] so far, oldest first
+    pub notifications: Vec<Notification>,
+    /// whether the notification history is open
+    pub show_notifications: bool,
+    /// how many entries the notification history is scrolled back
+    pub notifications_scroll: usize,
+    /// when each [`Section`] still showing data from the database was recorded
+    pub stale_since: HashMap<Section, DateTime<Utc>>,
+    /// whether the last request failed to reach the API, making cached data read-only
+    pub offline: bool,
 }
 
 impl Default for State {
@@ -38,30 +138,542 @@ impl Default for State {
             contracts: Vec::new(),
             contracts_list_state: ListState::default(),
             factions: Vec::new(),
+            ships: Vec::new(),
+            ships_list_state: ListState::default(),
+            cooldowns: HashMap::new(),
+            surveys: Vec::new(),
+            activity_logs: HashMap::new(),
+            activity_scroll: 0,
+            system_symbol: String::new(),
+            systems: Vec::new(),
+            waypoints: Vec::new(),
+            waypoints_table_state: TableState::default(),
+            waypoints_sort: WaypointSort::Symbol,
+            waypoints_sort_descending: false,
+            all_systems_loaded: false,
+            map: MapState::default(),
+            market_symbol: String::new(),
+            market: None,
+            market_table_state: TableState::default(),
+            market_history: HashMap::new(),
+            shipyard_symbol: String::new(),
+            shipyard: None,
+            shipyard_table_state: TableState::default(),
+            trade_ship: String::new(),
+            trade_flight_mode: ShipNavFlightMode::Cruise,
+            trade_data: None,
+            trade_routes: Vec::new(),
+            trade_table_state: TableState::default(),
+            prompt: None,
+            picker: None,
+            confirm: None,
+            toasts: VecDeque::new(),
+            notifications: Vec::new(),
+            show_notifications: false,
+            notifications_scroll: 0,
+            stale_since: HashMap::new(),
+            offline: false,
         }
     }
 }
 
-#[derive(Debug, EnumIter, Display, EnumCount, PartialEq, Eq)]
+/// Parts of [`State`] that can be shown from the database until they are fetched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Section {
+    Agent,
+    Contracts,
+    Ships,
+    Waypoints,
+}
+
+#[derive(Debug, Clone, Copy, EnumIter, Display, EnumCount, PartialEq, Eq)]
 pub enum Tab {
     Agent,
     Systems,
     Fleet,
+    Market,
+    Shipyard,
+    Trade,
+}
+
+#[derive(Debug, EnumIter, Display, Clone, Copy, PartialEq, Eq)]
+pub enum WaypointSort {
+    Symbol,
+    Type,
+    Distance,
+    Faction,
+}
+
+/// Most entries kept in each ship's activity log
+const MAX_LOG_ENTRIES: usize = 500;
+
+/// How many toasts are shown in the status bar at once
+pub const MAX_VISIBLE_TOASTS: usize = 3;
+
+/// How long a toast is shown for
+const TOAST_SECONDS: i64 = 8;
+
+/// How far back market price history is shown
+pub const PRICE_HISTORY_DAYS: i64 = 7;
+
+/// Flight modes a ship can be set to
+const FLIGHT_MODES: [ShipNavFlightMode; 4] = [
+    ShipNavFlightMode::Cruise,
+    ShipNavFlightMode::Burn,
+    ShipNavFlightMode::Drift,
+    ShipNavFlightMode::Stealth,
+];
+
+/// Products that ships can refine
+const REFINE_PRODUCTS: [Produce; 9] = [
+    Produce::Iron,
+    Produce::Copper,
+    Produce::Silver,
+    Produce::Gold,
+    Produce::Aluminum,
+    Produce::Platinum,
+    Produce::Uranite,
+    Produce::Meritium,
+    Produce::Fuel,
+];
+
+/// Agent picker values for the options after the agents, which can't be agent symbols
+const ADD_AGENT_BY_TOKEN: &str = "+token";
+const REGISTER_AGENT: &str = "+register";
+
+/// An entry in a ship's activity log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    /// When it happened
+    pub time: DateTime<Utc>,
+    /// What happened
+    pub message: String,
+}
+
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// Something the user should know about, such as a failed request
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    /// When it happened
+    pub time: DateTime<Utc>,
+    /// How bad it is
+    pub severity: Severity,
+    /// Short summary
+    pub message: String,
+    /// Extra lines shown in the notification history
+    pub details: Vec<String>,
+}
+
+/// A [`Notification`] queued for the status bar
+#[derive(Debug, Clone, PartialEq)]
+pub struct Toast {
+    /// What is shown
+    pub notification: Notification,
+    /// When it started being shown, if it has
+    pub shown_at: Option<DateTime<Utc>>,
+}
+
+/// View of the star map on the systems page
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapState {
+    /// whether arrow keys pan the map instead of moving the selection
+    pub focused: bool,
+    /// whether the map shows every system instead of the waypoints of the current system
+    pub galaxy: bool,
+    /// center of the view, in map coordinates
+    pub center: (f64, f64),
+    /// zoom level, where 1 fits everything on the map
+    pub zoom: f64,
+}
+
+impl Default for MapState {
+    fn default() -> Self {
+        Self {
+            focused: false,
+            galaxy: false,
+            center: (0.0, 0.0),
+            zoom: 1.0,
+        }
+    }
+}
+
+/// A single line text prompt shown over the current tab
+#[derive(Debug)]
+pub struct Prompt {
+    /// What the prompt is asking for
+    pub title: String,
+    /// Text entered so far
+    pub input: String,
+    /// What to do with the input once submitted
+    pub action: PromptAction,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PromptAction {
+    /// Show the system with the entered symbol on the systems page
+    GotoSystem,
+    /// Buy the entered number of units of a good with a ship
+    PurchaseCargo { ship: String, good: String },
+    /// Sell the entered number of units of a good from a ship
+    SellCargo { ship: String, good: String },
+    /// Jettison the entered number of units of a good from a ship
+    Jettison { ship: String, good: String },
+    /// Change the rate limits to the entered burst and per-second limits
+    RateLimits,
+    /// Add the agent the entered token belongs to
+    AddAgent,
+    /// Register a new agent with the entered call sign, starting with this faction
+    RegisterAgent { faction: register_request::Faction },
+}
+
+/// A popup list to choose one of several options from
+#[derive(Debug)]
+pub struct Picker {
+    /// What the picker is choosing
+    pub title: String,
+    /// Options to choose from
+    pub items: Vec<PickerItem>,
+    /// [`ListState`] for the list of options
+    pub list_state: ListState,
+    /// What to do with the chosen option
+    pub action: PickerAction,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PickerItem {
+    /// Value passed on to the action when chosen
+    pub value: String,
+    /// Text shown in the list
+    pub label: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PickerAction {
+    /// Navigate the ship with this symbol to the chosen waypoint
+    NavigateShip(String),
+    /// Set the flight mode of the ship with this symbol
+    SetFlightMode(String),
+    /// Extract resources with the ship with this symbol, using the chosen survey
+    Extract(String),
+    /// Choose a good to jettison from the ship with this symbol
+    Jettison(String),
+    /// Refine the chosen product with the ship with this symbol
+    Refine(String),
+    /// Deliver the chosen ship's cargo for the contract with this id
+    DeliverContract(String),
+    /// Negotiate a new contract with the chosen ship
+    NegotiateContract,
+    /// Show the chosen agent, or add or register one
+    SwitchAgent,
+    /// Register a new agent, starting with the chosen faction
+    RegisterFaction,
+    /// Cancel the chosen running or waiting event
+    CancelEvent,
+}
+
+/// A yes/no dialog shown before an action that spends credits or can't be undone
+#[derive(Debug)]
+pub struct Confirm {
+    /// What is being confirmed
+    pub title: String,
+    /// Details of what will happen
+    pub message: Vec<String>,
+    /// Expected change in credits if confirmed, if any
+    pub credit_change: Option<i64>,
+    /// Event dispatched if confirmed
+    pub event: IoEvent,
+}
+
+/// Common interface of [`ListState`] and [`TableState`] for list navigation
+trait Selectable {
+    fn selected(&self) -> Option<usize>;
+    fn select(&mut self, index: Option<usize>);
+}
+
+impl Selectable for ListState {
+    fn selected(&self) -> Option<usize> {
+        self.selected()
+    }
+
+    fn select(&mut self, index: Option<usize>) {
+        self.select(index);
+    }
+}
+
+impl Selectable for TableState {
+    fn selected(&self) -> Option<usize> {
+        self.selected()
+    }
+
+    fn select(&mut self, index: Option<usize>) {
+        self.select(index);
+    }
+}
+
+/// Moves the selection of a list with `count` items by `delta`, wrapping around
+fn move_selection(list_state: &mut dyn Selectable, count: usize, delta: i32) {
+    if count == 0 {
+        list_state.select(None);
+    } else if let Some(selected) = list_state.selected() {
+        list_state.select(Some(
+            (i32::try_from(selected).unwrap_or(i32::MAX) + delta)
+                .rem_euclid(i32::try_from(count).unwrap_or(i32::MAX)) as usize,
+        ));
+    } else {
+        list_state.select(Some(0));
+    }
 }
 
 impl App {
     /// Constructs a new instance of [`App`].
     #[must_use]
-    pub fn new(io_sender: mpsc::Sender<IoEvent>) -> Self {
+    pub fn new(io_sender: mpsc::Sender<AgentEvent>) -> Self {
         Self {
             running: true,
             state: State::default(),
+            profile: None,
+            profiles: Vec::new(),
+            other_states: HashMap::new(),
             io_sender,
         }
     }
 
+    /// Runs `f` with the state of the given agent shown, putting back the shown state after.
+    /// Results for an agent that isn't shown update its kept state instead.
+    pub fn with_agent_state<R>(
+        &mut self,
+        agent: Option<&str>,
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> R {
+        let shown = self.profile.as_ref().map(|p| p.symbol.as_str());
+        let Some(agent) = agent.filter(|&agent| Some(agent) != shown) else {
+            return f(self);
+        };
+        let state = self.other_states.remove(agent).unwrap_or_default();
+        let shown_state = mem::replace(&mut self.state, state);
+        let result = f(self);
+        let state = mem::replace(&mut self.state, shown_state);
+        self.other_states.insert(agent.to_owned(), state);
+        result
+    }
+
+    /// Shows another agent, loading what we last knew of it the first time
+    pub async fn switch_agent(&mut self, symbol: &str) {
+        if self.profile.as_ref().is_some_and(|p| p.symbol == symbol) {
+            return;
+        }
+        let Some(profile) = self.profiles.iter().find(|p| p.symbol == symbol).cloned() else {
+            return;
+        };
+
+        let tab = self.state.tab;
+        let kept = self.other_states.remove(symbol);
+        let loaded = kept.is_some();
+        self.show_agent(profile, kept.unwrap_or_default());
+        if !loaded {
+            self.state.tab = tab;
+            self.load_agent().await;
+        }
+        self.notify(Severity::Info, format!("Switched to {symbol}"), Vec::new());
+    }
+
+    /// Shows an agent's state, keeping the state of the agent shown before
+    fn show_agent(&mut self, profile: Profile, state: State) {
+        let previous = mem::replace(&mut self.state, state);
+        let previous_profile = match self.profile.replace(profile) {
+            Some(previous_profile) => Some(previous_profile),
+            None => self.add_token_profile(&previous.agent.symbol),
+        };
+        if let Some(previous_profile) = previous_profile {
+            self.other_states.insert(previous_profile.symbol, previous);
+        }
+    }
+
+    /// Adds a profile for the agent `TOKEN` from the settings belongs to, so it can be switched
+    /// back to. Tokens that don't name their agent are taken to be for the agent shown with them.
+    fn add_token_profile(&mut self, shown_symbol: &str) -> Option<Profile> {
+        let token = SETTINGS.token.clone()?;
+        let symbol = profile::agent_symbol(&token)
+            .or_else(|| Some(shown_symbol.to_owned()).filter(|s| !s.is_empty()))?;
+        let profile = Profile { symbol, token };
+        self.add_profile(profile.clone());
+        Some(profile)
+    }
+
+    /// Adds a profile, replacing the token of a known agent
+    fn add_profile(&mut self, profile: Profile) {
+        if let Some(known) = self
+            .profiles
+            .iter_mut()
+            .find(|p| p.symbol == profile.symbol)
+        {
+            *known = profile;
+        } else {
+            self.profiles.push(profile);
+            self.profiles.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        }
+    }
+
+    /// Shows the shown agent's recorded data and fetches it again.
+    /// Without a profile, that is the agent that was used last.
+    pub async fn load_agent(&mut self) {
+        self.load_cached_agent().await;
+        self.update_agent_tab().await;
+        self.update_fleet_tab().await;
+    }
+
+    /// Shows the shown agent's recorded data, without fetching it again
+    pub async fn load_cached_agent(&mut self) {
+        let cache = match &self.profile {
+            Some(profile) => db_util::load_cache(&profile.symbol).await,
+            None => match db_util::load_latest_agent_symbol().await {
+                Ok(Some(symbol)) => db_util::load_cache(&symbol).await,
+                other => other.map(|_| None),
+            },
+        };
+        match cache {
+            Ok(Some(cache)) => self.hydrate(cache),
+            Ok(None) => {}
+            Err(e) => {
+                error!("Error loading cached data: {e:#?}");
+                self.notify_error(&e);
+            }
+        }
+    }
+
+    /// Opens a picker to switch to another agent or add one
+    pub fn pick_agent(&mut self) {
+        self.open_picker("Switch agent", PickerAction::SwitchAgent);
+    }
+
+    /// Adds an agent by its token, records the token and switches to it
+    async fn add_agent(&mut self, token: &str) {
+        let Some(symbol) = profile::agent_symbol(token) else {
+            self.notify(
+                Severity::Warning,
+                "Not an agent token".to_owned(),
+                vec!["Tokens are given when an agent is registered".to_owned()],
+            );
+            return;
+        };
+        if let Err(e) = db_util::save_agent_token(&symbol, token).await {
+            error!("Error saving agent token: {e:#?}");
+            self.notify_error(&e);
+            return;
+        }
+        self.add_profile(Profile {
+            symbol: symbol.clone(),
+            token: token.to_owned(),
+        });
+        self.switch_agent(&symbol).await;
+    }
+
+    /// Asks whether to archive data from before the server reset and register a new agent
+    pub fn offer_reset_archive(&mut self, previous: String, current: String) {
+        let mut message = vec![
+            format!("The server reset on {current}, our data and tokens are from {previous}"),
+            "Archive them and register a new agent?".to_owned(),
+        ];
+        if SETTINGS.token.is_some() || !SETTINGS.agents.is_empty() {
+            message.push("Tokens in the config file or TOKEN are old too, replace them".to_owned());
+        }
+        self.open_confirm(
+            "Server reset",
+            message,
+            None,
+            IoEvent::ArchiveReset { previous, current },
+        );
+    }
+
+    /// Forgets every agent once their data is archived, and starts registering a new one
+    pub fn start_new_reset(&mut self, previous: &str) {
+        self.profiles.clear();
+        self.profile = None;
+        self.other_states.clear();
+        self.state = State {
+            tab: self.state.tab,
+            ..State::default()
+        };
+        self.notify(
+            Severity::Info,
+            format!("Archived data from the reset on {previous}"),
+            Vec::new(),
+        );
+        self.open_picker("Starting faction", PickerAction::RegisterFaction);
+    }
+
+    /// Starts registering a new agent, by choosing the faction it starts with.
+    /// The picker lists the factions known so far, and is filled in once they're fetched.
+    pub async fn register_agent(&mut self) {
+        self.dispatch(IoEvent::UpdateFactions).await;
+        self.open_picker("Starting faction", PickerAction::RegisterFaction);
+    }
+
+    /// Shows an agent that was just registered, starting with what registering it returned
+    pub fn add_registered_agent(
+        &mut self,
+        profile: Profile,
+        agent: Agent,
+        ship: Ship,
+        contract: Contract,
+    ) {
+        let symbol = profile.symbol.clone();
+        let mut state = State {
+            tab: self.state.tab,
+            factions: self.state.factions.clone(),
+            agent,
+            ships: vec![ship],
+            contracts: vec![contract],
+            ..State::default()
+        };
+        state.ships_list_state.select(Some(0));
+        state.contracts_list_state.select(Some(0));
+
+        self.add_profile(profile.clone());
+        self.show_agent(profile, state);
+        self.notify(
+            Severity::Info,
+            format!("Registered {symbol}"),
+            vec!["Its token is recorded, press a to switch agents".to_owned()],
+        );
+    }
+
     pub async fn dispatch(&mut self, action: IoEvent) {
-        if let Err(e) = self.io_sender.send(action).await {
+        if self.state.offline && !action.is_refresh() {
+            self.notify(
+                Severity::Warning,
+                "Offline, cached data is read-only. Refresh to reconnect".to_owned(),
+                Vec::new(),
+            );
+            return;
+        }
+        // Without a token, e.g. once a reset is archived, there is no agent to act as
+        if action.needs_agent() && self.profile.is_none() && profile::current_token().is_none() {
+            if self.state.picker.is_none() {
+                Box::pin(self.register_agent()).await;
+            }
+            return;
+        }
+        // These don't act as the shown agent, and change which agents there are
+        let profile = if matches!(
+            action,
+            IoEvent::RegisterAgent { .. } | IoEvent::ArchiveReset { .. }
+        ) {
+            None
+        } else {
+            self.profile.clone()
+        };
+        let event = AgentEvent {
+            profile,
+            event: action,
+        };
+        if let Err(e) = self.io_sender.send(event).await {
             error!("Error from dispatch {e}");
         }
     }
@@ -72,42 +684,1375 @@ impl App {
         self.dispatch(IoEvent::UpdateFactions).await;
     }
 
-    pub async fn accept_or_fulfull_contract(&mut self) {
-        if let Some(index) = self.state.contracts_list_state.selected() {
-            let contract = &self.state.contracts[index];
-            if !contract.accepted {
-                self.dispatch(IoEvent::AcceptContract(contract.id.clone()))
+    pub async fn update_fleet_tab(&mut self) {
+        self.dispatch(IoEvent::UpdateShips).await;
+    }
+
+    /// Shows data recorded by an earlier session until it is fetched again
+    pub fn hydrate(&mut self, cache: Cache) {
+        let state = &mut self.state;
+        state.agent = cache.agent.data;
+        state
+            .stale_since
+            .insert(Section::Agent, cache.agent.updated_at);
+        if let Some(contracts) = cache.contracts {
+            state.contracts = contracts.data;
+            state.contracts_list_state.select(Some(0));
+            state
+                .stale_since
+                .insert(Section::Contracts, contracts.updated_at);
+        }
+        state.factions = cache.factions;
+        if let Some(ships) = cache.ships {
+            state.ships = ships.data;
+            state.ships_list_state.select(Some(0));
+            state.stale_since.insert(Section::Ships, ships.updated_at);
+        }
+        state.systems = cache.systems;
+        if let Some(waypoints) = cache.waypoints {
+            st_util::waypoint_system_symbol(&state.agent.headquarters)
+                .clone_into(&mut state.system_symbol);
+            state.waypoints = waypoints.data;
+            state
+                .stale_since
+                .insert(Section::Waypoints, waypoints.updated_at);
+            self.sort_waypoints();
+            self.state.waypoints_table_state.select(Some(0));
+        }
+    }
+
+    /// Marks a [`Section`] as showing data fresh from the API
+    pub fn mark_fresh(&mut self, section: Section) {
+        self.state.stale_since.remove(&section);
+    }
+
+    /// Switches to the systems tab, showing the headquarters system if no system is shown yet
+    pub async fn open_systems_tab(&mut self) {
+        self.state.tab = Tab::Systems;
+        if self.state.system_symbol.is_empty()
+            || self.state.stale_since.contains_key(&Section::Waypoints)
+        {
+            self.update_systems_tab().await;
+        }
+    }
+
+    /// Shows the system containing the agent's headquarters on the systems page
+    pub async fn view_headquarters_system(&mut self) {
+        if !self.state.agent.headquarters.is_empty() {
+            let symbol = st_util::waypoint_system_symbol(&self.state.agent.headquarters).to_owned();
+            self.view_system(symbol).await;
+        }
+    }
+
+    /// Shows the given system on the systems page and loads its data
+    pub async fn view_system(&mut self, symbol: String) {
+        if symbol != self.state.system_symbol {
+            self.state.waypoints.clear();
+            self.state.waypoints_table_state.select(None);
+        }
+        self.state.system_symbol = symbol;
+        self.state.map.galaxy = false;
+        self.reset_map();
+        self.update_systems_tab().await;
+    }
+
+    pub async fn update_systems_tab(&mut self) {
+        if self.state.system_symbol.is_empty() {
+            if self.state.agent.headquarters.is_empty() {
+                return;
+            }
+            st_util::waypoint_system_symbol(&self.state.agent.headquarters)
+                .clone_into(&mut self.state.system_symbol);
+        }
+        let symbol = self.state.system_symbol.clone();
+        self.dispatch(IoEvent::UpdateSystem(symbol.clone())).await;
+        self.dispatch(IoEvent::UpdateWaypoints(symbol)).await;
+    }
+
+    /// Returns the [`System`] shown on the systems page, if it has been loaded
+    #[must_use]
+    pub fn current_system(&self) -> Option<&System> {
+        self.state
+            .systems
+            .iter()
+            .find(|s| s.symbol == self.state.system_symbol)
+    }
+
+    /// Cycles the column the waypoints table is sorted by
+    pub fn cycle_waypoints_sort(&mut self) {
+        let mut sorts = WaypointSort::iter().cycle();
+        sorts.find(|&sort| sort == self.state.waypoints_sort);
+        self.state.waypoints_sort = sorts.next().unwrap_or(WaypointSort::Symbol);
+        self.sort_waypoints();
+    }
+
+    /// Flips the order the waypoints table is sorted in
+    pub fn reverse_waypoints_sort(&mut self) {
+        self.state.waypoints_sort_descending = !self.state.waypoints_sort_descending;
+        self.sort_waypoints();
+    }
+
+    /// Sorts the current waypoints, keeping the same waypoint selected
+    pub fn sort_waypoints(&mut self) {
+        let state = &mut self.state;
+        let selected = state
+            .waypoints_table_state
+            .selected()
+            .and_then(|index| state.waypoints.get(index))
+            .map(|w| w.symbol.clone());
+
+        let sort = state.waypoints_sort;
+        let descending = state.waypoints_sort_descending;
+        state.waypoints.sort_by(|a, b| {
+            let ordering = match sort {
+                WaypointSort::Symbol => a.symbol.cmp(&b.symbol),
+                WaypointSort::Type => a.r#type.cmp(&b.r#type),
+                WaypointSort::Distance => (i64::from(a.x).pow(2) + i64::from(a.y).pow(2))
+                    .cmp(&(i64::from(b.x).pow(2) + i64::from(b.y).pow(2))),
+                WaypointSort::Faction => a
+                    .faction
+                    .as_ref()
+                    .map(|f| &f.symbol)
+                    .cmp(&b.faction.as_ref().map(|f| &f.symbol)),
+            }
+            .then_with(|| a.symbol.cmp(&b.symbol));
+            if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+
+        if let Some(symbol) = selected {
+            let index = state.waypoints.iter().position(|w| w.symbol == symbol);
+            state.waypoints_table_state.select(index);
+        }
+    }
+
+    /// Distance from the origin that the map must show to fit everything in its current view
+    #[must_use]
+    pub fn map_extent(&self) -> f64 {
+        let max_coordinate = if self.state.map.galaxy {
+            self.state
+                .systems
+                .iter()
+                .map(|s| s.x.abs().max(s.y.abs()))
+                .max()
+        } else {
+            self.state
+                .waypoints
+                .iter()
+                .map(|w| w.x.abs().max(w.y.abs()))
+                .max()
+        };
+        // Leave a margin so nothing sits right on the border
+        (f64::from(max_coordinate.unwrap_or(0)) * 1.1).max(10.0)
+    }
+
+    /// Whether arrow keys currently pan the map
+    #[must_use]
+    pub fn map_focused(&self) -> bool {
+        self.state.tab == Tab::Systems && self.state.map.focused
+    }
+
+    pub fn toggle_map_focus(&mut self) {
+        self.state.map.focused = !self.state.map.focused;
+    }
+
+    /// Moves the map view by the given fraction of the visible area
+    pub fn pan_map(&mut self, dx: f64, dy: f64) {
+        let step = self.map_extent() / self.state.map.zoom;
+        self.state.map.center.0 += dx * step;
+        self.state.map.center.1 += dy * step;
+    }
+
+    /// Multiplies the zoom level of the map by the given factor
+    pub fn zoom_map(&mut self, factor: f64) {
+        self.state.map.zoom = (self.state.map.zoom * factor).clamp(0.5, 1000.0);
+    }
+
+    /// Returns the map to its default view, fitting everything
+    pub fn reset_map(&mut self) {
+        self.state.map = MapState {
+            focused: self.state.map.focused,
+            galaxy: self.state.map.galaxy,
+            ..MapState::default()
+        };
+    }
+
+    /// Switches the map between the current system and the whole galaxy
+    pub async fn toggle_galaxy_map(&mut self) {
+        self.state.map.galaxy = !self.state.map.galaxy;
+        self.reset_map();
+        if self.state.map.galaxy {
+            // Center on the current system instead of the middle of the galaxy
+            if let Some(system) = self.current_system() {
+                self.state.map.center = (f64::from(system.x), f64::from(system.y));
+                self.state.map.zoom = 10.0;
+            }
+            if !self.state.all_systems_loaded {
+                self.dispatch(IoEvent::UpdateSystems).await;
+            }
+        }
+    }
+
+    /// Shows the system closest to the center of the galaxy map on the systems page
+    pub async fn view_system_at_map_center(&mut self) {
+        let (x, y) = self.state.map.center;
+        let closest = self
+            .state
+            .systems
+            .iter()
+            .map(|s| {
+                let distance = (f64::from(s.x) - x).hypot(f64::from(s.y) - y);
+                (distance, &s.symbol)
+            })
+            .min_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, symbol)| symbol.clone());
+        if let Some(symbol) = closest {
+            self.view_system(symbol).await;
+        }
+    }
+
+    /// Opens a text prompt for the given action
+    pub fn open_prompt(&mut self, title: &str, action: PromptAction) {
+        self.open_prompt_with(title, action, String::new());
+    }
+
+    /// Opens a text prompt for the given action, starting with some input already entered
+    pub fn open_prompt_with(&mut self, title: &str, action: PromptAction, input: String) {
+        self.state.prompt = Some(Prompt {
+            title: title.to_owned(),
+            input,
+            action,
+        });
+    }
+
+    /// Closes the current prompt and acts on its input
+    pub async fn submit_prompt(&mut self) {
+        let Some(prompt) = self.state.prompt.take() else {
+            return;
+        };
+        let input = prompt.input.trim().to_uppercase();
+        if input.is_empty() {
+            return;
+        }
+        match prompt.action {
+            // Tokens are case sensitive
+            PromptAction::AddAgent => self.add_agent(prompt.input.trim()).await,
+            PromptAction::GotoSystem => self.view_system(input).await,
+            PromptAction::PurchaseCargo { ship, good } => {
+                if let Some(units) = self.parse_units(&input) {
+                    let price = self.market_price(&good, |g| g.purchase_price);
+                    self.open_confirm(
+                        "Buy cargo",
+                        vec![format!("Buy {units} {good} with {ship}")],
+                        Some(-i64::from(units) * price),
+                        IoEvent::PurchaseCargo { ship, good, units },
+                    );
+                }
+            }
+            PromptAction::SellCargo { ship, good } => {
+                if let Some(units) = self.parse_units(&input) {
+                    let price = self.market_price(&good, |g| g.sell_price);
+                    self.open_confirm(
+                        "Sell cargo",
+                        vec![format!("Sell {units} {good} from {ship}")],
+                        Some(i64::from(units) * price),
+                        IoEvent::SellCargo { ship, good, units },
+                    );
+                }
+            }
+            PromptAction::Jettison { ship, good } => {
+                let Some(units) = self.parse_units(&input) else {
+                    return;
+                };
+                let held = self.held_units(&ship, &good);
+                if units > held {
+                    self.notify(
+                        Severity::Warning,
+                        format!("{ship} doesn't have {units} {good}"),
+                        vec![format!("It holds {held}")],
+                    );
+                } else {
+                    self.open_confirm(
+                        "Jettison cargo",
+                        vec![
+                            format!("Jettison {units} {good} from {ship}"),
+                            "The cargo will be lost".to_owned(),
+                        ],
+                        None,
+                        IoEvent::Jettison { ship, good, units },
+                    );
+                }
+            }
+            PromptAction::RateLimits => self.set_rate_limits(&input),
+            PromptAction::RegisterAgent { faction } => {
+                if (3..=14).contains(&input.chars().count()) {
+                    let event = IoEvent::RegisterAgent {
+                        symbol: input,
+                        faction,
+                    };
+                    self.dispatch(event).await;
+                } else {
+                    self.notify(
+                        Severity::Warning,
+                        format!("{input} isn't a call sign"),
+                        vec!["Call signs are 3 to 14 characters long".to_owned()],
+                    );
+                }
+            }
+        }
+    }
+
+    /// Parses a positive number of units entered in a prompt, notifying the user if it isn't one
+    fn parse_units(&mut self, input: &str) -> Option<i32> {
+        match input.parse::<i32>() {
+            Ok(units) if units > 0 => Some(units),
+            _ => {
+                self.notify(
+                    Severity::Warning,
+                    format!("{input} isn't a number of units"),
+                    vec!["Enter a whole number greater than 0".to_owned()],
+                );
+                None
+            }
+        }
+    }
+
+    /// Opens a picker to choose a running or waiting event to cancel
+    pub fn pick_event_to_cancel(&mut self) {
+        if runner::activity().is_empty() {
+            self.notify(Severity::Info, "Nothing to cancel".to_owned(), Vec::new());
+        } else {
+            self.open_picker("Cancel", PickerAction::CancelEvent);
+        }
+    }
+
+    /// Running and waiting events that can be cancelled, oldest first
+    fn cancellable_events() -> Vec<PickerItem> {
+        runner::activity()
+            .into_iter()
+            .map(|status| {
+                let progress = if status.started_at.is_some() {
+                    let elapsed = chrono::Duration::from_std(status.elapsed())
+                        .unwrap_or_else(|_| chrono::Duration::zero());
+                    format!("running {}", st_util::format_duration(elapsed))
+                } else {
+                    "waiting".to_owned()
+                };
+                let agent = status.agent.map_or(String::new(), |a| format!("{a}: "));
+                PickerItem {
+                    value: status.id.to_string(),
+                    label: format!("{agent}{} ({progress})", status.event),
+                }
+            })
+            .collect()
+    }
+
+    /// Cancels the event with this id, if it hasn't finished
+    fn cancel_event(&mut self, id: u64) {
+        let message = match runner::cancel(id) {
+            Some(event) => format!("Cancelled {event}"),
+            None => "Already finished".to_owned(),
+        };
+        self.notify(Severity::Info, message, Vec::new());
+    }
+
+    /// Prompts for new rate limits, starting with the current ones
+    pub fn prompt_rate_limits(&mut self) {
+        let limits = rate_limit::limits();
+        self.open_prompt_with(
+            "Rate limit (burst, per second)",
+            PromptAction::RateLimits,
+            format!("{}, {}", limits.burst, limits.per_second),
+        );
+    }
+
+    /// Sets the rate limits from a burst and per-second limit separated by a comma or space
+    fn set_rate_limits(&mut self, input: &str) {
+        let numbers: Vec<u16> = input
+            .split([',', ' '])
+            .filter(|s| !s.is_empty())
+            .filter_map(|s| s.parse().ok())
+            .collect();
+        let &[burst, per_second] = numbers.as_slice() else {
+            self.notify(
+                Severity::Warning,
+                format!("Expected a burst and per-second limit, got {input}"),
+                Vec::new(),
+            );
+            return;
+        };
+        if burst == 0 || per_second == 0 {
+            self.notify(
+                Severity::Warning,
+                "Rate limits must be above zero".to_owned(),
+                Vec::new(),
+            );
+            return;
+        }
+        rate_limit::set_limits(RateLimits { burst, per_second });
+        self.notify(
+            Severity::Info,
+            format!("Rate limit set to {burst} at once, {per_second} per second"),
+            Vec::new(),
+        );
+    }
+
+    /// Shows the market at the given waypoint on the market page and loads it
+    pub async fn view_market(&mut self, waypoint_symbol: String) {
+        if waypoint_symbol != self.state.market_symbol {
+            self.state.market = None;
+            self.state.market_table_state.select(None);
+            self.state.market_history.clear();
+        }
+        self.state.market_symbol = waypoint_symbol;
+        self.state.tab = Tab::Market;
+        self.update_market_tab().await;
+    }
+
+    pub async fn update_market_tab(&mut self) {
+        if !self.state.market_symbol.is_empty() {
+            let symbol = self.state.market_symbol.clone();
+            self.dispatch(IoEvent::UpdateMarket(symbol)).await;
+        }
+    }
+
+    /// Shows the market at the waypoint selected on the systems page, if it has one
+    pub async fn view_selected_waypoint_market(&mut self) {
+        let waypoint = self
+            .state
+            .waypoints_table_state
+            .selected()
+            .and_then(|index| self.state.waypoints.get(index));
+        if let Some(waypoint) = waypoint {
+            if waypoint
+                .traits
+                .iter()
+                .any(|t| t.symbol == waypoint_trait::Symbol::Marketplace)
+            {
+                let symbol = waypoint.symbol.clone();
+                self.view_market(symbol).await;
+            }
+        }
+    }
+
+    /// Shows the market where the ship selected on the fleet page is
+    pub async fn view_selected_ship_market(&mut self) {
+        if let Some(ship) = self.selected_ship() {
+            if ship.nav.status != ShipNavStatus::InTransit {
+                let symbol = ship.nav.waypoint_symbol.clone();
+                self.view_market(symbol).await;
+            }
+        }
+    }
+
+    /// Returns the ship that trades on the market page: the ship selected on the fleet page
+    /// if it is docked at the market, otherwise any ship docked there
+    #[must_use]
+    pub fn market_ship(&self) -> Option<&Ship> {
+        let docked_here = |ship: &&Ship| {
+            ship.nav.status == ShipNavStatus::Docked
+                && ship.nav.waypoint_symbol == self.state.market_symbol
+        };
+        self.selected_ship()
+            .filter(docked_here)
+            .or_else(|| self.state.ships.iter().find(docked_here))
+    }
+
+    /// Returns the trade good selected on the market page, if prices are visible
+    #[must_use]
+    pub fn selected_trade_good(&self) -> Option<&MarketTradeGood> {
+        let trade_goods = self.state.market.as_ref()?.trade_goods.as_ref()?;
+        trade_goods.get(self.state.market_table_state.selected()?)
+    }
+
+    /// Returns the symbol of the good selected on the market page, even if prices aren't visible
+    #[must_use]
+    pub fn selected_market_good(&self) -> Option<String> {
+        let market = self.state.market.as_ref()?;
+        let index = self.state.market_table_state.selected()?;
+        match &market.trade_goods {
+            Some(trade_goods) => trade_goods.get(index).map(|g| g.symbol.clone()),
+            None => market
+                .imports
+                .iter()
+                .chain(&market.exports)
+                .chain(&market.exchange)
+                .nth(index)
+                .map(|g| g.symbol.to_string()),
+        }
+    }
+
+    /// Price of a good on the shown market, per unit, or 0 if it isn't known
+    fn market_price(&self, good: &str, price: impl Fn(&MarketTradeGood) -> i32) -> i64 {
+        self.state
+            .market
+            .as_ref()
+            .and_then(|m| m.trade_goods.as_ref())
+            .and_then(|goods| goods.iter().find(|g| g.symbol == good))
+            .map_or(0, |g| i64::from(price(g)))
+    }
+
+    /// Number of rows in the table of goods on the market page
+    fn market_row_count(&self) -> usize {
+        self.state.market.as_ref().map_or(0, |market| {
+            market.trade_goods.as_ref().map_or(
+                market.imports.len() + market.exports.len() + market.exchange.len(),
+                Vec::len,
+            )
+        })
+    }
+
+    /// Prompts for how many units of the selected good the market ship should buy
+    pub fn prompt_purchase_cargo(&mut self) {
+        let (Some(ship), Some(good)) = (self.market_ship(), self.selected_trade_good()) else {
+            return;
+        };
+        let space = ship.cargo.capacity - ship.cargo.units;
+        let units = space.min(good.trade_volume).max(0);
+        let title = format!(
+            "Buy {} with {} ({} each, {space} space)",
+            good.symbol, ship.symbol, good.purchase_price
+        );
+        let action = PromptAction::PurchaseCargo {
+            ship: ship.symbol.clone(),
+            good: good.symbol.clone(),
+        };
+        self.open_prompt_with(&title, action, units.to_string());
+    }
+
+    /// Prompts for how many units of the selected good the market ship should sell
+    pub fn prompt_sell_cargo(&mut self) {
+        let (Some(ship), Some(good)) = (self.market_ship(), self.selected_trade_good()) else {
+            return;
+        };
+        let held = ship
+            .cargo
+            .inventory
+            .iter()
+            .find(|item| item.symbol == good.symbol)
+            .map_or(0, |item| item.units);
+        if held == 0 {
+            return;
+        }
+        let title = format!(
+            "Sell {} from {} ({} each, {held} held)",
+            good.symbol, ship.symbol, good.sell_price
+        );
+        let action = PromptAction::SellCargo {
+            ship: ship.symbol.clone(),
+            good: good.symbol.clone(),
+        };
+        self.open_prompt_with(&title, action, held.to_string());
+    }
+
+    /// Shows the shipyard at the given waypoint on the shipyard page and loads it
+    pub async fn view_shipyard(&mut self, waypoint_symbol: String) {
+        if waypoint_symbol != self.state.shipyard_symbol {
+            self.state.shipyard = None;
+            self.state.shipyard_table_state.select(None);
+        }
+        self.state.shipyard_symbol = waypoint_symbol;
+        self.state.tab = Tab::Shipyard;
+        self.update_shipyard_tab().await;
+    }
+
+    pub async fn update_shipyard_tab(&mut self) {
+        if !self.state.shipyard_symbol.is_empty() {
+            let symbol = self.state.shipyard_symbol.clone();
+            self.dispatch(IoEvent::UpdateShipyard(symbol)).await;
+        }
+    }
+
+    /// Shows the shipyard at the waypoint selected on the systems page, if it has one
+    pub async fn view_selected_waypoint_shipyard(&mut self) {
+        let waypoint = self
+            .state
+            .waypoints_table_state
+            .selected()
+            .and_then(|index| self.state.waypoints.get(index));
+        if let Some(waypoint) = waypoint {
+            if waypoint
+                .traits
+                .iter()
+                .any(|t| t.symbol == waypoint_trait::Symbol::Shipyard)
+            {
+                let symbol = waypoint.symbol.clone();
+                self.view_shipyard(symbol).await;
+            }
+        }
+    }
+
+    /// Shows the shipyard where the ship selected on the fleet page is
+    pub async fn view_selected_ship_shipyard(&mut self) {
+        if let Some(ship) = self.selected_ship() {
+            if ship.nav.status != ShipNavStatus::InTransit {
+                let symbol = ship.nav.waypoint_symbol.clone();
+                self.view_shipyard(symbol).await;
+            }
+        }
+    }
+
+    /// Number of rows in the table of ships on the shipyard page
+    fn shipyard_row_count(&self) -> usize {
+        self.state.shipyard.as_ref().map_or(0, |shipyard| {
+            shipyard
+                .ships
+                .as_ref()
+                .map_or(shipyard.ship_types.len(), Vec::len)
+        })
+    }
+
+    /// Returns the ship for sale selected on the shipyard page, if prices are visible
+    #[must_use]
+    pub fn selected_shipyard_ship(&self) -> Option<&ShipyardShip> {
+        let ships = self.state.shipyard.as_ref()?.ships.as_ref()?;
+        ships.get(self.state.shipyard_table_state.selected()?)
+    }
+
+    /// Asks for confirmation before buying the ship selected on the shipyard page
+    pub fn confirm_purchase_ship(&mut self) {
+        let Some(ship) = self.selected_shipyard_ship() else {
+            return;
+        };
+        let Some(ship_type) = ship.r#type else {
+            return;
+        };
+        let message = vec![format!(
+            "Buy {} at {}",
+            ship.name, self.state.shipyard_symbol
+        )];
+        let credit_change = -i64::from(ship.purchase_price);
+        let event = IoEvent::PurchaseShip {
+            ship_type,
+            waypoint: self.state.shipyard_symbol.clone(),
+        };
+        self.open_confirm("Purchase ship", message, Some(credit_change), event);
+    }
+
+    /// Switches to the trade tab, finding routes for the ship selected on the fleet page
+    pub async fn open_trade_tab(&mut self) {
+        self.state.tab = Tab::Trade;
+        self.update_trade_tab().await;
+    }
+
+    /// Finds trade routes for the ship selected on the fleet page from the latest recorded prices
+    pub async fn update_trade_tab(&mut self) {
+        let Some(ship) = self.selected_ship() else {
+            return;
+        };
+        let (ship_symbol, system_symbol) = (ship.symbol.clone(), ship.nav.system_symbol.clone());
+        if self
+            .state
+            .trade_data
+            .as_ref()
+            .is_some_and(|data| data.system_symbol != system_symbol)
+        {
+            self.state.trade_data = None;
+        }
+        if ship_symbol != self.state.trade_ship {
+            self.state.trade_ship = ship_symbol;
+            self.state.trade_table_state.select(None);
+        }
+        self.update_trade_routes();
+        self.dispatch(IoEvent::UpdateTradeData(system_symbol)).await;
+    }
+
+    /// Plans trade routes for the trade ship with the recorded markets
+    pub fn update_trade_routes(&mut self) {
+        let routes = match (self.ship(&self.state.trade_ship), &self.state.trade_data) {
+            (Some(ship), Some(data)) => {
+                trade::find_routes(ship, data, self.state.trade_flight_mode)
+            }
+            _ => Vec::new(),
+        };
+        let selected = match self.state.trade_table_state.selected() {
+            _ if routes.is_empty() => None,
+            Some(index) => Some(index.min(routes.len() - 1)),
+            None => Some(0),
+        };
+        self.state.trade_routes = routes;
+        self.state.trade_table_state.select(selected);
+    }
+
+    /// Cycles the flight mode trade routes are planned with
+    pub fn cycle_trade_flight_mode(&mut self) {
+        self.state.trade_flight_mode = match self.state.trade_flight_mode {
+            ShipNavFlightMode::Cruise => ShipNavFlightMode::Burn,
+            ShipNavFlightMode::Burn => ShipNavFlightMode::Drift,
+            ShipNavFlightMode::Drift => ShipNavFlightMode::Stealth,
+            ShipNavFlightMode::Stealth => ShipNavFlightMode::Cruise,
+        };
+        self.update_trade_routes();
+    }
+
+    /// Returns the route selected on the trade page, if any
+    #[must_use]
+    pub fn selected_trade_route(&self) -> Option<&TradeRoute> {
+        self.state
+            .trade_routes
+            .get(self.state.trade_table_state.selected()?)
+    }
+
+    /// Asks for confirmation before the trade ship runs the selected route
+    pub fn confirm_trade_route(&mut self) {
+        let Some(route) = self.selected_trade_route() else {
+            return;
+        };
+        let mut message = vec![format!(
+            "Buy {} {} at {} for {} each",
+            route.units, route.good, route.buy_at.symbol, route.buy_price
+        )];
+        if let Some(jump) = &route.jump {
+            message.push(format!(
+                "Jump from {} to {}",
+                jump.from_gate.symbol, jump.system_symbol
+            ));
+        }
+        message.extend([
+            format!(
+                "Sell at {} for {} each",
+                route.sell_at.symbol, route.sell_price
+            ),
+            format!(
+                "Flying {} using about {} fuel, taking about {}",
+                route.flight_mode.to_string(),
+                route.fuel,
+                st_util::format_duration(chrono::Duration::seconds(route.seconds))
+            ),
+            format!("Expected profit: {:+}", route.profit),
+        ]);
+        let credit_change = -i64::from(route.units) * i64::from(route.buy_price);
+        let event = IoEvent::RunTradeRoute {
+            ship: self.state.trade_ship.clone(),
+            route: route.clone(),
+        };
+        let title = format!("Trade with {}", self.state.trade_ship);
+        self.open_confirm(&title, message, Some(credit_change), event);
+    }
+
+    /// Opens a confirmation dialog that dispatches the given event if confirmed
+    pub fn open_confirm(
+        &mut self,
+        title: &str,
+        message: Vec<String>,
+        credit_change: Option<i64>,
+        event: IoEvent,
+    ) {
+        self.state.confirm = Some(Confirm {
+            title: title.to_owned(),
+            message,
+            credit_change,
+            event,
+        });
+    }
+
+    /// Closes the open confirmation dialog and carries out its action
+    pub async fn submit_confirm(&mut self) {
+        if let Some(confirm) = self.state.confirm.take() {
+            self.dispatch(confirm.event).await;
+        }
+    }
+
+    /// Returns the ship selected on the fleet page, if any
+    #[must_use]
+    pub fn selected_ship(&self) -> Option<&Ship> {
+        self.state
+            .ships_list_state
+            .selected()
+            .and_then(|index| self.state.ships.get(index))
+    }
+
+    /// Returns the ship with the given symbol, if it is known
+    #[must_use]
+    pub fn ship(&self, symbol: &str) -> Option<&Ship> {
+        self.state.ships.iter().find(|s| s.symbol == symbol)
+    }
+
+    /// Returns the ship with the given symbol, if it is known
+    pub fn ship_mut(&mut self, symbol: &str) -> Option<&mut Ship> {
+        self.state.ships.iter_mut().find(|s| s.symbol == symbol)
+    }
+
+    pub async fn orbit_selected_ship(&mut self) {
+        if let Some(ship) = self.selected_ship() {
+            let symbol = ship.symbol.clone();
+            self.dispatch(IoEvent::OrbitShip(symbol)).await;
+        }
+    }
+
+    pub async fn dock_selected_ship(&mut self) {
+        if let Some(ship) = self.selected_ship() {
+            let symbol = ship.symbol.clone();
+            self.dispatch(IoEvent::DockShip(symbol)).await;
+        }
+    }
+
+    /// Opens a picker to choose a destination for the selected ship within its system
+    pub async fn pick_navigation_destination(&mut self) {
+        let Some(ship) = self.selected_ship() else {
+            return;
+        };
+        let ship_symbol = ship.symbol.clone();
+        let system_symbol = ship.nav.system_symbol.clone();
+
+        // The picker lists the waypoints of the system shown on the systems page
+        if system_symbol != self.state.system_symbol {
+            self.view_system(system_symbol).await;
+        }
+        let action = PickerAction::NavigateShip(ship_symbol.clone());
+        self.open_picker(&format!("Navigate {ship_symbol} to"), action);
+    }
+
+    /// Opens a picker to choose the flight mode of the selected ship
+    pub fn pick_flight_mode(&mut self) {
+        if let Some(ship) = self.selected_ship() {
+            let action = PickerAction::SetFlightMode(ship.symbol.clone());
+            let title = format!("Flight mode for {}", ship.symbol);
+            self.open_picker(&title, action);
+        }
+    }
+
+    /// Returns the unexpired surveys of the given waypoint
+    pub fn surveys_at<'a>(&'a self, waypoint_symbol: &'a str) -> impl Iterator<Item = &'a Survey> {
+        self.state
+            .surveys
+            .iter()
+            .filter(move |s| s.symbol == waypoint_symbol)
+    }
+
+    /// Adds a message to a ship's activity log
+    pub fn log_activity(&mut self, ship_symbol: &str, message: String) {
+        let log = self
+            .state
+            .activity_logs
+            .entry(ship_symbol.to_owned())
+            .or_default();
+        log.push(LogEntry {
+            time: Utc::now(),
+            message,
+        });
+        if log.len() > MAX_LOG_ENTRIES {
+            log.drain(..log.len() - MAX_LOG_ENTRIES);
+        }
+    }
+
+    /// Scrolls the activity log on the fleet page back (positive) or forward (negative)
+    pub fn scroll_activity_log(&mut self, delta: i32) {
+        let len = self
+            .selected_ship()
+            .and_then(|ship| self.state.activity_logs.get(&ship.symbol))
+            .map_or(0, Vec::len);
+        let scroll = i64::try_from(self.state.activity_scroll).unwrap_or(i64::MAX);
+        self.state.activity_scroll = usize::try_from((scroll + i64::from(delta)).max(0))
+            .unwrap_or(0)
+            .min(len);
+    }
+
+    pub async fn survey_with_selected_ship(&mut self) {
+        if let Some(ship) = self.selected_ship() {
+            let symbol = ship.symbol.clone();
+            self.dispatch(IoEvent::CreateSurvey(symbol)).await;
+        }
+    }
+
+    /// Extracts resources with the selected ship, first opening a picker to choose a survey
+    /// if there are any for its location
+    pub async fn extract_with_selected_ship(&mut self) {
+        let Some(ship) = self.selected_ship() else {
+            return;
+        };
+        let symbol = ship.symbol.clone();
+        if self.surveys_at(&ship.nav.waypoint_symbol).next().is_some() {
+            self.open_picker(
+                &format!("Extract with {symbol} using"),
+                PickerAction::Extract(symbol),
+            );
+        } else {
+            self.dispatch(IoEvent::ExtractResources {
+                ship: symbol,
+                survey: None,
+            })
+            .await;
+        }
+    }
+
+    /// Opens a picker to choose a good to jettison from the selected ship
+    pub fn pick_jettison_cargo(&mut self) {
+        if let Some(ship) = self.selected_ship() {
+            let action = PickerAction::Jettison(ship.symbol.clone());
+            let title = format!("Jettison from {}", ship.symbol);
+            self.open_picker(&title, action);
+        }
+    }
+
+    /// Opens a picker to choose what the selected ship should refine
+    pub fn pick_refine_product(&mut self) {
+        if let Some(ship) = self.selected_ship() {
+            let action = PickerAction::Refine(ship.symbol.clone());
+            let title = format!("Refine with {}", ship.symbol);
+            self.open_picker(&title, action);
+        }
+    }
+
+    /// Options that a picker with the given action chooses from
+    fn picker_items(&self, action: &PickerAction) -> Vec<PickerItem> {
+        match action {
+            PickerAction::NavigateShip(ship_symbol) => {
+                let origin = self
+                    .state
+                    .ships
+                    .iter()
+                    .find(|s| &s.symbol == ship_symbol)
+                    .map(|s| (s.nav.route.destination.x, s.nav.route.destination.y));
+                self.state
+                    .waypoints
+                    .iter()
+                    .map(|w| {
+                        let distance = origin.map_or(String::new(), |(x, y)| {
+                            format!("{:.0}", f64::from(w.x - x).hypot(f64::from(w.y - y)))
+                        });
+                        PickerItem {
+                            value: w.symbol.clone(),
+                            label: format!(
+                                "{:<16} {:<16} {distance:>5}",
+                                w.symbol,
+                                w.r#type.to_string()
+                            ),
+                        }
+                    })
+                    .collect()
+            }
+            PickerAction::SetFlightMode(_) => FLIGHT_MODES
+                .iter()
+                .map(|mode| PickerItem {
+                    value: mode.to_string(),
+                    label: mode.to_string(),
+                })
+                .collect(),
+            PickerAction::Extract(ship_symbol) => {
+                let waypoint_symbol = self
+                    .state
+                    .ships
+                    .iter()
+                    .find(|s| &s.symbol == ship_symbol)
+                    .map_or("", |s| s.nav.waypoint_symbol.as_str());
+                let now = Utc::now();
+                let surveys = self.surveys_at(waypoint_symbol).map(|survey| {
+                    let expires = st_util::parse_time(&survey.expiration)
+                        .map_or(String::new(), |e| st_util::format_duration(e - now));
+                    PickerItem {
+                        value: survey.signature.clone(),
+                        label: format!(
+                            "{:<8} {:>9}  {}",
+                            st_util::survey_size_to_string(survey.size),
+                            expires,
+                            survey
+                                .deposits
+                                .iter()
+                                .map(|d| d.symbol.as_str())
+                                .collect::<Vec<&str>>()
+                                .join(", ")
+                        ),
+                    }
+                });
+                [PickerItem {
+                    value: String::new(),
+                    label: "No survey".to_owned(),
+                }]
+                .into_iter()
+                .chain(surveys)
+                .collect()
+            }
+            PickerAction::Jettison(ship_symbol) => self
+                .state
+                .ships
+                .iter()
+                .find(|s| &s.symbol == ship_symbol)
+                .map_or_else(Vec::new, |ship| {
+                    ship.cargo
+                        .inventory
+                        .iter()
+                        .map(|item| PickerItem {
+                            value: item.symbol.clone(),
+                            label: format!("{:<24} {:>5}", item.symbol, item.units),
+                        })
+                        .collect()
+                }),
+            PickerAction::DeliverContract(id) => self.contract_deliveries(id),
+            PickerAction::NegotiateContract => self.contract_negotiators(),
+            PickerAction::Refine(_) => REFINE_PRODUCTS
+                .iter()
+                .map(|&produce| PickerItem {
+                    value: st_util::produce_to_string(produce).to_owned(),
+                    label: st_util::produce_to_string(produce).to_owned(),
+                })
+                .collect(),
+            PickerAction::SwitchAgent => self.agent_choices(),
+            PickerAction::RegisterFaction => self.starting_faction_choices(),
+            PickerAction::CancelEvent => Self::cancellable_events(),
+        }
+    }
+
+    /// Agents that can be switched to, then an option to add another
+    fn agent_choices(&self) -> Vec<PickerItem> {
+        let shown = self.profile.as_ref().map(|p| p.symbol.as_str());
+        self.profiles
+            .iter()
+            .map(|profile| {
+                let note = if Some(profile.symbol.as_str()) == shown {
+                    "shown"
+                } else if self.other_states.contains_key(&profile.symbol) {
+                    "loaded"
+                } else {
+                    ""
+                };
+                PickerItem {
+                    value: profile.symbol.clone(),
+                    label: format!("{:<16} {note}", profile.symbol),
+                }
+            })
+            .chain([
+                PickerItem {
+                    value: ADD_AGENT_BY_TOKEN.to_owned(),
+                    label: "Add an agent by token".to_owned(),
+                },
+                PickerItem {
+                    value: REGISTER_AGENT.to_owned(),
+                    label: "Register a new agent".to_owned(),
+                },
+            ])
+            .collect()
+    }
+
+    /// Factions a new agent can start with, from the factions the server lists.
+    /// Registering only accepts the factions `register_request::Faction` knows of,
+    /// so any others the server lists are left out.
+    fn starting_faction_choices(&self) -> Vec<PickerItem> {
+        self.state
+            .factions
+            .iter()
+            .filter(|faction| st_util::starting_faction_from_str(&faction.symbol).is_some())
+            .map(|faction| PickerItem {
+                value: faction.symbol.clone(),
+                label: format!(
+                    "{:<10} {} ({})",
+                    faction.symbol, faction.name, faction.headquarters
+                ),
+            })
+            .collect()
+    }
+
+    /// Opens a picker for the given action
+    pub fn open_picker(&mut self, title: &str, action: PickerAction) {
+        let items = self.picker_items(&action);
+        let mut list_state = ListState::default();
+        if !items.is_empty() {
+            list_state.select(Some(0));
+        }
+        self.state.picker = Some(Picker {
+            title: title.to_owned(),
+            items,
+            list_state,
+            action,
+        });
+    }
+
+    /// Rebuilds the options of the open picker, e.g. once the data it lists has loaded
+    pub fn refresh_picker(&mut self) {
+        let Some(action) = self.state.picker.as_ref().map(|p| p.action.clone()) else {
+            return;
+        };
+        let items = self.picker_items(&action);
+        if let Some(picker) = self.state.picker.as_mut() {
+            let selected = match picker.list_state.selected() {
+                Some(index) if index < items.len() => Some(index),
+                _ if items.is_empty() => None,
+                _ => Some(0),
+            };
+            picker.list_state.select(selected);
+            picker.items = items;
+        }
+    }
+
+    /// Moves the selection of the open picker
+    pub fn picker_move(&mut self, delta: i32) {
+        if let Some(picker) = self.state.picker.as_mut() {
+            move_selection(&mut picker.list_state, picker.items.len(), delta);
+        }
+    }
+
+    /// Closes the open picker and acts on the chosen option
+    pub async fn submit_picker(&mut self) {
+        let Some(picker) = self.state.picker.take() else {
+            return;
+        };
+        let Some(item) = picker
+            .list_state
+            .selected()
+            .and_then(|index| picker.items.into_iter().nth(index))
+        else {
+            return;
+        };
+        match picker.action {
+            PickerAction::NavigateShip(ship) => {
+                self.dispatch(IoEvent::NavigateShip {
+                    ship,
+                    waypoint: item.value,
+                })
+                .await;
+            }
+            PickerAction::SetFlightMode(ship) => {
+                if let Some(&mode) = FLIGHT_MODES.iter().find(|m| m.to_string() == item.value) {
+                    self.dispatch(IoEvent::SetFlightMode { ship, mode }).await;
+                }
+            }
+            PickerAction::Extract(ship) => {
+                let survey = self
+                    .state
+                    .surveys
+                    .iter()
+                    .find(|s| s.signature == item.value)
+                    .cloned();
+                self.dispatch(IoEvent::ExtractResources { ship, survey })
                     .await;
-            } else if let Some(delivers) = &contract.terms.deliver {
-                if delivers
+            }
+            PickerAction::Jettison(ship) => {
+                let held = self
+                    .state
+                    .ships
+                    .iter()
+                    .find(|s| s.symbol == ship)
+                    .and_then(|s| s.cargo.inventory.iter().find(|i| i.symbol == item.value))
+                    .map_or(0, |i| i.units);
+                let title = format!("Jettison {} from {ship} ({held} held)", item.value);
+                let action = PromptAction::Jettison {
+                    ship,
+                    good: item.value,
+                };
+                self.open_prompt_with(&title, action, held.to_string());
+            }
+            PickerAction::Refine(ship) => {
+                if let Some(&produce) = REFINE_PRODUCTS
                     .iter()
-                    .all(|d| d.units_fulfilled >= d.units_required)
+                    .find(|&&p| st_util::produce_to_string(p) == item.value)
                 {
-                    self.dispatch(IoEvent::AcceptContract(contract.id.clone()))
-                        .await;
+                    self.dispatch(IoEvent::RefineCargo { ship, produce }).await;
+                }
+            }
+            PickerAction::DeliverContract(contract) => {
+                let Some((ship, good)) = item.value.split_once(' ') else {
+                    return;
+                };
+                let units = self.deliverable_units(&contract, ship, good);
+                if units > 0 {
+                    self.dispatch(IoEvent::DeliverContract {
+                        contract,
+                        ship: ship.to_owned(),
+                        good: good.to_owned(),
+                        units,
+                    })
+                    .await;
+                } else {
+                    self.notify(
+                        Severity::Warning,
+                        format!("{ship} has no {good} to deliver for {contract}"),
+                        Vec::new(),
+                    );
+                }
+            }
+            PickerAction::NegotiateContract => {
+                self.dispatch(IoEvent::NegotiateContract(item.value)).await;
+            }
+            PickerAction::SwitchAgent if item.value == ADD_AGENT_BY_TOKEN => {
+                self.open_prompt("Agent token", PromptAction::AddAgent);
+            }
+            PickerAction::SwitchAgent if item.value == REGISTER_AGENT => {
+                Box::pin(self.register_agent()).await;
+            }
+            PickerAction::SwitchAgent => self.switch_agent(&item.value).await,
+            PickerAction::RegisterFaction => {
+                if let Some(faction) = st_util::starting_faction_from_str(&item.value) {
+                    let action = PromptAction::RegisterAgent { faction };
+                    self.open_prompt("Call sign, 3 to 14 characters", action);
+                }
+            }
+            PickerAction::CancelEvent => {
+                if let Ok(id) = item.value.parse() {
+                    self.cancel_event(id);
                 }
             }
         }
     }
 
+    /// Asks for confirmation to accept the selected contract, or to fulfill it once every
+    /// delivery is complete
+    pub fn accept_or_fulfull_contract(&mut self) {
+        let Some(contract) = self.selected_contract() else {
+            return;
+        };
+        let payment = &contract.terms.payment;
+        if !contract.accepted {
+            let message = vec![
+                format!(
+                    "Accept contract {} from {}",
+                    contract.id, contract.faction_symbol
+                ),
+                format!("Deliver by {}", contract.terms.deadline),
+            ];
+            let credit_change = Some(i64::from(payment.on_accepted));
+            let event = IoEvent::AcceptContract(contract.id.clone());
+            self.open_confirm("Accept contract", message, credit_change, event);
+        } else if !contract.fulfilled
+            && contract
+                .terms
+                .deliver
+                .iter()
+                .flatten()
+                .all(|d| d.units_fulfilled >= d.units_required)
+        {
+            let message = vec![format!("Fulfill contract {}", contract.id)];
+            let credit_change = Some(i64::from(payment.on_fulfilled));
+            let event = IoEvent::FulfillContract(contract.id.clone());
+            self.open_confirm("Fulfill contract", message, credit_change, event);
+        }
+    }
+
+    /// Returns the contract selected on the agent page, if any
+    #[must_use]
+    pub fn selected_contract(&self) -> Option<&Contract> {
+        self.state
+            .contracts_list_state
+            .selected()
+            .and_then(|index| self.state.contracts.get(index))
+    }
+
+    /// Number of units of a good a ship has in its cargo hold
+    fn held_units(&self, ship_symbol: &str, good: &str) -> i32 {
+        self.state
+            .ships
+            .iter()
+            .find(|s| s.symbol == ship_symbol)
+            .and_then(|ship| ship.cargo.inventory.iter().find(|item| item.symbol == good))
+            .map_or(0, |item| item.units)
+    }
+
+    /// Number of units of a good a ship can deliver for a contract right now: what it holds,
+    /// up to what the contract still needs, if it is docked at the destination
+    fn deliverable_units(&self, contract_id: &str, ship_symbol: &str, good: &str) -> i32 {
+        let Some(contract) = self.state.contracts.iter().find(|c| c.id == contract_id) else {
+            return 0;
+        };
+        let Some(ship) = self.state.ships.iter().find(|s| s.symbol == ship_symbol) else {
+            return 0;
+        };
+        if ship.nav.status != ShipNavStatus::Docked {
+            return 0;
+        }
+        let held = self.held_units(ship_symbol, good);
+        contract
+            .terms
+            .deliver
+            .iter()
+            .flatten()
+            .find(|d| d.trade_symbol == good && d.destination_symbol == ship.nav.waypoint_symbol)
+            .map_or(0, |d| held.min(d.units_required - d.units_fulfilled))
+    }
+
+    /// Ships and goods that can currently be delivered for a contract
+    fn contract_deliveries(&self, contract_id: &str) -> Vec<PickerItem> {
+        let Some(contract) = self.state.contracts.iter().find(|c| c.id == contract_id) else {
+            return Vec::new();
+        };
+        let mut items = Vec::new();
+        for deliver in contract.terms.deliver.iter().flatten() {
+            for ship in &self.state.ships {
+                let units =
+                    self.deliverable_units(contract_id, &ship.symbol, &deliver.trade_symbol);
+                if units > 0 {
+                    items.push(PickerItem {
+                        value: format!("{} {}", ship.symbol, deliver.trade_symbol),
+                        label: format!(
+                            "{:<16} {:<20} {units:>5}",
+                            ship.symbol, deliver.trade_symbol
+                        ),
+                    });
+                }
+            }
+        }
+        items
+    }
+
+    /// Ships docked at a faction headquarters, which can negotiate new contracts
+    fn contract_negotiators(&self) -> Vec<PickerItem> {
+        self.state
+            .ships
+            .iter()
+            .filter(|ship| {
+                ship.nav.status == ShipNavStatus::Docked
+                    && self
+                        .state
+                        .factions
+                        .iter()
+                        .any(|f| f.headquarters == ship.nav.waypoint_symbol)
+            })
+            .map(|ship| PickerItem {
+                value: ship.symbol.clone(),
+                label: format!("{:<16} {}", ship.symbol, ship.nav.waypoint_symbol),
+            })
+            .collect()
+    }
+
+    /// Opens a picker to choose a docked ship to deliver cargo for the selected contract
+    pub fn pick_contract_delivery(&mut self) {
+        let Some(contract) = self.selected_contract() else {
+            return;
+        };
+        if !contract.accepted || contract.fulfilled {
+            return;
+        }
+        let action = PickerAction::DeliverContract(contract.id.clone());
+        if !self.picker_items(&action).is_empty() {
+            self.open_picker("Deliver with", action);
+        }
+    }
+
+    /// Opens a picker to choose a ship docked at a faction headquarters to negotiate a new
+    /// contract with
+    pub fn pick_contract_negotiator(&mut self) {
+        let action = PickerAction::NegotiateContract;
+        if !self.picker_items(&action).is_empty() {
+            self.open_picker("Negotiate contract with", action);
+        }
+    }
+
     fn list_move(&mut self, delta: i32) {
-        let (count, list_state) = match self.state.tab {
+        if self.state.tab == Tab::Fleet {
+            self.state.activity_scroll = 0;
+        }
+        let (count, list_state): (usize, &mut dyn Selectable) = match self.state.tab {
             Tab::Agent => (
                 self.state.contracts.len(),
                 &mut self.state.contracts_list_state,
             ),
-            _ => return,
-        };
-        if count == 0 {
-            list_state.select(None);
-        } else if let Some(selected) = list_state.selected() {
-            list_state.select(Some(
-                (i32::try_from(selected).unwrap_or(i32::MAX) + delta)
-                    .rem_euclid(i32::try_from(count).unwrap_or(i32::MAX)) as usize,
-            ));
-        } else {
-            list_state.select(Some(0));
-        }
+            Tab::Systems => (
+                self.state.waypoints.len(),
+                &mut self.state.waypoints_table_state,
+            ),
+            Tab::Fleet => (self.state.ships.len(), &mut self.state.ships_list_state),
+            Tab::Market => (self.market_row_count(), &mut self.state.market_table_state),
+            Tab::Shipyard => (
+                self.shipyard_row_count(),
+                &mut self.state.shipyard_table_state,
+            ),
+            Tab::Trade => (
+                self.state.trade_routes.len(),
+                &mut self.state.trade_table_state,
+            ),
+        };
+        move_selection(list_state, count, delta);
     }
 
     pub fn list_next(&mut self) {
@@ -119,7 +2064,111 @@ impl App {
     }
 
     /// Handles the tick event of the terminal.
-    pub fn tick(&self) {}
+    ///
+    /// Ships whose transit has finished are marked as arrived and their nav state is refreshed.
+    /// Expired surveys, cooldowns and toasts are dropped.
+    pub async fn tick(&mut self) {
+        let now = Utc::now();
+        let mut arrived = Vec::new();
+        for ship in &mut self.state.ships {
+            if ship.nav.status == ShipNavStatus::InTransit
+                && st_util::parse_time(&ship.nav.route.arrival).is_some_and(|a| a <= now)
+            {
+                // Ships drop into orbit at their destination
+                ship.nav.status = ShipNavStatus::InOrbit;
+                arrived.push(ship.symbol.clone());
+            }
+        }
+        for symbol in arrived {
+            self.dispatch(IoEvent::UpdateShipNav(symbol)).await;
+        }
+
+        self.state
+            .surveys
+            .retain(|s| st_util::parse_time(&s.expiration).is_none_or(|e| e > now));
+        self.state
+            .cooldowns
+            .retain(|_, c| st_util::cooldown_remaining(c, now).is_some());
+
+        let timeout = chrono::Duration::seconds(TOAST_SECONDS);
+        self.state
+            .toasts
+            .retain(|t| t.shown_at.is_none_or(|shown_at| now - shown_at < timeout));
+        self.show_queued_toasts(now);
+    }
+
+    /// Shows a notification in the status bar and adds it to the history
+    pub fn notify(&mut self, severity: Severity, message: String, details: Vec<String>) {
+        let notification = Notification {
+            time: Utc::now(),
+            severity,
+            message,
+            details,
+        };
+        self.state.notifications.push(notification.clone());
+        self.state.toasts.push_back(Toast {
+            notification,
+            shown_at: None,
+        });
+        self.show_queued_toasts(Utc::now());
+    }
+
+    /// Notifies the user of an error, with the details of the SpaceTraders error if it is one
+    pub fn notify_error(&mut self, error: &anyhow::Error) {
+        let Some(api_error) = error.downcast_ref::<ApiError>() else {
+            self.notify(Severity::Error, format!("{error:#}"), Vec::new());
+            return;
+        };
+
+        // Rejected requests are usually the game's rules, not something broken
+        let severity = if api_error.is_server_error() {
+            Severity::Error
+        } else {
+            Severity::Warning
+        };
+        let mut details = Vec::new();
+        if let Some(status) = api_error.status {
+            details.push(format!("Status: {status}"));
+        }
+        if let Some(code) = api_error.code {
+            details.push(format!("Code: {code}"));
+        }
+        if let Some(data) = &api_error.data {
+            let data = serde_json::to_string_pretty(data).unwrap_or_else(|_| data.to_string());
+            details.push("Data:".to_owned());
+            details.extend(data.lines().map(|line| format!("  {line}")));
+        }
+        if api_error.retries > 0 {
+            details.push(format!("Gave up after {} retries", api_error.retries));
+        }
+        self.notify(severity, api_error.to_string(), details);
+    }
+
+    /// Starts showing queued toasts while there is room in the status bar
+    fn show_queued_toasts(&mut self, now: DateTime<Utc>) {
+        for toast in self.state.toasts.iter_mut().take(MAX_VISIBLE_TOASTS) {
+            toast.shown_at.get_or_insert(now);
+        }
+    }
+
+    /// Opens or closes the notification history. Opening it dismisses the toasts in the
+    /// status bar, since they are all in the history.
+    pub fn toggle_notifications(&mut self) {
+        self.state.show_notifications = !self.state.show_notifications;
+        self.state.notifications_scroll = 0;
+        if self.state.show_notifications {
+            self.state.toasts.clear();
+        }
+    }
+
+    /// Scrolls the notification history back (positive) or forward (negative)
+    pub fn scroll_notifications(&mut self, delta: i32) {
+        let len = self.state.notifications.len();
+        let scroll = i64::try_from(self.state.notifications_scroll).unwrap_or(i64::MAX);
+        self.state.notifications_scroll = usize::try_from((scroll + i64::from(delta)).max(0))
+            .unwrap_or(0)
+            .min(len.saturating_sub(1));
+    }
 
     /// Set running to false to quit the application.
     pub fn quit(&mut self) {
@@ -0,0 +1,69 @@
+use anyhow::{anyhow, Result};
+use tokio::sync::{mpsc, oneshot};
+
+use super::App;
+
+/// A change to the app, applied by the UI loop between draws
+pub type Update = Box<dyn FnOnce(&mut App) + Send>;
+
+/// Lets IO tasks read and change the app without holding it.
+/// The UI loop owns the [`App`] and applies each [`Update`] as it arrives,
+/// so it redraws as soon as data comes in and never waits on a request.
+#[derive(Debug, Clone)]
+pub struct AppHandle {
+    sender: mpsc::UnboundedSender<Update>,
+    /// agent whose state updates apply to, or `None` for whichever is shown
+    agent: Option<String>,
+}
+
+impl AppHandle {
+    /// Creates a handle, and the receiver the owner of the app applies updates from
+    #[must_use]
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<Update>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        (
+            Self {
+                sender,
+                agent: None,
+            },
+            receiver,
+        )
+    }
+
+    /// Agent whose state updates apply to, if one was chosen
+    #[must_use]
+    pub fn agent(&self) -> Option<&str> {
+        self.agent.as_deref()
+    }
+
+    /// A handle whose updates apply to an agent's state, even once another agent is shown
+    #[must_use]
+    pub fn for_agent(&self, agent: Option<&str>) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            agent: agent.map(ToOwned::to_owned),
+        }
+    }
+
+    /// Runs `f` on the app and returns its result once the UI loop has applied it
+    ///
+    /// # Errors
+    /// Errors if the app has closed, so nothing will apply the update
+    pub async fn update<R: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut App) -> R + Send + 'static,
+    ) -> Result<R> {
+        let (result_sender, result) = oneshot::channel();
+        let agent = self.agent.clone();
+        self.sender
+            .send(Box::new(move |app| {
+                let output = app.with_agent_state(agent.as_deref(), f);
+                // Nobody is waiting for the result if the request was cancelled
+                let _ = result_sender.send(output);
+            }))
+            .map_err(|_| anyhow!("App closed before the update was applied"))?;
+        result
+            .await
+            .map_err(|_| anyhow!("App closed before the update was applied"))
+    }
+}
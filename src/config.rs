@@ -1,47 +1,209 @@
 use std::{
-    env,
+    collections::{BTreeMap, HashMap},
+    env, fs, io,
+    path::PathBuf,
     process,
-    sync::Arc
+    sync::Arc,
+    time::Duration,
 };
 
-use spacedust::apis::configuration::Configuration;
+use anyhow::{anyhow, Context, Result};
 use once_cell::sync::Lazy;
-use reqwest_middleware::{Middleware, ClientWithMiddleware};
+use reqwest_middleware::{ClientWithMiddleware, Middleware};
+use serde::Deserialize;
+use spacedust::apis::configuration::Configuration;
+use sqlx::any::{AnyPool, AnyPoolOptions};
 use tokio::sync::OnceCell;
-use sqlx::{Pool, Postgres};
-use sqlx::postgres::PgPoolOptions;
 
-/// [`Configuration`] object for use in all API calls.
-/// Sets API key and manages rate limit.
-pub static CONFIGURATION: Lazy<Configuration> = Lazy::new(|| {
-    let Ok(token) = env::var("TOKEN") else {
-        eprintln!("TOKEN environment variable expected");
+/// How long to wait for a response before giving up on a request.
+/// Timed out reads are retried by the rate limiter, actions aren't in case they were taken.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Config file read when `SPACETRADERS_CONFIG` doesn't name another, in the working directory
+const DEFAULT_CONFIG_FILE: &str = "spacetraders.json";
+
+/// A server the client can talk to, as listed in the config file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// base URL of the API, the live server if unset
+    pub url: Option<String>,
+    /// agent token to authenticate with
+    pub token: Option<String>,
+    /// database for this server's data, so servers don't mix their records
+    pub database_url: Option<String>,
+    /// tokens of agents that can be switched between, by agent symbol
+    pub agents: BTreeMap<String, String>,
+}
+
+/// Contents of the config file. Anything left out falls back to the defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    /// name of the server in `servers` to use
+    pub server: Option<String>,
+    /// servers that can be switched between by name
+    pub servers: HashMap<String, ServerConfig>,
+    /// server settings for when no server is chosen, and defaults for the chosen one
+    pub url: Option<String>,
+    pub token: Option<String>,
+    pub database_url: Option<String>,
+    pub agents: BTreeMap<String, String>,
+    pub rate_limit_burst: Option<u16>,
+    pub rate_limit_per_second: Option<u16>,
+    pub log_file: Option<String>,
+}
+
+/// Settings after applying the config file and environment, in that order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Settings {
+    /// name of the server in use, if one was chosen from the config file
+    pub server: Option<String>,
+    pub url: Option<String>,
+    /// token to use at startup, if one is set
+    pub token: Option<String>,
+    pub database_url: Option<String>,
+    /// tokens of agents that can be switched between, by agent symbol
+    pub agents: BTreeMap<String, String>,
+    pub rate_limit_burst: Option<u16>,
+    pub rate_limit_per_second: Option<u16>,
+    pub log_file: Option<String>,
+}
+
+impl Settings {
+    /// Reads the config file, if there is one, and applies overrides from the environment.
+    /// `SPACETRADERS_SERVER` picks the server, and `SPACETRADERS_URL`, `TOKEN`, `DATABASE_URL`,
+    /// `RATE_LIMIT_BURST`, `RATE_LIMIT_PER_SECOND` and `LOG_FILE` override single settings.
+    ///
+    /// # Errors
+    /// Fails if the config file can't be read or parsed, or a setting is invalid
+    pub fn load() -> Result<Self> {
+        let requested = env::var_os("SPACETRADERS_CONFIG").map(PathBuf::from);
+        let path = requested
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_FILE));
+        let file = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .with_context(|| format!("Invalid config file {}", path.display()))?,
+            // Only complain about a missing file if it was asked for
+            Err(e) if e.kind() == io::ErrorKind::NotFound && requested.is_none() => {
+                ConfigFile::default()
+            }
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Couldn't read config file {}", path.display()));
+            }
+        };
+        Self::resolve(file, |name| env::var(name).ok())
+    }
+
+    /// Picks the server from `file` and applies the overrides `var` returns for each variable
+    ///
+    /// # Errors
+    /// Fails if the chosen server isn't listed or a rate limit isn't a number
+    pub fn resolve(file: ConfigFile, var: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let server = var("SPACETRADERS_SERVER").or(file.server);
+        let chosen = match &server {
+            Some(name) => file.servers.get(name).cloned().ok_or_else(|| {
+                let mut known: Vec<_> = file.servers.keys().map(String::as_str).collect();
+                known.sort_unstable();
+                anyhow!(
+                    "Unknown server {name}, expected one of: {}",
+                    known.join(", ")
+                )
+            })?,
+            None => ServerConfig::default(),
+        };
+        let number = |name| -> Result<Option<u16>> {
+            var(name)
+                .map(|v| {
+                    v.parse()
+                        .with_context(|| format!("{name} should be a whole number, got {v}"))
+                })
+                .transpose()
+        };
+
+        let mut agents = file.agents;
+        agents.extend(chosen.agents);
+
+        Ok(Self {
+            server,
+            url: var("SPACETRADERS_URL").or(chosen.url).or(file.url),
+            token: var("TOKEN").or(chosen.token).or(file.token),
+            database_url: var("DATABASE_URL")
+                .or(chosen.database_url)
+                .or(file.database_url),
+            agents,
+            rate_limit_burst: number("RATE_LIMIT_BURST")?.or(file.rate_limit_burst),
+            rate_limit_per_second: number("RATE_LIMIT_PER_SECOND")?.or(file.rate_limit_per_second),
+            log_file: var("LOG_FILE").or(file.log_file),
+        })
+    }
+}
+
+/// Settings read once at startup, see [`Settings::load`]
+pub static SETTINGS: Lazy<Settings> = Lazy::new(|| {
+    Settings::load().unwrap_or_else(|e| {
+        eprintln!("{e:#}");
         process::exit(1);
-    };
+    })
+});
 
+/// [`Configuration`] object for use in all API calls.
+/// Manages rate limit and which agent requests are made as.
+/// Requests go to the configured server's URL if set, e.g. for a local test server.
+/// The token is added per request, see [`crate::profile::with_token`].
+pub static CONFIGURATION: Lazy<Configuration> = Lazy::new(|| {
     let mut configuration = Configuration::new();
-    configuration.bearer_access_token = Some(token);
-    let middleware: Box<[Arc<dyn Middleware>]> = Box::new([Arc::new(crate::rate_limit::Middleware)]);
-    configuration.client = ClientWithMiddleware::new(reqwest::Client::new(), middleware);
+    if let Some(base_path) = &SETTINGS.url {
+        configuration.base_path.clone_from(base_path);
+    }
+    let middleware: Box<[Arc<dyn Middleware>]> = Box::new([
+        Arc::new(crate::profile::Middleware),
+        Arc::new(crate::rate_limit::Middleware),
+    ]);
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .unwrap_or_else(|e| {
+            eprintln!("Couldn't set up the HTTP client: {e}");
+            process::exit(1);
+        });
+    configuration.client = ClientWithMiddleware::new(client, middleware);
     configuration
 });
 
-static DB_POOL: OnceCell<Pool<Postgres>> = OnceCell::const_new();
-/// Returns the global database pool to be used for all database operations
-pub async fn get_global_db_pool() -> &'static Pool<Postgres> {
-    DB_POOL.get_or_init(|| async {
-        let Ok(database_url) = env::var("DATABASE_URL") else {
-            eprintln!("DATABASE_URL environment variable expected");
-            process::exit(1);
-        };
-        let Ok(pool) = PgPoolOptions::new()
-            .max_connections(5)
-            .connect(&database_url)
-            .await 
-        else {
-            eprintln!("Database connection failed");
-            process::exit(1);
-        };
-        pool
-    }).await
+/// Database used when no database is configured, a file in the working directory
+#[cfg(feature = "sqlite")]
+const DEFAULT_DATABASE_URL: &str = "sqlite://spacetraders.db?mode=rwc";
+
+static DB_POOL: OnceCell<AnyPool> = OnceCell::const_new();
+/// Returns the global database pool to be used for all database operations.
+/// The backend is chosen by the scheme of the database URL, e.g. `postgres://` or `sqlite://`.
+pub async fn get_global_db_pool() -> &'static AnyPool {
+    DB_POOL
+        .get_or_init(|| async {
+            let database_url = match SETTINGS.database_url.clone() {
+                Some(database_url) => database_url,
+                #[cfg(feature = "sqlite")]
+                None => DEFAULT_DATABASE_URL.to_owned(),
+                #[cfg(not(feature = "sqlite"))]
+                None => {
+                    eprintln!("DATABASE_URL expected, in the environment or {DEFAULT_CONFIG_FILE}");
+                    process::exit(1);
+                }
+            };
+            match AnyPoolOptions::new()
+                .max_connections(5)
+                .connect(&database_url)
+                .await
+            {
+                Ok(pool) => pool,
+                Err(e) => {
+                    eprintln!("Database connection failed: {e}");
+                    process::exit(1);
+                }
+            }
+        })
+        .await
 }
@@ -1,6 +1,21 @@
 #![allow(clippy::expect_used)]
 
-use crate::config::get_global_db_pool;
+use std::collections::HashMap;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Serialize};
+use spacedust::models::{
+    Agent, Contract, Faction, JumpGate, Market, MarketTransaction, Ship, ShipType, Shipyard,
+    ShipyardTransaction, System, Waypoint, WaypointType,
+};
+use sqlx::Row;
+
+use crate::{
+    config::get_global_db_pool,
+    st_util,
+    trade::{GoodPrice, KnownMarket, Location},
+};
 
 pub async fn setup_database() {
     sqlx::migrate!()
@@ -8,3 +23,709 @@ pub async fn setup_database() {
         .await
         .expect("database migration");
 }
+
+/// Name the API uses for an enum value, e.g. `IN_ORBIT`
+fn api_name<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_owned))
+        .unwrap_or_default()
+}
+
+/// Parse a timestamp returned by the API, falling back to now if it is malformed
+fn api_time(timestamp: &str) -> DateTime<Utc> {
+    st_util::parse_time(timestamp).unwrap_or_else(Utc::now)
+}
+
+/// Records the latest state of an agent and a snapshot of its credits
+///
+/// # Errors
+/// Errors if a database query fails
+pub async fn save_agent(agent: &Agent) -> Result<()> {
+    let now = Utc::now();
+    let mut tx = get_global_db_pool().await.begin().await?;
+    sqlx::query(
+        "INSERT INTO agents (symbol, account_id, headquarters, credits, updated_at)
+             VALUES ($1, $2, $3, $4, $5)
+             ON CONFLICT (symbol) DO
+                UPDATE SET credits = EXCLUDED.credits, updated_at = EXCLUDED.updated_at",
+    )
+    .bind(&agent.symbol)
+    .bind(&agent.account_id)
+    .bind(&agent.headquarters)
+    .bind(i64::from(agent.credits))
+    .bind(now)
+    .execute(&mut tx)
+    .await?;
+    sqlx::query("INSERT INTO agent_snapshots (symbol, credits, fetched_at) VALUES ($1, $2, $3)")
+        .bind(&agent.symbol)
+        .bind(i64::from(agent.credits))
+        .bind(now)
+        .execute(&mut tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Records the latest state of ships and a snapshot of where they are and what they carry
+///
+/// # Errors
+/// Errors if a database query fails
+pub async fn save_ships(ships: &[Ship]) -> Result<()> {
+    let now = Utc::now();
+    let mut tx = get_global_db_pool().await.begin().await?;
+    for ship in ships {
+        sqlx::query(
+            "INSERT INTO ships (symbol, data, updated_at) VALUES ($1, $2, $3)
+                 ON CONFLICT (symbol) DO
+                    UPDATE SET data = EXCLUDED.data, updated_at = EXCLUDED.updated_at",
+        )
+        .bind(&ship.symbol)
+        .bind(serde_json::to_string(ship)?)
+        .bind(now)
+        .execute(&mut tx)
+        .await?;
+        sqlx::query(
+            "INSERT INTO ship_snapshots
+                 (symbol, status, waypoint_symbol, fuel, cargo_units, fetched_at)
+                 VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+        .bind(&ship.symbol)
+        .bind(api_name(&ship.nav.status))
+        .bind(&ship.nav.waypoint_symbol)
+        .bind(ship.fuel.current)
+        .bind(ship.cargo.units)
+        .bind(now)
+        .execute(&mut tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Records the latest state of contracts offered to an agent
+///
+/// # Errors
+/// Errors if a database query fails
+pub async fn save_contracts(agent_symbol: &str, contracts: &[Contract]) -> Result<()> {
+    let now = Utc::now();
+    let mut tx = get_global_db_pool().await.begin().await?;
+    for contract in contracts {
+        sqlx::query(
+            "INSERT INTO contracts
+                 (id, faction_symbol, type, accepted, fulfilled, data, updated_at, agent_symbol)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                 ON CONFLICT (id) DO
+                    UPDATE SET accepted = EXCLUDED.accepted, fulfilled = EXCLUDED.fulfilled,
+                        data = EXCLUDED.data, updated_at = EXCLUDED.updated_at,
+                        agent_symbol = EXCLUDED.agent_symbol",
+        )
+        .bind(&contract.id)
+        .bind(&contract.faction_symbol)
+        .bind(api_name(&contract.r#type))
+        .bind(contract.accepted)
+        .bind(contract.fulfilled)
+        .bind(serde_json::to_string(contract)?)
+        .bind(now)
+        .bind(agent_symbol)
+        .execute(&mut tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Records the latest state of factions
+///
+/// # Errors
+/// Errors if a database query fails
+pub async fn save_factions(factions: &[Faction]) -> Result<()> {
+    let now = Utc::now();
+    let mut tx = get_global_db_pool().await.begin().await?;
+    for faction in factions {
+        sqlx::query(
+            "INSERT INTO factions (symbol, headquarters, data, updated_at)
+                 VALUES ($1, $2, $3, $4)
+                 ON CONFLICT (symbol) DO
+                    UPDATE SET headquarters = EXCLUDED.headquarters, data = EXCLUDED.data,
+                        updated_at = EXCLUDED.updated_at",
+        )
+        .bind(&faction.symbol)
+        .bind(&faction.headquarters)
+        .bind(serde_json::to_string(faction)?)
+        .bind(now)
+        .execute(&mut tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Records the latest state of systems
+///
+/// # Errors
+/// Errors if a database query fails
+pub async fn save_systems(systems: &[System]) -> Result<()> {
+    let now = Utc::now();
+    let mut tx = get_global_db_pool().await.begin().await?;
+    for system in systems {
+        sqlx::query(
+            "INSERT INTO systems (symbol, sector_symbol, type, x, y, data, updated_at)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7)
+                 ON CONFLICT (symbol) DO
+                    UPDATE SET data = EXCLUDED.data, updated_at = EXCLUDED.updated_at",
+        )
+        .bind(&system.symbol)
+        .bind(&system.sector_symbol)
+        .bind(api_name(&system.r#type))
+        .bind(system.x)
+        .bind(system.y)
+        .bind(serde_json::to_string(system)?)
+        .bind(now)
+        .execute(&mut tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Records the latest state of waypoints
+///
+/// # Errors
+/// Errors if a database query fails
+pub async fn save_waypoints(waypoints: &[Waypoint]) -> Result<()> {
+    let now = Utc::now();
+    let mut tx = get_global_db_pool().await.begin().await?;
+    for waypoint in waypoints {
+        sqlx::query(
+            "INSERT INTO waypoints (symbol, system_symbol, type, x, y, data, updated_at)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7)
+                 ON CONFLICT (symbol) DO
+                    UPDATE SET data = EXCLUDED.data, updated_at = EXCLUDED.updated_at",
+        )
+        .bind(&waypoint.symbol)
+        .bind(&waypoint.system_symbol)
+        .bind(api_name(&waypoint.r#type))
+        .bind(waypoint.x)
+        .bind(waypoint.y)
+        .bind(serde_json::to_string(waypoint)?)
+        .bind(now)
+        .execute(&mut tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Records the latest state of a market and the prices of its goods,
+/// along with any transactions it lists
+///
+/// # Errors
+/// Errors if a database query fails
+pub async fn save_market(market: &Market) -> Result<()> {
+    let now = Utc::now();
+    let mut tx = get_global_db_pool().await.begin().await?;
+    sqlx::query(
+        "INSERT INTO markets (waypoint_symbol, data, updated_at) VALUES ($1, $2, $3)
+             ON CONFLICT (waypoint_symbol) DO
+                UPDATE SET data = EXCLUDED.data, updated_at = EXCLUDED.updated_at",
+    )
+    .bind(&market.symbol)
+    .bind(serde_json::to_string(market)?)
+    .bind(now)
+    .execute(&mut tx)
+    .await?;
+    for good in market.trade_goods.iter().flatten() {
+        sqlx::query(
+            "INSERT INTO market_prices (waypoint_symbol, trade_symbol, supply, trade_volume,
+                     purchase_price, sell_price, fetched_at)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7)",
+        )
+        .bind(&market.symbol)
+        .bind(&good.symbol)
+        .bind(api_name(&good.supply))
+        .bind(good.trade_volume)
+        .bind(good.purchase_price)
+        .bind(good.sell_price)
+        .bind(now)
+        .execute(&mut tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    if let Some(transactions) = &market.transactions {
+        save_transactions(transactions).await?;
+    }
+
+    Ok(())
+}
+
+/// Records the latest state of a shipyard
+///
+/// # Errors
+/// Errors if a database query fails
+pub async fn save_shipyard(shipyard: &Shipyard) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO shipyards (waypoint_symbol, data, updated_at) VALUES ($1, $2, $3)
+             ON CONFLICT (waypoint_symbol) DO
+                UPDATE SET data = EXCLUDED.data, updated_at = EXCLUDED.updated_at",
+    )
+    .bind(&shipyard.symbol)
+    .bind(serde_json::to_string(shipyard)?)
+    .bind(Utc::now())
+    .execute(get_global_db_pool().await)
+    .await?;
+
+    Ok(())
+}
+
+/// Records market transactions, ignoring ones that are already recorded
+///
+/// # Errors
+/// Errors if a database query fails
+pub async fn save_transactions(transactions: &[MarketTransaction]) -> Result<()> {
+    let mut tx = get_global_db_pool().await.begin().await?;
+    for transaction in transactions {
+        sqlx::query(
+            "INSERT INTO transactions (waypoint_symbol, ship_symbol, trade_symbol, type, units,
+                     price_per_unit, total_price, timestamp)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                 ON CONFLICT DO NOTHING",
+        )
+        .bind(&transaction.waypoint_symbol)
+        .bind(&transaction.ship_symbol)
+        .bind(&transaction.trade_symbol)
+        .bind(api_name(&transaction.r#type))
+        .bind(transaction.units)
+        .bind(transaction.price_per_unit)
+        .bind(transaction.total_price)
+        .bind(api_time(&transaction.timestamp))
+        .execute(&mut tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Records the purchase of a ship
+///
+/// # Errors
+/// Errors if the database query fails
+pub async fn save_ship_purchase(
+    ship: &Ship,
+    ship_type: ShipType,
+    transaction: &ShipyardTransaction,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO transactions (waypoint_symbol, ship_symbol, trade_symbol, type, units,
+                 price_per_unit, total_price, timestamp)
+             VALUES ($1, $2, $3, 'SHIP_PURCHASE', 1, $4, $4, $5)
+             ON CONFLICT DO NOTHING",
+    )
+    .bind(&transaction.waypoint_symbol)
+    .bind(&ship.symbol)
+    .bind(api_name(&ship_type))
+    .bind(transaction.price)
+    .bind(api_time(&transaction.timestamp))
+    .execute(get_global_db_pool().await)
+    .await?;
+
+    Ok(())
+}
+
+/// Records the systems a jump gate connects to
+///
+/// # Errors
+/// Errors if the database query fails
+pub async fn save_jump_gate(
+    waypoint_symbol: &str,
+    system_symbol: &str,
+    jump_gate: &JumpGate,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO jump_gates (waypoint_symbol, system_symbol, data, updated_at)
+             VALUES ($1, $2, $3, $4)
+             ON CONFLICT (waypoint_symbol) DO
+                UPDATE SET data = EXCLUDED.data, updated_at = EXCLUDED.updated_at",
+    )
+    .bind(waypoint_symbol)
+    .bind(system_symbol)
+    .bind(serde_json::to_string(jump_gate)?)
+    .bind(Utc::now())
+    .execute(get_global_db_pool().await)
+    .await?;
+
+    Ok(())
+}
+
+/// Loads the recorded connections of a jump gate, if they have been recorded
+///
+/// # Errors
+/// Errors if the database query fails or the recorded data can't be parsed
+pub async fn load_jump_gate(waypoint_symbol: &str) -> Result<Option<JumpGate>> {
+    let row = sqlx::query("SELECT data FROM jump_gates WHERE waypoint_symbol = $1")
+        .bind(waypoint_symbol)
+        .fetch_optional(get_global_db_pool().await)
+        .await?;
+
+    Ok(match row {
+        Some(row) => Some(serde_json::from_str(row.try_get("data")?)?),
+        None => None,
+    })
+}
+
+/// Loads where the jump gate of a system is, if its waypoints have been recorded
+///
+/// # Errors
+/// Errors if the database query fails
+pub async fn load_jump_gate_location(system_symbol: &str) -> Result<Option<Location>> {
+    let row = sqlx::query(
+        "SELECT symbol, x, y FROM waypoints WHERE system_symbol = $1 AND type = $2 LIMIT 1",
+    )
+    .bind(system_symbol)
+    .bind(api_name(&WaypointType::JumpGate))
+    .fetch_optional(get_global_db_pool().await)
+    .await?;
+
+    Ok(match row {
+        Some(row) => Some(Location {
+            symbol: row.try_get("symbol")?,
+            x: row.try_get("x")?,
+            y: row.try_get("y")?,
+        }),
+        None => None,
+    })
+}
+
+/// Loads the last recorded prices of the markets in a system
+///
+/// # Errors
+/// Errors if the database query fails
+pub async fn load_known_markets(system_symbol: &str) -> Result<Vec<KnownMarket>> {
+    let rows = sqlx::query(
+        "SELECT prices.waypoint_symbol, prices.trade_symbol, prices.trade_volume,
+                 prices.purchase_price, prices.sell_price, waypoints.x, waypoints.y
+             FROM market_prices prices
+             JOIN waypoints ON waypoints.symbol = prices.waypoint_symbol
+             WHERE waypoints.system_symbol = $1
+                 AND prices.fetched_at = (
+                     SELECT MAX(latest.fetched_at) FROM market_prices latest
+                         WHERE latest.waypoint_symbol = prices.waypoint_symbol
+                             AND latest.trade_symbol = prices.trade_symbol
+                 )
+             ORDER BY prices.waypoint_symbol",
+    )
+    .bind(system_symbol)
+    .fetch_all(get_global_db_pool().await)
+    .await?;
+
+    let mut markets: Vec<KnownMarket> = Vec::new();
+    for row in rows {
+        let waypoint_symbol: String = row.try_get("waypoint_symbol")?;
+        let price = GoodPrice {
+            symbol: row.try_get("trade_symbol")?,
+            trade_volume: row.try_get("trade_volume")?,
+            purchase_price: row.try_get("purchase_price")?,
+            sell_price: row.try_get("sell_price")?,
+        };
+        match markets.last_mut() {
+            Some(market) if market.location.symbol == waypoint_symbol => {
+                market.trade_goods.push(price);
+            }
+            _ => markets.push(KnownMarket {
+                location: Location {
+                    symbol: waypoint_symbol,
+                    x: row.try_get("x")?,
+                    y: row.try_get("y")?,
+                },
+                system_symbol: system_symbol.to_owned(),
+                trade_goods: vec![price],
+            }),
+        }
+    }
+
+    Ok(markets)
+}
+
+/// Prices of a good at a market at one point in time
+#[derive(Debug, Clone)]
+pub struct PricePoint {
+    pub fetched_at: DateTime<Utc>,
+    pub supply: String,
+    pub trade_volume: i32,
+    pub purchase_price: i32,
+    pub sell_price: i32,
+}
+
+/// Loads the prices recorded at a market since the given time, by trade symbol, oldest first
+///
+/// # Errors
+/// Errors if the database query fails
+pub async fn load_price_history(
+    waypoint_symbol: &str,
+    since: DateTime<Utc>,
+) -> Result<HashMap<String, Vec<PricePoint>>> {
+    let rows = sqlx::query(
+        "SELECT trade_symbol, supply, trade_volume, purchase_price, sell_price, fetched_at
+             FROM market_prices WHERE waypoint_symbol = $1 AND fetched_at >= $2
+             ORDER BY fetched_at",
+    )
+    .bind(waypoint_symbol)
+    .bind(since)
+    .fetch_all(get_global_db_pool().await)
+    .await?;
+
+    let mut history: HashMap<String, Vec<PricePoint>> = HashMap::new();
+    for row in rows {
+        history
+            .entry(row.try_get("trade_symbol")?)
+            .or_default()
+            .push(PricePoint {
+                fetched_at: row.try_get("fetched_at")?,
+                supply: row.try_get("supply")?,
+                trade_volume: row.try_get("trade_volume")?,
+                purchase_price: row.try_get("purchase_price")?,
+                sell_price: row.try_get("sell_price")?,
+            });
+    }
+
+    Ok(history)
+}
+
+/// Data recorded by an earlier session
+pub struct Cached<T> {
+    pub data: T,
+    /// when the oldest part of `data` was fetched
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Last known state of an agent, for showing before the API answers
+pub struct Cache {
+    pub agent: Cached<Agent>,
+    pub contracts: Option<Cached<Vec<Contract>>>,
+    pub factions: Vec<Faction>,
+    pub ships: Option<Cached<Vec<Ship>>>,
+    pub systems: Vec<System>,
+    /// waypoints in the system containing the agent's headquarters
+    pub waypoints: Option<Cached<Vec<Waypoint>>>,
+}
+
+/// Loads objects stored as JSON by a query selecting `data` and `updated_at` columns,
+/// or `None` if there are none
+async fn load_objects<T: DeserializeOwned>(
+    query: &str,
+    param: Option<&str>,
+) -> Result<Option<Cached<Vec<T>>>> {
+    let mut query = sqlx::query(query);
+    if let Some(param) = param {
+        query = query.bind(param);
+    }
+    let rows = query.fetch_all(get_global_db_pool().await).await?;
+
+    let mut data = Vec::with_capacity(rows.len());
+    let mut oldest: Option<DateTime<Utc>> = None;
+    for row in rows {
+        data.push(serde_json::from_str(row.try_get("data")?)?);
+        let updated_at = row.try_get("updated_at")?;
+        oldest = Some(oldest.map_or(updated_at, |oldest| oldest.min(updated_at)));
+    }
+
+    Ok(oldest.map(|updated_at| Cached { data, updated_at }))
+}
+
+/// Loads the last known state of an agent, if it has been recorded
+///
+/// # Errors
+/// Errors if a database query fails or recorded data can't be parsed
+pub async fn load_cache(agent_symbol: &str) -> Result<Option<Cache>> {
+    let Some(row) = sqlx::query(
+        "SELECT symbol, account_id, headquarters, credits, updated_at FROM agents
+             WHERE symbol = $1",
+    )
+    .bind(agent_symbol)
+    .fetch_optional(get_global_db_pool().await)
+    .await?
+    else {
+        return Ok(None);
+    };
+    let agent = Cached {
+        data: Agent::new(
+            row.try_get("account_id")?,
+            row.try_get("symbol")?,
+            row.try_get("headquarters")?,
+            i32::try_from(row.try_get::<i64, _>("credits")?)?,
+        ),
+        updated_at: row.try_get("updated_at")?,
+    };
+
+    let contracts = load_objects(
+        "SELECT data, updated_at FROM contracts WHERE agent_symbol = $1 ORDER BY id",
+        Some(&agent.data.symbol),
+    )
+    .await?;
+    let factions = load_objects(
+        "SELECT data, updated_at FROM factions ORDER BY symbol",
+        None,
+    )
+    .await?
+    .map_or_else(Vec::new, |f| f.data);
+    // Ship symbols are the agent symbol followed by a number
+    let ships = load_objects(
+        "SELECT data, updated_at FROM ships
+             WHERE substr(symbol, 1, length($1)) = $1 ORDER BY symbol",
+        Some(&format!("{}-", agent.data.symbol)),
+    )
+    .await?;
+    let systems = load_objects("SELECT data, updated_at FROM systems ORDER BY symbol", None)
+        .await?
+        .map_or_else(Vec::new, |s| s.data);
+    let waypoints = load_objects(
+        "SELECT data, updated_at FROM waypoints WHERE system_symbol = $1",
+        Some(st_util::waypoint_system_symbol(&agent.data.headquarters)),
+    )
+    .await?;
+
+    Ok(Some(Cache {
+        agent,
+        contracts,
+        factions,
+        ships,
+        systems,
+        waypoints,
+    }))
+}
+
+/// Symbol of the agent that was fetched most recently, if any has been recorded
+///
+/// # Errors
+/// Errors if the database query fails
+pub async fn load_latest_agent_symbol() -> Result<Option<String>> {
+    let row = sqlx::query("SELECT symbol FROM agents ORDER BY updated_at DESC LIMIT 1")
+        .fetch_optional(get_global_db_pool().await)
+        .await?;
+
+    Ok(row.map(|row| row.try_get("symbol")).transpose()?)
+}
+
+/// Records the token of an agent, so it can be switched to later
+///
+/// # Errors
+/// Errors if the database query fails
+pub async fn save_agent_token(symbol: &str, token: &str) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO agent_tokens (symbol, token, updated_at) VALUES ($1, $2, $3)
+             ON CONFLICT (symbol) DO
+                UPDATE SET token = EXCLUDED.token, updated_at = EXCLUDED.updated_at",
+    )
+    .bind(symbol)
+    .bind(token)
+    .bind(Utc::now())
+    .execute(get_global_db_pool().await)
+    .await?;
+
+    Ok(())
+}
+
+/// Loads the recorded agent symbols and their tokens
+///
+/// # Errors
+/// Errors if the database query fails
+pub async fn load_agent_tokens() -> Result<Vec<(String, String)>> {
+    let rows = sqlx::query("SELECT symbol, token FROM agent_tokens ORDER BY symbol")
+        .fetch_all(get_global_db_pool().await)
+        .await?;
+
+    rows.iter()
+        .map(|row| Ok((row.try_get("symbol")?, row.try_get("token")?)))
+        .collect()
+}
+
+/// Tables holding data from one reset of the server, which is archived once it resets
+const RESET_TABLES: [&str; 14] = [
+    "agents",
+    "agent_snapshots",
+    "agent_tokens",
+    "ships",
+    "ship_snapshots",
+    "contracts",
+    "factions",
+    "systems",
+    "waypoints",
+    "markets",
+    "market_prices",
+    "shipyards",
+    "transactions",
+    "jump_gates",
+];
+
+/// Reset date of the server when our data was fetched, if it has been recorded
+///
+/// # Errors
+/// Errors if the database query fails
+pub async fn load_reset_date() -> Result<Option<String>> {
+    let row = sqlx::query("SELECT reset_date FROM server_resets ORDER BY recorded_at DESC LIMIT 1")
+        .fetch_optional(get_global_db_pool().await)
+        .await?;
+
+    Ok(row.map(|row| row.try_get("reset_date")).transpose()?)
+}
+
+/// Records the server's reset date, so we can tell when it resets again
+///
+/// # Errors
+/// Errors if the database query fails
+pub async fn save_reset_date(reset_date: &str) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO server_resets (reset_date, recorded_at) VALUES ($1, $2)
+             ON CONFLICT (reset_date) DO NOTHING",
+    )
+    .bind(reset_date)
+    .bind(Utc::now())
+    .execute(get_global_db_pool().await)
+    .await?;
+
+    Ok(())
+}
+
+/// Moves data from before the server reset into tables named after the reset it was from,
+/// e.g. `ships_2023_06_03`, and records the current reset date
+///
+/// # Errors
+/// Errors if a database query fails, in which case nothing is moved
+pub async fn archive_reset(previous: &str, current: &str) -> Result<()> {
+    // Reset dates come from the server, so keep only what is safe in a table name
+    let suffix: String = previous
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+
+    let mut tx = get_global_db_pool().await.begin().await?;
+    for table in RESET_TABLES {
+        sqlx::query(&format!(
+            "CREATE TABLE {table}_{suffix} AS SELECT * FROM {table}"
+        ))
+        .execute(&mut tx)
+        .await?;
+        sqlx::query(&format!("DELETE FROM {table}"))
+            .execute(&mut tx)
+            .await?;
+    }
+    sqlx::query(
+        "INSERT INTO server_resets (reset_date, recorded_at) VALUES ($1, $2)
+             ON CONFLICT (reset_date) DO NOTHING",
+    )
+    .bind(current)
+    .bind(Utc::now())
+    .execute(&mut tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(())
+}
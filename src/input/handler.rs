@@ -1,4 +1,4 @@
-use crate::app::{App, Tab};
+use crate::app::{App, PromptAction, Tab};
 use anyhow::Result;
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 
@@ -7,6 +7,24 @@ use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 /// # Errors
 /// Currently never errors
 pub async fn handle_key_events(key_event: KeyEvent, app: &mut App) -> Result<()> {
+    // An open popup captures all input
+    if app.state.prompt.is_some() {
+        handle_prompt_key_events(key_event, app).await;
+        return Ok(());
+    }
+    if app.state.picker.is_some() {
+        handle_picker_key_events(key_event, app).await;
+        return Ok(());
+    }
+    if app.state.confirm.is_some() {
+        handle_confirm_key_events(key_event, app).await;
+        return Ok(());
+    }
+    if app.state.show_notifications {
+        handle_notifications_key_events(key_event, app);
+        return Ok(());
+    }
+
     match key_event.code {
         // Exit application on `ESC` or `q`
         KeyCode::Esc | KeyCode::Char('q') => {
@@ -18,31 +36,176 @@ pub async fn handle_key_events(key_event: KeyEvent, app: &mut App) -> Result<()>
                 app.quit();
             }
         }
+        KeyCode::Char('!') => app.toggle_notifications(),
+        KeyCode::Char('l' | 'L') => app.prompt_rate_limits(),
+        KeyCode::Char('x' | 'X') => app.pick_event_to_cancel(),
+        KeyCode::Char('a' | 'A') => app.pick_agent(),
         // Tab-switching
         KeyCode::Char('1') => app.state.tab = Tab::Agent,
-        KeyCode::Char('2') => app.state.tab = Tab::Systems,
+        KeyCode::Char('2') => app.open_systems_tab().await,
         KeyCode::Char('3') => app.state.tab = Tab::Fleet,
-        // List navigation
-        KeyCode::Up => app.list_prev(),
-        KeyCode::Down => app.list_next(),
+        KeyCode::Char('4') => app.state.tab = Tab::Market,
+        KeyCode::Char('5') => app.state.tab = Tab::Shipyard,
+        KeyCode::Char('6') => app.open_trade_tab().await,
+        // List navigation, unless the arrow keys are panning the map
+        KeyCode::Up if !app.map_focused() => app.list_prev(),
+        KeyCode::Down if !app.map_focused() => app.list_next(),
         // Tab-specific behavior
-        key => match app.state.tab {
-            Tab::Agent => match key {
-                KeyCode::Char('r' | 'R') => {
-                    app.update_agent_tab().await;
-                }
-                KeyCode::Enter => {
-                    app.accept_or_fulfull_contract().await;
-                }
-                _ => {}
-            },
-            Tab::Systems => {
-                //TODO: Systems controls
-            }
-            Tab::Fleet => {
-                //TODO: Fleet controls
+        key => handle_tab_key_events(key, app).await,
+    }
+    Ok(())
+}
+
+/// Handles key events specific to the current tab
+async fn handle_tab_key_events(key: KeyCode, app: &mut App) {
+    match app.state.tab {
+        Tab::Agent => match key {
+            KeyCode::Char('r' | 'R') => {
+                app.update_agent_tab().await;
+            }
+            KeyCode::Enter => app.accept_or_fulfull_contract(),
+            KeyCode::Char('d' | 'D') => app.pick_contract_delivery(),
+            KeyCode::Char('n' | 'N') => app.pick_contract_negotiator(),
+            _ => {}
+        },
+        Tab::Systems => match key {
+            KeyCode::Char('r' | 'R') => {
+                app.update_systems_tab().await;
+            }
+            KeyCode::Char('g' | 'G') => {
+                app.open_prompt("Go to system", PromptAction::GotoSystem);
             }
+            KeyCode::Char('h' | 'H') => {
+                app.view_headquarters_system().await;
+            }
+            KeyCode::Char('s') => app.cycle_waypoints_sort(),
+            KeyCode::Char('S') => app.reverse_waypoints_sort(),
+            KeyCode::Char('m' | 'M') => app.toggle_map_focus(),
+            KeyCode::Enter if !app.state.map.focused => {
+                app.view_selected_waypoint_market().await;
+            }
+            KeyCode::Char('y' | 'Y') => app.view_selected_waypoint_shipyard().await,
+            _ if app.state.map.focused => handle_map_key_events(key, app).await,
+            _ => {}
+        },
+        Tab::Fleet => match key {
+            KeyCode::Char('r' | 'R') => {
+                app.update_fleet_tab().await;
+            }
+            KeyCode::Char('o' | 'O') => app.orbit_selected_ship().await,
+            KeyCode::Char('d' | 'D') => app.dock_selected_ship().await,
+            KeyCode::Char('n' | 'N') => app.pick_navigation_destination().await,
+            KeyCode::Char('f' | 'F') => app.pick_flight_mode(),
+            KeyCode::Char('m' | 'M') => app.view_selected_ship_market().await,
+            KeyCode::Char('y' | 'Y') => app.view_selected_ship_shipyard().await,
+            KeyCode::Char('s' | 'S') => app.survey_with_selected_ship().await,
+            KeyCode::Char('e' | 'E') => app.extract_with_selected_ship().await,
+            KeyCode::Char('j' | 'J') => app.pick_jettison_cargo(),
+            KeyCode::Char('p' | 'P') => app.pick_refine_product(),
+            KeyCode::PageUp => app.scroll_activity_log(5),
+            KeyCode::PageDown => app.scroll_activity_log(-5),
+            _ => {}
+        },
+        Tab::Market => match key {
+            KeyCode::Char('r' | 'R') => {
+                app.update_market_tab().await;
+            }
+            KeyCode::Char('b' | 'B') => app.prompt_purchase_cargo(),
+            KeyCode::Char('s' | 'S') => app.prompt_sell_cargo(),
+            _ => {}
+        },
+        Tab::Shipyard => match key {
+            KeyCode::Char('r' | 'R') => {
+                app.update_shipyard_tab().await;
+            }
+            KeyCode::Char('b' | 'B') => app.confirm_purchase_ship(),
+            _ => {}
+        },
+        Tab::Trade => match key {
+            KeyCode::Char('r' | 'R') => {
+                app.update_trade_tab().await;
+            }
+            KeyCode::Char('f' | 'F') => app.cycle_trade_flight_mode(),
+            KeyCode::Enter => app.confirm_trade_route(),
+            _ => {}
         },
     }
-    Ok(())
+}
+
+/// Handles key events for the star map while it is focused
+async fn handle_map_key_events(key: KeyCode, app: &mut App) {
+    const PAN_STEP: f64 = 0.2;
+    const ZOOM_STEP: f64 = 1.5;
+    match key {
+        KeyCode::Up => app.pan_map(0.0, -PAN_STEP),
+        KeyCode::Down => app.pan_map(0.0, PAN_STEP),
+        KeyCode::Left => app.pan_map(-PAN_STEP, 0.0),
+        KeyCode::Right => app.pan_map(PAN_STEP, 0.0),
+        KeyCode::Char('+' | '=') => app.zoom_map(ZOOM_STEP),
+        KeyCode::Char('-' | '_') => app.zoom_map(1.0 / ZOOM_STEP),
+        KeyCode::Char('0') => app.reset_map(),
+        KeyCode::Char('v' | 'V') => app.toggle_galaxy_map().await,
+        KeyCode::Enter if app.state.map.galaxy => app.view_system_at_map_center().await,
+        _ => {}
+    }
+}
+
+/// Handles key events while the notification history is open
+fn handle_notifications_key_events(key_event: KeyEvent, app: &mut App) {
+    match key_event.code {
+        KeyCode::Esc | KeyCode::Char('!' | 'q') => app.toggle_notifications(),
+        KeyCode::Char('c' | 'C') if key_event.modifiers == KeyModifiers::CONTROL => {
+            app.toggle_notifications();
+        }
+        KeyCode::Up => app.scroll_notifications(1),
+        KeyCode::Down => app.scroll_notifications(-1),
+        KeyCode::PageUp => app.scroll_notifications(10),
+        KeyCode::PageDown => app.scroll_notifications(-10),
+        _ => {}
+    }
+}
+
+/// Handles key events while a confirmation dialog is open
+async fn handle_confirm_key_events(key_event: KeyEvent, app: &mut App) {
+    match key_event.code {
+        KeyCode::Char('y' | 'Y') | KeyCode::Enter => app.submit_confirm().await,
+        KeyCode::Char('n' | 'N') | KeyCode::Esc => app.state.confirm = None,
+        KeyCode::Char('c' | 'C') if key_event.modifiers == KeyModifiers::CONTROL => {
+            app.state.confirm = None;
+        }
+        _ => {}
+    }
+}
+
+/// Handles key events while a picker is open
+async fn handle_picker_key_events(key_event: KeyEvent, app: &mut App) {
+    match key_event.code {
+        KeyCode::Esc => app.state.picker = None,
+        KeyCode::Char('c' | 'C') if key_event.modifiers == KeyModifiers::CONTROL => {
+            app.state.picker = None;
+        }
+        KeyCode::Enter => app.submit_picker().await,
+        KeyCode::Up => app.picker_move(-1),
+        KeyCode::Down => app.picker_move(1),
+        _ => {}
+    }
+}
+
+/// Handles key events while a text prompt is open
+async fn handle_prompt_key_events(key_event: KeyEvent, app: &mut App) {
+    let Some(prompt) = app.state.prompt.as_mut() else {
+        return;
+    };
+    match key_event.code {
+        KeyCode::Esc => app.state.prompt = None,
+        KeyCode::Enter => app.submit_prompt().await,
+        KeyCode::Backspace => {
+            prompt.input.pop();
+        }
+        KeyCode::Char('c' | 'C') if key_event.modifiers == KeyModifiers::CONTROL => {
+            app.state.prompt = None;
+        }
+        KeyCode::Char(c) => prompt.input.push(c),
+        _ => {}
+    }
 }
@@ -1,11 +1,243 @@
+use std::fmt;
+
+use spacedust::models::{
+    register_request::Faction, ship_refine_request::Produce, ShipNavFlightMode, ShipType, Survey,
+};
+
+use crate::{profile::Profile, rate_limit::Priority, st_util, trade::TradeRoute};
+
 pub mod handler;
+pub mod runner;
 
 #[allow(clippy::module_name_repetitions)]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum IoEvent {
     UpdateAgent,
     UpdateContracts,
     UpdateFactions,
+    UpdateShips,
+    UpdateSystems,
+    UpdateSystem(String),
+    UpdateWaypoints(String),
     AcceptContract(String),
     FulfillContract(String),
+    UpdateShipNav(String),
+    OrbitShip(String),
+    DockShip(String),
+    NavigateShip {
+        ship: String,
+        waypoint: String,
+    },
+    SetFlightMode {
+        ship: String,
+        mode: ShipNavFlightMode,
+    },
+    UpdateMarket(String),
+    PurchaseCargo {
+        ship: String,
+        good: String,
+        units: i32,
+    },
+    SellCargo {
+        ship: String,
+        good: String,
+        units: i32,
+    },
+    UpdateShipyard(String),
+    PurchaseShip {
+        ship_type: ShipType,
+        waypoint: String,
+    },
+    CreateSurvey(String),
+    ExtractResources {
+        ship: String,
+        survey: Option<Survey>,
+    },
+    Jettison {
+        ship: String,
+        good: String,
+        units: i32,
+    },
+    RefineCargo {
+        ship: String,
+        produce: Produce,
+    },
+    DeliverContract {
+        contract: String,
+        ship: String,
+        good: String,
+        units: i32,
+    },
+    NegotiateContract(String),
+    UpdateTradeData(String),
+    RunTradeRoute {
+        ship: String,
+        route: TradeRoute,
+    },
+    RegisterAgent {
+        symbol: String,
+        faction: Faction,
+    },
+    /// Checks whether the server has reset since our data was fetched,
+    /// fetching the agent, its contracts, the factions and the fleet if it hasn't
+    CheckServerStatus,
+    /// Archives data from the reset on `previous`, to start over in the reset on `current`
+    ArchiveReset {
+        previous: String,
+        current: String,
+    },
+}
+
+/// An event dispatched on behalf of an agent, run with its token and applied to its state
+#[derive(Clone, Debug, PartialEq)]
+pub struct AgentEvent {
+    /// agent the event is for, or `None` to use `TOKEN` from the settings
+    pub profile: Option<Profile>,
+    pub event: IoEvent,
+}
+
+impl AgentEvent {
+    /// Symbol of the agent the event is for, if one was chosen
+    #[must_use]
+    pub fn agent(&self) -> Option<&str> {
+        self.profile.as_ref().map(|p| p.symbol.as_str())
+    }
+}
+
+impl IoEvent {
+    /// Whether the event only fetches data, rather than taking an action in the game
+    #[must_use]
+    pub fn is_refresh(&self) -> bool {
+        matches!(
+            self,
+            Self::UpdateAgent
+                | Self::UpdateContracts
+                | Self::UpdateFactions
+                | Self::UpdateShips
+                | Self::UpdateSystems
+                | Self::UpdateSystem(_)
+                | Self::UpdateWaypoints(_)
+                | Self::UpdateShipNav(_)
+                | Self::UpdateMarket(_)
+                | Self::UpdateShipyard(_)
+                | Self::UpdateTradeData(_)
+                | Self::CheckServerStatus
+        )
+    }
+
+    /// Whether the event runs a ship on its own for a long time, like a trade route
+    #[must_use]
+    pub fn is_automation(&self) -> bool {
+        matches!(self, Self::RunTradeRoute { .. })
+    }
+
+    /// Whether the event acts as an agent, so can't run until one is registered
+    #[must_use]
+    pub fn needs_agent(&self) -> bool {
+        !matches!(
+            self,
+            Self::UpdateFactions
+                | Self::RegisterAgent { .. }
+                | Self::CheckServerStatus
+                | Self::ArchiveReset { .. }
+        )
+    }
+
+    /// Priority the event's requests are made at
+    #[must_use]
+    pub fn priority(&self) -> Priority {
+        if self.is_refresh() {
+            Priority::Background
+        } else if self.is_automation() {
+            Priority::Automation
+        } else {
+            Priority::Interactive
+        }
+    }
+
+    /// Ship whose state the event depends on or changes, if any.
+    /// Events for the same ship are run one at a time, in the order they were dispatched.
+    #[must_use]
+    pub fn ship(&self) -> Option<&str> {
+        match self {
+            Self::UpdateShipNav(ship)
+            | Self::OrbitShip(ship)
+            | Self::DockShip(ship)
+            | Self::NavigateShip { ship, .. }
+            | Self::SetFlightMode { ship, .. }
+            | Self::PurchaseCargo { ship, .. }
+            | Self::SellCargo { ship, .. }
+            | Self::CreateSurvey(ship)
+            | Self::ExtractResources { ship, .. }
+            | Self::Jettison { ship, .. }
+            | Self::RefineCargo { ship, .. }
+            | Self::DeliverContract { ship, .. }
+            | Self::NegotiateContract(ship)
+            | Self::RunTradeRoute { ship, .. } => Some(ship),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for IoEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UpdateAgent => write!(f, "Update agent"),
+            Self::UpdateContracts => write!(f, "Update contracts"),
+            Self::UpdateFactions => write!(f, "Update factions"),
+            Self::UpdateShips => write!(f, "Update ships"),
+            Self::UpdateSystems => write!(f, "Update systems"),
+            Self::UpdateSystem(system) => write!(f, "Update system {system}"),
+            Self::UpdateWaypoints(system) => write!(f, "Update waypoints in {system}"),
+            Self::AcceptContract(id) => write!(f, "Accept contract {id}"),
+            Self::FulfillContract(id) => write!(f, "Fulfill contract {id}"),
+            Self::UpdateShipNav(ship) => write!(f, "Update {ship} nav"),
+            Self::OrbitShip(ship) => write!(f, "Orbit {ship}"),
+            Self::DockShip(ship) => write!(f, "Dock {ship}"),
+            Self::NavigateShip { ship, waypoint } => write!(f, "Navigate {ship} to {waypoint}"),
+            Self::SetFlightMode { ship, mode } => {
+                write!(f, "Set {ship} flight mode to {}", mode.to_string())
+            }
+            Self::UpdateMarket(waypoint) => write!(f, "Update market at {waypoint}"),
+            Self::PurchaseCargo { ship, good, units } => {
+                write!(f, "Buy {units} {good} with {ship}")
+            }
+            Self::SellCargo { ship, good, units } => write!(f, "Sell {units} {good} from {ship}"),
+            Self::UpdateShipyard(waypoint) => write!(f, "Update shipyard at {waypoint}"),
+            Self::PurchaseShip {
+                ship_type,
+                waypoint,
+            } => write!(f, "Buy {} at {waypoint}", ship_type.to_string()),
+            Self::CreateSurvey(ship) => write!(f, "Survey with {ship}"),
+            Self::ExtractResources { ship, .. } => write!(f, "Extract with {ship}"),
+            Self::Jettison { ship, good, units } => {
+                write!(f, "Jettison {units} {good} from {ship}")
+            }
+            Self::RefineCargo { ship, produce } => write!(
+                f,
+                "Refine {} with {ship}",
+                st_util::produce_to_string(*produce)
+            ),
+            Self::DeliverContract {
+                contract,
+                ship,
+                good,
+                units,
+            } => write!(f, "Deliver {units} {good} from {ship} for {contract}"),
+            Self::NegotiateContract(ship) => write!(f, "Negotiate a contract with {ship}"),
+            Self::UpdateTradeData(system) => write!(f, "Update trade data for {system}"),
+            Self::RunTradeRoute { ship, route } => {
+                write!(f, "Trade {} with {ship}", route.good)
+            }
+            Self::RegisterAgent { symbol, faction } => write!(
+                f,
+                "Register {symbol} with {}",
+                st_util::starting_faction_to_string(*faction)
+            ),
+            Self::CheckServerStatus => write!(f, "Check server status"),
+            Self::ArchiveReset { previous, .. } => {
+                write!(f, "Archive data from the reset on {previous}")
+            }
+        }
+    }
 }
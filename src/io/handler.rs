@@ -1,20 +1,48 @@
-use std::sync::Arc;
+use std::{slice, time::Duration};
 
-use anyhow::Result;
-use spacedust::apis::agents_api::get_my_agent;
-use tokio::sync::Mutex;
-
-use crate::{app::App, config::CONFIGURATION, st_util};
+use crate::{
+    api_error::ApiError,
+    app::{App, AppHandle, Section, PRICE_HISTORY_DAYS},
+    config::CONFIGURATION,
+    db_util,
+    profile::{self, Profile},
+    st_util,
+    trade::{Jump, TradeData, TradeRoute},
+};
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use log::info;
+use spacedust::{
+    apis::{
+        agents_api::get_my_agent,
+        contracts_api::deliver_contract,
+        default_api::register,
+        fleet_api::{
+            create_survey, dock_ship, extract_resources, get_ship_nav, jettison, jump_ship,
+            navigate_ship, orbit_ship, patch_ship_nav, purchase_cargo, purchase_ship, refuel_ship,
+            sell_cargo, ship_refine,
+        },
+        systems_api::{get_jump_gate, get_market, get_shipyard, get_system},
+    },
+    models::{
+        register_request::Faction, ship_refine_request::Produce, AcceptContract200ResponseData,
+        Cooldown, DeliverContractRequest, ExtractResourcesRequest, JettisonRequest, JumpGate,
+        JumpShipRequest, NavigateShipRequest, PatchShipNavRequest, PurchaseCargoRequest,
+        PurchaseShipRequest, RegisterRequest, SellCargo201ResponseData, SellCargoRequest, Ship,
+        ShipNav, ShipNavFlightMode, ShipNavStatus, ShipRefineRequest, ShipType, Survey,
+    },
+};
 
 use super::IoEvent;
 
 #[allow(clippy::module_name_repetitions)]
 pub struct IoHandler {
-    app: Arc<Mutex<App>>,
+    app: AppHandle,
 }
 
 impl IoHandler {
-    pub fn new(app: Arc<Mutex<App>>) -> Self {
+    #[must_use]
+    pub fn new(app: AppHandle) -> Self {
         Self { app }
     }
 
@@ -27,67 +55,925 @@ impl IoHandler {
             IoEvent::UpdateAgent => self.update_agent().await?,
             IoEvent::UpdateContracts => self.update_contracts().await?,
             IoEvent::UpdateFactions => self.update_factions().await?,
+            IoEvent::UpdateShips => self.update_ships().await?,
+            IoEvent::UpdateSystems => self.update_systems().await?,
+            IoEvent::UpdateSystem(symbol) => self.update_system(&symbol).await?,
+            IoEvent::UpdateWaypoints(symbol) => self.update_waypoints(&symbol).await?,
             IoEvent::AcceptContract(id) => self.accept_contract(&id).await?,
             IoEvent::FulfillContract(id) => self.fulfill_contract(&id).await?,
+            IoEvent::UpdateShipNav(ship) => self.update_ship_nav(&ship).await?,
+            IoEvent::OrbitShip(ship) => self.orbit_ship(&ship).await?,
+            IoEvent::DockShip(ship) => self.dock_ship(&ship).await?,
+            IoEvent::NavigateShip { ship, waypoint } => {
+                self.navigate_ship(&ship, waypoint).await?;
+            }
+            IoEvent::SetFlightMode { ship, mode } => self.set_flight_mode(&ship, mode).await?,
+            IoEvent::UpdateMarket(waypoint) => self.update_market(&waypoint).await?,
+            IoEvent::PurchaseCargo { ship, good, units } => {
+                self.purchase_cargo(&ship, good, units).await?;
+            }
+            IoEvent::SellCargo { ship, good, units } => self.sell_cargo(&ship, good, units).await?,
+            IoEvent::UpdateShipyard(waypoint) => self.update_shipyard(&waypoint).await?,
+            IoEvent::PurchaseShip {
+                ship_type,
+                waypoint,
+            } => self.purchase_ship(ship_type, waypoint).await?,
+            IoEvent::CreateSurvey(ship) => self.create_survey(&ship).await?,
+            IoEvent::ExtractResources { ship, survey } => {
+                self.extract_resources(&ship, survey).await?;
+            }
+            IoEvent::Jettison { ship, good, units } => self.jettison(&ship, good, units).await?,
+            IoEvent::RefineCargo { ship, produce } => self.refine_cargo(&ship, produce).await?,
+            IoEvent::DeliverContract {
+                contract,
+                ship,
+                good,
+                units,
+            } => self.deliver_contract(&contract, &ship, good, units).await?,
+            IoEvent::NegotiateContract(ship) => self.negotiate_contract(&ship).await?,
+            IoEvent::UpdateTradeData(system) => self.update_trade_data(&system).await?,
+            IoEvent::RunTradeRoute { ship, route } => self.run_trade_route(&ship, &route).await?,
+            IoEvent::RegisterAgent { symbol, faction } => {
+                self.register_agent(symbol, faction).await?;
+            }
+            IoEvent::CheckServerStatus => self.check_server_status().await?,
+            IoEvent::ArchiveReset { previous, current } => {
+                self.archive_reset(previous, &current).await?;
+            }
         }
 
         Ok(())
     }
 
     async fn update_agent(&mut self) -> Result<()> {
-        let agent = get_my_agent(&CONFIGURATION).await?.data;
-
-        let mut app = self.app.lock().await;
-        app.state.agent = *agent;
-
-        // sqlx::query!(
-        //     "INSERT INTO agents(account_id, symbol, headquarters, credits)
-        //          VALUES ($1, $2, $3, $4)
-        //          ON CONFLICT (symbol) DO
-        //             UPDATE SET credits = EXCLUDED.credits",
-        //     agent.account_id,
-        //     agent.symbol,
-        //     agent.symbol,
-        //     agent.credits
-        // )
-        // .execute(get_global_db_pool().await)
-        // .await?;
+        let agent = get_my_agent(&CONFIGURATION)
+            .await
+            .map_err(ApiError::from)?
+            .data;
+
+        let fetched = (*agent).clone();
+        self.app
+            .update(move |app| {
+                app.state.agent = fetched;
+                app.mark_fresh(Section::Agent);
+            })
+            .await?;
+        db_util::save_agent(&agent).await?;
 
         Ok(())
     }
 
     async fn update_contracts(&mut self) -> Result<()> {
-        let contracts = st_util::list_contracts().await?;
+        let contracts = st_util::list_contracts().await.map_err(ApiError::from)?;
 
-        let mut app = self.app.lock().await;
-        if contracts.is_empty() {
-            app.state.contracts_list_state.select(None);
-        } else {
-            app.state.contracts_list_state.select(Some(0));
-        }
-        app.state.contracts = contracts;
+        let fetched = contracts.clone();
+        self.app
+            .update(move |app| {
+                if fetched.is_empty() {
+                    app.state.contracts_list_state.select(None);
+                } else {
+                    app.state.contracts_list_state.select(Some(0));
+                }
+                app.state.contracts = fetched;
+                app.mark_fresh(Section::Contracts);
+            })
+            .await?;
+        db_util::save_contracts(&self.agent_symbol()?, &contracts).await?;
 
         Ok(())
     }
 
     async fn update_factions(&mut self) -> Result<()> {
-        let factions = st_util::list_factions().await?;
+        let factions = st_util::list_factions().await.map_err(ApiError::from)?;
 
-        let mut app = self.app.lock().await;
-        app.state.factions = factions;
+        let fetched = factions.clone();
+        self.app
+            .update(move |app| {
+                app.state.factions = fetched;
+                // The faction picker labels factions with what we know of them
+                app.refresh_picker();
+            })
+            .await?;
+        db_util::save_factions(&factions).await?;
 
         Ok(())
     }
 
-    async fn accept_contract(&mut self, id: &str) -> Result<()> {
-        spacedust::apis::contracts_api::accept_contract(&CONFIGURATION, id, 0).await?;
+    async fn update_ships(&mut self) -> Result<()> {
+        let ships = st_util::list_ships().await.map_err(ApiError::from)?;
+
+        let fetched = ships.clone();
+        self.app
+            .update(move |app| {
+                // Keep the current selection if it still points at a ship
+                let selected = match app.state.ships_list_state.selected() {
+                    Some(index) if index < fetched.len() => Some(index),
+                    _ if fetched.is_empty() => None,
+                    _ => Some(0),
+                };
+                app.state.ships_list_state.select(selected);
+                app.state.ships = fetched;
+                app.mark_fresh(Section::Ships);
+            })
+            .await?;
+        db_util::save_ships(&ships).await?;
+
+        Ok(())
+    }
+
+    async fn update_systems(&mut self) -> Result<()> {
+        let systems = st_util::list_systems().await.map_err(ApiError::from)?;
+
+        let fetched = systems.clone();
+        self.app
+            .update(move |app| {
+                app.state.systems = fetched;
+                app.state.all_systems_loaded = true;
+            })
+            .await?;
+        db_util::save_systems(&systems).await?;
 
         Ok(())
     }
 
+    async fn update_system(&mut self, symbol: &str) -> Result<()> {
+        let system = *get_system(&CONFIGURATION, symbol)
+            .await
+            .map_err(ApiError::from)?
+            .data;
+
+        let fetched = system.clone();
+        self.app
+            .update(move |app| {
+                let systems = &mut app.state.systems;
+                if let Some(known) = systems.iter_mut().find(|s| s.symbol == fetched.symbol) {
+                    *known = fetched;
+                } else {
+                    systems.push(fetched);
+                }
+            })
+            .await?;
+        db_util::save_systems(&[system]).await?;
+
+        Ok(())
+    }
+
+    async fn update_waypoints(&mut self, system_symbol: &str) -> Result<()> {
+        let waypoints = st_util::list_system_waypoints(system_symbol)
+            .await
+            .map_err(ApiError::from)?;
+        db_util::save_waypoints(&waypoints).await?;
+
+        let system_symbol = system_symbol.to_owned();
+        self.app
+            .update(move |app| {
+                // The user may have moved on to another system in the meantime
+                if app.state.system_symbol != system_symbol {
+                    return;
+                }
+                // Keep the same waypoint selected across refreshes
+                let selected = app
+                    .state
+                    .waypoints_table_state
+                    .selected()
+                    .and_then(|index| app.state.waypoints.get(index))
+                    .map(|w| w.symbol.clone());
+                app.state.waypoints = waypoints;
+                app.mark_fresh(Section::Waypoints);
+                app.state.waypoints_table_state.select(None);
+                app.sort_waypoints();
+                let index = selected
+                    .and_then(|symbol| app.state.waypoints.iter().position(|w| w.symbol == symbol))
+                    .or(if app.state.waypoints.is_empty() {
+                        None
+                    } else {
+                        Some(0)
+                    });
+                app.state.waypoints_table_state.select(index);
+                app.refresh_picker();
+            })
+            .await
+    }
+
+    async fn accept_contract(&mut self, id: &str) -> Result<()> {
+        let data = *spacedust::apis::contracts_api::accept_contract(&CONFIGURATION, id, 0)
+            .await
+            .map_err(ApiError::from)?
+            .data;
+        self.apply_contract_update(data).await
+    }
+
     async fn fulfill_contract(&mut self, id: &str) -> Result<()> {
-        spacedust::apis::contracts_api::fulfill_contract(&CONFIGURATION, id, 0).await?;
+        let data = *spacedust::apis::contracts_api::fulfill_contract(&CONFIGURATION, id, 0)
+            .await
+            .map_err(ApiError::from)?
+            .data;
+        self.apply_contract_update(data).await
+    }
+
+    /// Applies the agent and contract returned by accepting or fulfilling a contract
+    async fn apply_contract_update(&mut self, data: AcceptContract200ResponseData) -> Result<()> {
+        let (agent, updated) = ((*data.agent).clone(), (*data.contract).clone());
+        self.app
+            .update(move |app| {
+                app.state.agent = agent;
+                if let Some(contract) = app.state.contracts.iter_mut().find(|c| c.id == updated.id)
+                {
+                    *contract = updated;
+                }
+            })
+            .await?;
+        db_util::save_agent(&data.agent).await?;
+        db_util::save_contracts(&data.agent.symbol, &[*data.contract]).await
+    }
+
+    async fn deliver_contract(
+        &mut self,
+        id: &str,
+        ship_symbol: &str,
+        good: String,
+        units: i32,
+    ) -> Result<()> {
+        let request = DeliverContractRequest::new(ship_symbol.to_owned(), good.clone(), units);
+        let data = *deliver_contract(&CONFIGURATION, id, Some(request))
+            .await
+            .map_err(ApiError::from)?
+            .data;
+
+        let (symbol, id, updated) = (
+            ship_symbol.to_owned(),
+            id.to_owned(),
+            (*data.contract).clone(),
+        );
+        self.app
+            .update(move |app| {
+                app.log_activity(&symbol, format!("Delivered {units} {good} for {id}"));
+                if let Some(ship) = app.ship_mut(&symbol) {
+                    ship.cargo = data.cargo;
+                }
+                if let Some(contract) = app.state.contracts.iter_mut().find(|c| c.id == id) {
+                    *contract = updated;
+                }
+            })
+            .await?;
+        db_util::save_contracts(&self.agent_symbol()?, &[*data.contract]).await?;
+        self.save_ship(ship_symbol).await
+    }
 
+    async fn negotiate_contract(&mut self, ship_symbol: &str) -> Result<()> {
+        let contract = st_util::negotiate_contract(ship_symbol)
+            .await
+            .map_err(ApiError::from)?;
+
+        let (symbol, negotiated) = (ship_symbol.to_owned(), contract.clone());
+        self.app
+            .update(move |app| {
+                app.log_activity(
+                    &symbol,
+                    format!(
+                        "Negotiated contract {} with {}",
+                        negotiated.id, negotiated.faction_symbol
+                    ),
+                );
+                app.state.contracts.push(negotiated);
+                let index = app.state.contracts.len() - 1;
+                app.state.contracts_list_state.select(Some(index));
+            })
+            .await?;
+        db_util::save_contracts(&self.agent_symbol()?, &[contract]).await
+    }
+
+    async fn register_agent(&mut self, symbol: String, faction: Faction) -> Result<()> {
+        // A new agent isn't made by any other, whose token may be from before a reset
+        let request = RegisterRequest::new(faction, symbol);
+        let data = *profile::without_token(register(&CONFIGURATION, Some(request)))
+            .await
+            .map_err(ApiError::from)?
+            .data;
+
+        // The token can't be fetched again, so record it before anything else can fail
+        db_util::save_agent_token(&data.agent.symbol, &data.token).await?;
+        db_util::save_agent(&data.agent).await?;
+        db_util::save_ships(slice::from_ref(&data.ship)).await?;
+        db_util::save_contracts(&data.agent.symbol, slice::from_ref(&data.contract)).await?;
+
+        let profile = Profile {
+            symbol: data.agent.symbol.clone(),
+            token: data.token,
+        };
+        self.app
+            .update(move |app| {
+                app.add_registered_agent(profile, *data.agent, *data.ship, *data.contract);
+            })
+            .await
+    }
+
+    async fn check_server_status(&mut self) -> Result<()> {
+        // Tokens from before a reset are rejected, and the status doesn't need one
+        let status = profile::without_token(st_util::get_status())
+            .await
+            .map_err(ApiError::from)?;
+        match db_util::load_reset_date().await? {
+            Some(previous) if previous != status.reset_date => {
+                info!(
+                    "Server reset on {}, our data is from the reset on {previous}",
+                    status.reset_date
+                );
+                let current = status.reset_date;
+                return self
+                    .app
+                    .update(move |app| app.offer_reset_archive(previous, current))
+                    .await;
+            }
+            Some(_) => {}
+            // Data recorded before reset dates were is assumed to be from the current reset
+            None => db_util::save_reset_date(&status.reset_date).await?,
+        }
+
+        // Nothing to fetch until an agent is registered
+        if profile::current_token().is_some() {
+            self.update_agent().await?;
+            self.update_contracts().await?;
+            self.update_factions().await?;
+            self.update_ships().await?;
+        }
         Ok(())
     }
+
+    async fn archive_reset(&mut self, previous: String, current: &str) -> Result<()> {
+        db_util::archive_reset(&previous, current).await?;
+        profile::forget_settings_token();
+        self.app
+            .update(move |app| app.start_new_reset(&previous))
+            .await?;
+        // The new agent's faction is picked from these
+        self.update_factions().await
+    }
+
+    /// Symbol of the agent the event is for, which its data is recorded under.
+    /// Read from the event rather than the app, since another agent may be shown by now.
+    fn agent_symbol(&self) -> Result<String> {
+        self.app
+            .agent()
+            .map(ToOwned::to_owned)
+            // Without a profile, requests are made with the token from the settings
+            .or_else(|| profile::current_token().and_then(|token| profile::agent_symbol(&token)))
+            .ok_or_else(|| anyhow!("Don't know which agent the data is for"))
+    }
+
+    /// Records the state we hold for a ship after an action changes it
+    async fn save_ship(&mut self, ship_symbol: &str) -> Result<()> {
+        let symbol = ship_symbol.to_owned();
+        let ship = self
+            .app
+            .update(move |app| app.ship(&symbol).cloned())
+            .await?;
+        if let Some(ship) = ship {
+            db_util::save_ships(&[ship]).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the nav state of a known ship
+    async fn set_ship_nav(&mut self, ship_symbol: &str, nav: ShipNav) -> Result<()> {
+        let symbol = ship_symbol.to_owned();
+        self.app
+            .update(move |app| {
+                if let Some(ship) = app.ship_mut(&symbol) {
+                    *ship.nav = nav;
+                }
+            })
+            .await?;
+        self.save_ship(ship_symbol).await
+    }
+
+    async fn update_ship_nav(&mut self, ship_symbol: &str) -> Result<()> {
+        let nav = *get_ship_nav(&CONFIGURATION, ship_symbol)
+            .await
+            .map_err(ApiError::from)?
+            .data;
+        self.set_ship_nav(ship_symbol, nav).await
+    }
+
+    async fn orbit_ship(&mut self, ship_symbol: &str) -> Result<()> {
+        let nav = *orbit_ship(&CONFIGURATION, ship_symbol, 0)
+            .await
+            .map_err(ApiError::from)?
+            .data
+            .nav;
+        self.set_ship_nav(ship_symbol, nav).await
+    }
+
+    async fn dock_ship(&mut self, ship_symbol: &str) -> Result<()> {
+        let nav = *dock_ship(&CONFIGURATION, ship_symbol, 0.0)
+            .await
+            .map_err(ApiError::from)?
+            .data
+            .nav;
+        self.set_ship_nav(ship_symbol, nav).await
+    }
+
+    async fn navigate_ship(&mut self, ship_symbol: &str, waypoint_symbol: String) -> Result<()> {
+        let data = navigate_ship(
+            &CONFIGURATION,
+            ship_symbol,
+            Some(NavigateShipRequest::new(waypoint_symbol)),
+        )
+        .await
+        .map_err(ApiError::from)?
+        .data;
+
+        let symbol = ship_symbol.to_owned();
+        self.app
+            .update(move |app| {
+                if let Some(ship) = app.ship_mut(&symbol) {
+                    ship.nav = data.nav;
+                    ship.fuel = data.fuel;
+                }
+            })
+            .await?;
+        self.save_ship(ship_symbol).await
+    }
+
+    async fn set_flight_mode(&mut self, ship_symbol: &str, mode: ShipNavFlightMode) -> Result<()> {
+        let request = PatchShipNavRequest {
+            flight_mode: Some(mode),
+        };
+        let nav = *patch_ship_nav(&CONFIGURATION, ship_symbol, Some(request))
+            .await
+            .map_err(ApiError::from)?
+            .data;
+        self.set_ship_nav(ship_symbol, nav).await
+    }
+
+    async fn update_market(&mut self, waypoint_symbol: &str) -> Result<()> {
+        let system_symbol = st_util::waypoint_system_symbol(waypoint_symbol);
+        let market = *get_market(&CONFIGURATION, system_symbol, waypoint_symbol)
+            .await
+            .map_err(ApiError::from)?
+            .data;
+        db_util::save_market(&market).await?;
+        let since = Utc::now() - chrono::Duration::days(PRICE_HISTORY_DAYS);
+        let history = db_util::load_price_history(waypoint_symbol, since).await?;
+
+        let waypoint_symbol = waypoint_symbol.to_owned();
+        self.app
+            .update(move |app| {
+                // The user may have moved on to another market in the meantime
+                if app.state.market_symbol != waypoint_symbol {
+                    return;
+                }
+                if app.state.market_table_state.selected().is_none() {
+                    app.state.market_table_state.select(Some(0));
+                }
+                app.state.market = Some(market);
+                app.state.market_history = history;
+            })
+            .await
+    }
+
+    /// Applies the result of buying or selling cargo to the agent and ship
+    async fn apply_trade(
+        &mut self,
+        ship_symbol: &str,
+        data: SellCargo201ResponseData,
+    ) -> Result<()> {
+        let (symbol, agent, cargo) = (ship_symbol.to_owned(), (*data.agent).clone(), data.cargo);
+        self.app
+            .update(move |app| {
+                app.state.agent = agent;
+                if let Some(ship) = app.ship_mut(&symbol) {
+                    ship.cargo = cargo;
+                }
+            })
+            .await?;
+        db_util::save_agent(&data.agent).await?;
+        db_util::save_transactions(&[*data.transaction]).await?;
+        self.save_ship(ship_symbol).await
+    }
+
+    async fn purchase_cargo(&mut self, ship_symbol: &str, good: String, units: i32) -> Result<()> {
+        let request = PurchaseCargoRequest::new(good, units);
+        let data = *purchase_cargo(&CONFIGURATION, ship_symbol, Some(request))
+            .await
+            .map_err(ApiError::from)?
+            .data;
+        let waypoint_symbol = data.transaction.waypoint_symbol.clone();
+        self.apply_trade(ship_symbol, data).await?;
+
+        // Prices move after every trade
+        self.update_market(&waypoint_symbol).await
+    }
+
+    async fn sell_cargo(&mut self, ship_symbol: &str, good: String, units: i32) -> Result<()> {
+        let request = SellCargoRequest::new(good, units);
+        let data = *sell_cargo(&CONFIGURATION, ship_symbol, Some(request))
+            .await
+            .map_err(ApiError::from)?
+            .data;
+        let waypoint_symbol = data.transaction.waypoint_symbol.clone();
+        self.apply_trade(ship_symbol, data).await?;
+
+        // Prices move after every trade
+        self.update_market(&waypoint_symbol).await
+    }
+
+    async fn update_shipyard(&mut self, waypoint_symbol: &str) -> Result<()> {
+        let system_symbol = st_util::waypoint_system_symbol(waypoint_symbol);
+        let shipyard = *get_shipyard(&CONFIGURATION, system_symbol, waypoint_symbol)
+            .await
+            .map_err(ApiError::from)?
+            .data;
+        db_util::save_shipyard(&shipyard).await?;
+
+        let waypoint_symbol = waypoint_symbol.to_owned();
+        self.app
+            .update(move |app| {
+                // The user may have moved on to another shipyard in the meantime
+                if app.state.shipyard_symbol != waypoint_symbol {
+                    return;
+                }
+                if app.state.shipyard_table_state.selected().is_none() {
+                    app.state.shipyard_table_state.select(Some(0));
+                }
+                app.state.shipyard = Some(shipyard);
+            })
+            .await
+    }
+
+    async fn purchase_ship(&mut self, ship_type: ShipType, waypoint_symbol: String) -> Result<()> {
+        let request = PurchaseShipRequest::new(ship_type, waypoint_symbol.clone());
+        let data = *purchase_ship(&CONFIGURATION, Some(request))
+            .await
+            .map_err(ApiError::from)?
+            .data;
+
+        let (agent, ship) = ((*data.agent).clone(), (*data.ship).clone());
+        self.app
+            .update(move |app| {
+                app.state.agent = agent;
+                app.state.ships.push(ship);
+                if app.state.ships_list_state.selected().is_none() {
+                    app.state.ships_list_state.select(Some(0));
+                }
+            })
+            .await?;
+        db_util::save_agent(&data.agent).await?;
+        db_util::save_ships(std::slice::from_ref(&data.ship)).await?;
+        db_util::save_ship_purchase(&data.ship, ship_type, &data.transaction).await?;
+
+        self.update_shipyard(&waypoint_symbol).await
+    }
+
+    async fn create_survey(&mut self, ship_symbol: &str) -> Result<()> {
+        let data = *create_survey(&CONFIGURATION, ship_symbol, 0)
+            .await
+            .map_err(ApiError::from)?
+            .data;
+
+        let symbol = ship_symbol.to_owned();
+        self.app
+            .update(move |app| {
+                for survey in &data.surveys {
+                    let deposits: Vec<&str> =
+                        survey.deposits.iter().map(|d| d.symbol.as_str()).collect();
+                    app.log_activity(
+                        &symbol,
+                        format!(
+                            "Surveyed {} ({}): {}",
+                            survey.symbol,
+                            st_util::survey_size_to_string(survey.size),
+                            deposits.join(", ")
+                        ),
+                    );
+                }
+                app.state.surveys.extend(data.surveys);
+                set_cooldown(app, *data.cooldown);
+            })
+            .await
+    }
+
+    async fn extract_resources(&mut self, ship_symbol: &str, survey: Option<Survey>) -> Result<()> {
+        let request = ExtractResourcesRequest {
+            survey: survey.map(Box::new),
+        };
+        let data = *extract_resources(&CONFIGURATION, ship_symbol, Some(request))
+            .await
+            .map_err(ApiError::from)?
+            .data;
+
+        let symbol = ship_symbol.to_owned();
+        self.app
+            .update(move |app| {
+                let extracted = &data.extraction.r#yield;
+                app.log_activity(
+                    &symbol,
+                    format!("Extracted {} {}", extracted.units, extracted.symbol),
+                );
+                if let Some(ship) = app.ship_mut(&symbol) {
+                    ship.cargo = data.cargo;
+                }
+                set_cooldown(app, *data.cooldown);
+            })
+            .await?;
+        self.save_ship(ship_symbol).await
+    }
+
+    async fn jettison(&mut self, ship_symbol: &str, good: String, units: i32) -> Result<()> {
+        let request = JettisonRequest::new(good.clone(), units);
+        let data = *jettison(&CONFIGURATION, ship_symbol, Some(request))
+            .await
+            .map_err(ApiError::from)?
+            .data;
+
+        let symbol = ship_symbol.to_owned();
+        self.app
+            .update(move |app| {
+                app.log_activity(&symbol, format!("Jettisoned {units} {good}"));
+                if let Some(ship) = app.ship_mut(&symbol) {
+                    ship.cargo = data.cargo;
+                }
+            })
+            .await?;
+        self.save_ship(ship_symbol).await
+    }
+
+    async fn refine_cargo(&mut self, ship_symbol: &str, produce: Produce) -> Result<()> {
+        let request = ShipRefineRequest::new(produce);
+        let data = *ship_refine(&CONFIGURATION, ship_symbol, Some(request))
+            .await
+            .map_err(ApiError::from)?
+            .data;
+
+        let describe = |goods: &[spacedust::models::ShipRefine200ResponseDataProducedInner]| {
+            goods
+                .iter()
+                .map(|g| {
+                    format!(
+                        "{} {}",
+                        g.units.unwrap_or(0),
+                        g.trade_symbol.as_deref().unwrap_or("?")
+                    )
+                })
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+        let (symbol, activity) = (
+            ship_symbol.to_owned(),
+            format!(
+                "Refined {} into {}",
+                describe(&data.consumed),
+                describe(&data.produced)
+            ),
+        );
+        self.app
+            .update(move |app| {
+                app.log_activity(&symbol, activity);
+                if let Some(ship) = app.ship_mut(&symbol) {
+                    ship.cargo = data.cargo;
+                }
+                set_cooldown(app, *data.cooldown);
+            })
+            .await?;
+        self.save_ship(ship_symbol).await
+    }
+
+    async fn jump_ship(&mut self, ship_symbol: &str, system_symbol: &str) -> Result<()> {
+        let request = JumpShipRequest::new(system_symbol.to_owned());
+        let data = *jump_ship(&CONFIGURATION, ship_symbol, Some(request))
+            .await
+            .map_err(ApiError::from)?
+            .data;
+
+        let (symbol, activity, cooldown) = (
+            ship_symbol.to_owned(),
+            format!("Jumped to {system_symbol}"),
+            *data.cooldown,
+        );
+        self.app
+            .update(move |app| {
+                app.log_activity(&symbol, activity);
+                set_cooldown(app, cooldown);
+            })
+            .await?;
+        match data.nav {
+            Some(nav) => self.set_ship_nav(ship_symbol, *nav).await,
+            None => self.update_ship_nav(ship_symbol).await,
+        }
+    }
+
+    async fn refuel_ship(&mut self, ship_symbol: &str) -> Result<()> {
+        let data = *refuel_ship(&CONFIGURATION, ship_symbol, 0)
+            .await
+            .map_err(ApiError::from)?
+            .data;
+
+        let (symbol, agent, fuel) = (ship_symbol.to_owned(), (*data.agent).clone(), data.fuel);
+        self.app
+            .update(move |app| {
+                app.state.agent = agent;
+                if let Some(ship) = app.ship_mut(&symbol) {
+                    ship.fuel = fuel;
+                }
+            })
+            .await?;
+        db_util::save_agent(&data.agent).await?;
+        self.save_ship(ship_symbol).await
+    }
+
+    async fn update_trade_data(&mut self, system_symbol: &str) -> Result<()> {
+        let mut data = TradeData {
+            system_symbol: system_symbol.to_owned(),
+            markets: db_util::load_known_markets(system_symbol).await?,
+            jumps: Vec::new(),
+        };
+        if let Some(gate) = db_util::load_jump_gate_location(system_symbol).await? {
+            let jump_gate = self.jump_gate(system_symbol, &gate.symbol).await?;
+            for connected in jump_gate.connected_systems {
+                // Only systems we have seen the jump gate and markets of are any use
+                let Some(to_gate) = db_util::load_jump_gate_location(&connected.symbol).await?
+                else {
+                    continue;
+                };
+                let markets = db_util::load_known_markets(&connected.symbol).await?;
+                if markets.is_empty() {
+                    continue;
+                }
+                data.markets.extend(markets);
+                data.jumps.push(Jump {
+                    from_gate: gate.clone(),
+                    system_symbol: connected.symbol,
+                    to_gate,
+                    distance: connected.distance,
+                });
+            }
+        }
+
+        self.app
+            .update(move |app| {
+                // The trade ship may have moved on to another system in the meantime
+                let current = app
+                    .ship(&app.state.trade_ship)
+                    .is_some_and(|ship| ship.nav.system_symbol == data.system_symbol);
+                if current {
+                    app.state.trade_data = Some(data);
+                    app.update_trade_routes();
+                }
+            })
+            .await
+    }
+
+    /// Returns the systems a jump gate connects to, fetching and recording them the first time
+    async fn jump_gate(&mut self, system_symbol: &str, waypoint_symbol: &str) -> Result<JumpGate> {
+        if let Some(jump_gate) = db_util::load_jump_gate(waypoint_symbol).await? {
+            return Ok(jump_gate);
+        }
+        let jump_gate = *get_jump_gate(&CONFIGURATION, system_symbol, waypoint_symbol)
+            .await
+            .map_err(ApiError::from)?
+            .data;
+        db_util::save_jump_gate(waypoint_symbol, system_symbol, &jump_gate).await?;
+
+        Ok(jump_gate)
+    }
+
+    /// Runs a trade route, noting in the ship's activity log if it stops early
+    async fn run_trade_route(&mut self, ship_symbol: &str, route: &TradeRoute) -> Result<()> {
+        let result = self.trade(ship_symbol, route).await;
+        if let Err(e) = &result {
+            self.log_activity(ship_symbol, format!("Trade route stopped: {e:#}"))
+                .await?;
+        }
+        result
+    }
+
+    async fn trade(&mut self, ship_symbol: &str, route: &TradeRoute) -> Result<()> {
+        self.log_activity(
+            ship_symbol,
+            format!(
+                "Trading {} from {} to {}",
+                route.good, route.buy_at.symbol, route.sell_at.symbol
+            ),
+        )
+        .await?;
+
+        self.travel_to(ship_symbol, &route.buy_at.symbol, route.flight_mode)
+            .await?;
+        self.dock_ship(ship_symbol).await?;
+        if route.refuel_at_buy {
+            self.top_up_fuel(ship_symbol).await?;
+        }
+        let space = self.ship(ship_symbol).await?.cargo;
+        let mut remaining = route.units.min(space.capacity - space.units);
+        while remaining > 0 {
+            let units = remaining.min(route.buy_volume.max(1));
+            self.purchase_cargo(ship_symbol, route.good.clone(), units)
+                .await?;
+            remaining -= units;
+        }
+
+        if let Some(jump) = &route.jump {
+            self.travel_to(ship_symbol, &jump.from_gate.symbol, route.flight_mode)
+                .await?;
+            // Still docked if the goods were bought at the gate
+            if self.ship(ship_symbol).await?.nav.status == ShipNavStatus::Docked {
+                self.orbit_ship(ship_symbol).await?;
+            }
+            self.jump_ship(ship_symbol, &jump.system_symbol).await?;
+        }
+        self.travel_to(ship_symbol, &route.sell_at.symbol, route.flight_mode)
+            .await?;
+        self.dock_ship(ship_symbol).await?;
+        let cargo = self.ship(ship_symbol).await?.cargo;
+        let mut remaining = cargo
+            .inventory
+            .iter()
+            .find(|item| item.symbol == route.good)
+            .map_or(0, |item| item.units);
+        while remaining > 0 {
+            let units = remaining.min(route.sell_volume.max(1));
+            self.sell_cargo(ship_symbol, route.good.clone(), units)
+                .await?;
+            remaining -= units;
+        }
+        if route.refuel_at_sell {
+            self.top_up_fuel(ship_symbol).await?;
+        }
+
+        self.log_activity(ship_symbol, "Finished trade route".to_owned())
+            .await
+    }
+
+    /// Adds an entry to a ship's activity log
+    async fn log_activity(&mut self, ship_symbol: &str, activity: String) -> Result<()> {
+        let symbol = ship_symbol.to_owned();
+        self.app
+            .update(move |app| app.log_activity(&symbol, activity))
+            .await
+    }
+
+    /// Returns a copy of a ship's current state
+    async fn ship(&mut self, ship_symbol: &str) -> Result<Ship> {
+        let symbol = ship_symbol.to_owned();
+        self.app
+            .update(move |app| app.ship(&symbol).cloned())
+            .await?
+            .ok_or_else(|| anyhow!("Unknown ship {ship_symbol}"))
+    }
+
+    /// Refuels a docked ship if its tank isn't full
+    async fn top_up_fuel(&mut self, ship_symbol: &str) -> Result<()> {
+        let fuel = self.ship(ship_symbol).await?.fuel;
+        if fuel.current < fuel.capacity {
+            self.refuel_ship(ship_symbol).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Flies a ship to a waypoint in its system and waits for it to arrive
+    async fn travel_to(
+        &mut self,
+        ship_symbol: &str,
+        waypoint_symbol: &str,
+        mode: ShipNavFlightMode,
+    ) -> Result<()> {
+        self.wait_for_arrival(ship_symbol).await?;
+        let nav = self.ship(ship_symbol).await?.nav;
+        if nav.waypoint_symbol == waypoint_symbol {
+            return Ok(());
+        }
+        if nav.status == ShipNavStatus::Docked {
+            self.orbit_ship(ship_symbol).await?;
+        }
+        if nav.flight_mode != mode {
+            self.set_flight_mode(ship_symbol, mode).await?;
+        }
+        self.navigate_ship(ship_symbol, waypoint_symbol.to_owned())
+            .await?;
+        self.wait_for_arrival(ship_symbol).await
+    }
+
+    /// Waits for a ship to arrive if it is in transit
+    async fn wait_for_arrival(&mut self, ship_symbol: &str) -> Result<()> {
+        let nav = self.ship(ship_symbol).await?.nav;
+        if nav.status != ShipNavStatus::InTransit {
+            return Ok(());
+        }
+        if let Some(arrival) = st_util::parse_time(&nav.route.arrival) {
+            if let Ok(wait) = (arrival - Utc::now()).to_std() {
+                // Give the server a moment to catch up
+                tokio::time::sleep(wait + Duration::from_secs(1)).await;
+            }
+        }
+        self.update_ship_nav(ship_symbol).await
+    }
+}
+
+/// Records the cooldown a ship is on after an action
+fn set_cooldown(app: &mut App, cooldown: Cooldown) {
+    app.state
+        .cooldowns
+        .insert(cooldown.ship_symbol.clone(), cooldown);
 }
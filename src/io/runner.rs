@@ -0,0 +1,242 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex as SyncMutex,
+    time::{Duration, Instant},
+};
+
+use log::{debug, error};
+use once_cell::sync::Lazy;
+use tokio::sync::{mpsc, oneshot, Semaphore};
+
+use crate::{api_error::ApiError, app::AppHandle, profile, rate_limit};
+
+use super::{handler::IoHandler, AgentEvent, IoEvent};
+
+/// Most refreshes run at once, so a slow refresh can't hold up everything else
+const MAX_CONCURRENT_REFRESHES: usize = 4;
+/// Most actions run at once, separate from refreshes so actions never wait behind them
+const MAX_CONCURRENT_ACTIONS: usize = 4;
+/// Most automations like trade routes run at once, separate from actions since they run for
+/// as long as the ship takes to travel
+const MAX_CONCURRENT_AUTOMATIONS: usize = 32;
+
+/// An event that has been dispatched but hasn't finished
+#[derive(Debug, Clone)]
+pub struct EventStatus {
+    /// identifies the event to [`cancel`] it
+    pub id: u64,
+    pub event: IoEvent,
+    /// agent the event is for, if one was chosen
+    pub agent: Option<String>,
+    pub queued_at: Instant,
+    /// when the event started running, if it has
+    pub started_at: Option<Instant>,
+}
+
+impl EventStatus {
+    /// How long the event has been running, or waiting if it hasn't started
+    #[must_use]
+    pub fn elapsed(&self) -> Duration {
+        self.started_at.unwrap_or(self.queued_at).elapsed()
+    }
+}
+
+/// An event that has been given an id, with what it listens to for being cancelled
+type Queued = (u64, AgentEvent, oneshot::Receiver<()>);
+
+#[derive(Debug, Default)]
+struct Tracker {
+    next_id: u64,
+    events: Vec<(u64, EventStatus)>,
+    /// events waiting on an earlier event for the same ship, by ship
+    lanes: HashMap<String, VecDeque<Queued>>,
+    /// senders that cancel each unfinished event, by id
+    cancels: HashMap<u64, oneshot::Sender<()>>,
+}
+
+impl Tracker {
+    /// Records a dispatched event, returning it if it can start now.
+    /// Refreshes that are already waiting to run are dropped, since they would fetch the same data.
+    fn queue(&mut self, dispatched: AgentEvent) -> Option<Queued> {
+        let event = &dispatched.event;
+        if event.is_refresh()
+            && self.events.iter().any(|(_, status)| {
+                status.started_at.is_none()
+                    && status.event == *event
+                    && status.agent.as_deref() == dispatched.agent()
+            })
+        {
+            debug!("Skipping {event}, it's already queued");
+            return None;
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.events.push((
+            id,
+            EventStatus {
+                id,
+                event: event.clone(),
+                agent: dispatched.agent().map(ToOwned::to_owned),
+                queued_at: Instant::now(),
+                started_at: None,
+            },
+        ));
+        let (cancel, cancelled) = oneshot::channel();
+        self.cancels.insert(id, cancel);
+        if let Some(ship) = event.ship() {
+            if let Some(lane) = self.lanes.get_mut(ship) {
+                lane.push_back((id, dispatched, cancelled));
+                return None;
+            }
+            self.lanes.insert(ship.to_owned(), VecDeque::new());
+        }
+        Some((id, dispatched, cancelled))
+    }
+
+    /// Next event waiting on `ship`, closing the lane if there are none
+    fn next_for_ship(&mut self, ship: &str) -> Option<Queued> {
+        let next = self.lanes.get_mut(ship).and_then(VecDeque::pop_front);
+        if next.is_none() {
+            self.lanes.remove(ship);
+        }
+        next
+    }
+
+    fn start(&mut self, id: u64) {
+        if let Some((_, status)) = self.events.iter_mut().find(|(i, _)| *i == id) {
+            status.started_at = Some(Instant::now());
+        }
+    }
+
+    fn finish(&mut self, id: u64) {
+        self.events.retain(|(i, _)| *i != id);
+        self.cancels.remove(&id);
+    }
+
+    /// Stops an event, dropping it straight away if it's waiting on an earlier event for its ship.
+    /// Returns the event, or `None` if it has already finished.
+    fn cancel(&mut self, id: u64) -> Option<IoEvent> {
+        let (_, status) = self.events.iter().find(|(i, _)| *i == id)?;
+        let event = status.event.clone();
+        for lane in self.lanes.values_mut() {
+            lane.retain(|(i, ..)| *i != id);
+        }
+        if let Some(cancel) = self.cancels.remove(&id) {
+            // The event may finish on its own before it sees this
+            let _ = cancel.send(());
+        }
+        if status.started_at.is_none() {
+            self.finish(id);
+        }
+        Some(event)
+    }
+}
+
+static TRACKER: Lazy<SyncMutex<Tracker>> = Lazy::new(SyncMutex::default);
+static REFRESH_SLOTS: Semaphore = Semaphore::const_new(MAX_CONCURRENT_REFRESHES);
+static ACTION_SLOTS: Semaphore = Semaphore::const_new(MAX_CONCURRENT_ACTIONS);
+static AUTOMATION_SLOTS: Semaphore = Semaphore::const_new(MAX_CONCURRENT_AUTOMATIONS);
+
+fn tracker() -> std::sync::MutexGuard<'static, Tracker> {
+    TRACKER
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+/// Events that have been dispatched but haven't finished, oldest first
+#[must_use]
+pub fn activity() -> Vec<EventStatus> {
+    tracker()
+        .events
+        .iter()
+        .map(|(_, status)| status.clone())
+        .collect()
+}
+
+/// Cancels an event that hasn't finished, along with any of its requests waiting to be sent
+/// or in flight. Returns the event, or `None` if it has already finished.
+pub fn cancel(id: u64) -> Option<IoEvent> {
+    tracker().cancel(id)
+}
+
+/// Runs events as they are received, several at a time.
+/// Events for the same ship still run one at a time, in order.
+pub async fn run(app: AppHandle, mut receiver: mpsc::Receiver<AgentEvent>) {
+    while let Some(event) = receiver.recv().await {
+        let Some(queued) = tracker().queue(event) else {
+            continue;
+        };
+        tokio::spawn(run_lane(app.clone(), queued));
+    }
+}
+
+/// Runs an event, then any events that were waiting on the same ship
+async fn run_lane(app: AppHandle, mut queued: Queued) {
+    let ship = queued.1.event.ship().map(ToOwned::to_owned);
+    loop {
+        run_event(&app, queued).await;
+        let Some(ship) = &ship else {
+            return;
+        };
+        let Some(next) = tracker().next_for_ship(ship) else {
+            return;
+        };
+        queued = next;
+    }
+}
+
+async fn run_event(app: &AppHandle, (id, dispatched, mut cancelled): Queued) {
+    let AgentEvent { profile, event } = dispatched;
+    let slots = if event.is_refresh() {
+        &REFRESH_SLOTS
+    } else if event.is_automation() {
+        &AUTOMATION_SLOTS
+    } else {
+        &ACTION_SLOTS
+    };
+    // The semaphores are never closed
+    let _slot = tokio::select! {
+        slot = slots.acquire() => match slot {
+            Ok(slot) => slot,
+            Err(_) => return,
+        },
+        _ = &mut cancelled => return,
+    };
+    tracker().start(id);
+
+    // Results apply to the agent the event was for, even if another is shown by then
+    let app = app.for_agent(profile.as_ref().map(|p| p.symbol.as_str()));
+    let priority = event.priority();
+    let mut handler = IoHandler::new(app.clone());
+    let handling = rate_limit::with_priority(priority, handler.handle_io_event(event));
+    let handling = rate_limit::tracking_retries(handling);
+    let handling = async move {
+        match profile {
+            Some(profile) => profile::with_token(profile.token, handling).await,
+            None => handling.await,
+        }
+    };
+    // Dropping the handler returns the permits of any requests it was making
+    let result = tokio::select! {
+        result = handling => result,
+        _ = cancelled => {
+            tracker().finish(id);
+            return;
+        }
+    };
+
+    tracker().finish(id);
+    if let Err(e) = &result {
+        error!("Error handling io event: {e:#?}");
+    }
+    let reported = app.update(move |app| match result {
+        Ok(()) => app.state.offline = false,
+        Err(e) => {
+            app.state.offline = e.downcast_ref::<ApiError>().is_some_and(|e| e.unreachable);
+            app.notify_error(&e);
+        }
+    });
+    // Nothing is left to tell if the app has closed
+    let _ = reported.await;
+}
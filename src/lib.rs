@@ -13,7 +13,10 @@ pub mod input;
 /// External IO handler.
 pub mod io;
 
+pub mod api_error;
 pub mod config;
 pub mod db_util;
+pub mod profile;
 pub mod rate_limit;
 pub mod st_util;
+pub mod trade;
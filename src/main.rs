@@ -1,48 +1,68 @@
 use anyhow::Result;
-use log::error;
-use spacetraders_tui::app::App;
+use log::info;
+use spacetraders_tui::app::{App, AppHandle};
+use spacetraders_tui::config::SETTINGS;
 use spacetraders_tui::db_util;
 use spacetraders_tui::input::event::{EventHandler, InputEvent};
 use spacetraders_tui::input::handler::handle_key_events;
-use spacetraders_tui::io::handler::IoHandler;
-use spacetraders_tui::io::IoEvent;
+use spacetraders_tui::io::runner;
+use spacetraders_tui::io::{AgentEvent, IoEvent};
+use spacetraders_tui::profile;
 use spacetraders_tui::tui::Tui;
+use std::fs::File;
 use std::io;
-use std::sync::Arc;
 use tokio::sync::mpsc;
-use tokio::sync::Mutex;
 use tui::backend::CrosstermBackend;
 use tui::Terminal;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    env_logger::init();
+    // Settings come from the config file and the environment, so a .env file is optional
+    dotenvy::dotenv().ok();
+
+    // Log to a file, since the terminal is taken up by the user interface
+    let log_file = SETTINGS
+        .log_file
+        .as_deref()
+        .unwrap_or("spacetraders-tui.log");
+    env_logger::Builder::from_default_env()
+        .target(env_logger::Target::Pipe(Box::new(File::create(log_file)?)))
+        .init();
+    if let Some(server) = &SETTINGS.server {
+        info!("Using server {server}");
+    }
 
     // Setup database stuff
-    dotenvy::dotenv()?;
     db_util::setup_database().await;
 
     // Create IoEvent channel
-    let (sync_io_sender, mut sync_io_reciever) = mpsc::channel::<IoEvent>(100);
+    let (sync_io_sender, sync_io_reciever) = mpsc::channel::<AgentEvent>(100);
 
-    // Create an application.
-    let app_ref = Arc::new(Mutex::new(App::new(sync_io_sender.clone())));
+    // Create an application, owned by the main loop.
+    // I/O sends its results back as updates, applied between draws.
+    let mut app = App::new(sync_io_sender.clone());
+    let (app_handle, mut updates) = AppHandle::new();
 
     // Spawn thread to handle I/O
-    let io_app_ref = app_ref.clone();
-    tokio::spawn(async move {
-        let mut handler = IoHandler::new(io_app_ref);
-        while let Some(io_event) = sync_io_reciever.recv().await {
-            if let Err(e) = handler.handle_io_event(io_event).await {
-                error!("Error handling io event: {e:#?}");
-            }
-        }
-    });
+    tokio::spawn(runner::run(app_handle, sync_io_reciever));
 
-    // Initialize internal state
-    let mut app = app_ref.lock().await;
-    app.update_agent_tab().await;
-    drop(app);
+    // Pick the agent to act as, from the config file, the database or `TOKEN`
+    let profiles = profile::load_profiles().await?;
+    let initial = profile::initial_profile(&profiles).await?;
+    let first_run = initial.is_none() && SETTINGS.token.is_none();
+    app.profiles = profiles;
+    app.profile = initial;
+
+    if first_run {
+        // Nothing to act as until an agent is registered
+        app.register_agent().await;
+    } else {
+        // Initialize internal state, showing what we last knew until the API answers
+        app.load_cached_agent().await;
+    }
+    // Fetches the agent, contracts, factions and fleet once we know the server hasn't reset since
+    // they were recorded
+    app.dispatch(IoEvent::CheckServerStatus).await;
 
     // Initialize the terminal user interface.
     let backend = CrosstermBackend::new(io::stderr());
@@ -53,16 +73,22 @@ async fn main() -> Result<()> {
 
     // Start the main loop.
     loop {
-        let mut app = app_ref.lock().await;
         // Render the user interface.
         tui.draw(&mut app)?;
-        // Handle events.
-        match tui.events.next().await {
-            InputEvent::Tick => app.tick(),
-            InputEvent::Key(key_event) => handle_key_events(key_event, &mut app).await?,
-            // Event::Mouse(_) => {}
-            // Event::Resize(_, _) => {}
-            _ => {}
+        // Handle events, or redraw as soon as I/O changes something.
+        tokio::select! {
+            event = tui.events.next() => match event {
+                InputEvent::Tick => app.tick().await,
+                InputEvent::Key(key_event) => handle_key_events(key_event, &mut app).await?,
+                // Event::Mouse(_) => {}
+                // Event::Resize(_, _) => {}
+                _ => {}
+            },
+            Some(update) = updates.recv() => update(&mut app),
+        }
+        // Apply everything else that has arrived, so a burst of updates is drawn once
+        while let Ok(update) = updates.try_recv() {
+            update(&mut app);
         }
         if !app.running() {
             tui.events.close();
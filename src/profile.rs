@@ -0,0 +1,135 @@
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use anyhow::Result;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use reqwest::{
+    header::{HeaderValue, AUTHORIZATION},
+    Request, Response,
+};
+use serde::Deserialize;
+use task_local_extensions::Extensions;
+
+use crate::{config::SETTINGS, db_util};
+
+/// An agent that can be switched to, and the token that acts as it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    pub symbol: String,
+    pub token: String,
+}
+
+/// Claims of an agent token that we care about
+#[derive(Deserialize)]
+struct Claims {
+    identifier: String,
+}
+
+/// Reads the agent symbol a token was issued for.
+/// Tokens are JWTs naming the agent in their `identifier` claim.
+#[must_use]
+pub fn agent_symbol(token: &str) -> Option<String> {
+    let payload = token.split('.').nth(1)?;
+    let json = URL_SAFE_NO_PAD.decode(payload.trim_end_matches('=')).ok()?;
+    serde_json::from_slice::<Claims>(&json)
+        .ok()
+        .map(|claims| claims.identifier)
+}
+
+/// Every known agent, from the config file and those added in the app, by symbol.
+/// Tokens in the config file win over recorded ones for the same agent.
+///
+/// # Errors
+/// Errors if the recorded tokens can't be loaded
+pub async fn load_profiles() -> Result<Vec<Profile>> {
+    let mut tokens: BTreeMap<String, String> =
+        db_util::load_agent_tokens().await?.into_iter().collect();
+    tokens.extend(SETTINGS.agents.clone());
+    if let Some(token) = &SETTINGS.token {
+        if let Some(symbol) = agent_symbol(token) {
+            tokens.insert(symbol, token.clone());
+        }
+    }
+
+    Ok(tokens
+        .into_iter()
+        .map(|(symbol, token)| Profile { symbol, token })
+        .collect())
+}
+
+/// The profile to start with: the one for `TOKEN` if it is set,
+/// otherwise the agent that was used last
+///
+/// # Errors
+/// Errors if the last used agent can't be loaded
+pub async fn initial_profile(profiles: &[Profile]) -> Result<Option<Profile>> {
+    let symbol = match SETTINGS.token.as_deref().and_then(agent_symbol) {
+        Some(symbol) => Some(symbol),
+        None => db_util::load_latest_agent_symbol().await?,
+    };
+
+    Ok(symbol
+        .and_then(|symbol| profiles.iter().find(|p| p.symbol == symbol))
+        .or_else(|| profiles.first())
+        .cloned())
+}
+
+tokio::task_local! {
+    /// Token requests made by the current task authenticate with
+    static TOKEN: String;
+}
+
+/// Runs a future with all of its requests made as the agent the token belongs to.
+/// Requests made outside of this use `TOKEN` from the settings, if it is set.
+pub async fn with_token<F: Future>(token: String, future: F) -> F::Output {
+    TOKEN.scope(token, future).await
+}
+
+/// Runs a future with none of its requests made as an agent, not even the one for `TOKEN`
+pub async fn without_token<F: Future>(future: F) -> F::Output {
+    TOKEN.scope(String::new(), future).await
+}
+
+/// Set once `TOKEN` from the settings is from before the server's last reset
+static SETTINGS_TOKEN_STALE: AtomicBool = AtomicBool::new(false);
+
+/// Stops making requests with `TOKEN` from the settings, once the server has reset since
+pub fn forget_settings_token() {
+    SETTINGS_TOKEN_STALE.store(true, Ordering::Relaxed);
+}
+
+/// Token the current task's requests are made with, if any
+#[must_use]
+pub fn current_token() -> Option<String> {
+    match TOKEN.try_with(Clone::clone) {
+        Ok(token) => Some(token).filter(|token| !token.is_empty()),
+        Err(_) => SETTINGS
+            .token
+            .clone()
+            .filter(|_| !SETTINGS_TOKEN_STALE.load(Ordering::Relaxed)),
+    }
+}
+
+/// Adds the current task's token to each request, so agents can share one client
+#[derive(Debug, Clone, Copy)]
+pub struct Middleware;
+
+#[async_trait::async_trait]
+impl reqwest_middleware::Middleware for Middleware {
+    async fn handle(
+        &self,
+        mut request: Request,
+        extensions: &mut Extensions,
+        next: reqwest_middleware::Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        if let Some(token) = current_token() {
+            let value = HeaderValue::from_str(&format!("Bearer {token}"))
+                .map_err(|e| reqwest_middleware::Error::Middleware(e.into()))?;
+            request.headers_mut().insert(AUTHORIZATION, value);
+        }
+        next.run(request, extensions).await
+    }
+}
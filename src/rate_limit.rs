@@ -1,21 +1,35 @@
 // Based on https://gist.github.com/Jules-Bertholet/7bf734b3593e8f9831ef279246358b12
 
-use std::time::Duration;
+use std::{
+    cell::Cell,
+    collections::VecDeque,
+    future::Future,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex as SyncMutex,
+    },
+    time::{Duration, Instant},
+};
 
 use chrono::{DateTime, Utc};
-use log::{error, warn};
-use reqwest::{Request, Response, StatusCode};
+use log::{error, info, warn};
+use once_cell::sync::Lazy;
+use reqwest::{header::HeaderMap, Method, Request, Response, StatusCode};
+use strum::{Display, EnumCount, EnumIter, IntoEnumIterator};
 use task_local_extensions::Extensions;
 use tokio::{
-    sync::{Mutex, Semaphore, SemaphorePermit},
+    sync::{oneshot, Mutex},
     time::sleep,
 };
 
+use crate::config::{Settings, SETTINGS};
+
 /// The current documented burst limit.
-static BURST_LIMIT: u16 = 10;
+const BURST_LIMIT: u16 = 10;
 
 /// The current documented per-second limit.
-static RATE_LIMIT: u16 = 2;
+const RATE_LIMIT: u16 = 2;
 
 // Exponential backoff constants.
 
@@ -25,92 +39,527 @@ const BACKOFF_CONSTANT_SECONDS: f64 = 10.0;
 const BACKOFF_BASE: f64 = 1.5;
 /// For exponential backoff retry on server errors.
 const BACKOFF_EXPONENT_INCREMENT: f64 = 1.0;
+/// Longest to wait between retries.
+const MAX_BACKOFF_SECONDS: f64 = 60.0;
 
-/// A permit must be acquired from this pool before any API request.
-static REQUEST_SEMAPHORE: Semaphore = Semaphore::const_new(BURST_LIMIT as usize);
+/// Most times a failed request is retried before giving up.
+const MAX_RETRIES: usize = 3;
 
-struct ReturnPermit;
-impl ReturnPermit {
-    /// Wait for the appropriate amount of time (calculated from the rate limit), then returns a permit to the pool.
-    async fn return_permit(&mut self, permit: SemaphorePermit<'_>) {
-        sleep(Duration::from_millis(
-            1000_u16.saturating_div(RATE_LIMIT).into(),
-        ))
-        .await;
+/// How failed requests are retried
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// most times a failed request is retried before giving up
+    pub max_retries: usize,
+    /// seconds to wait before the first retry, growing exponentially after
+    pub backoff_seconds: f64,
+    /// longest to wait between retries, in seconds
+    pub max_backoff_seconds: f64,
+}
 
-        drop(permit);
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: MAX_RETRIES,
+            backoff_seconds: BACKOFF_CONSTANT_SECONDS,
+            max_backoff_seconds: MAX_BACKOFF_SECONDS,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// How long to wait before retrying a request that has been retried a number of times
+    fn backoff(self, retries: usize) -> Duration {
+        #[allow(clippy::cast_precision_loss)]
+        let backoff_exponent = BACKOFF_EXPONENT_INCREMENT * retries as f64;
+        Duration::from_secs_f64(
+            (self.backoff_seconds * BACKOFF_BASE.powf(backoff_exponent))
+                .min(self.max_backoff_seconds),
+        )
+    }
+}
+
+static RETRY_POLICY: Lazy<SyncMutex<RetryPolicy>> = Lazy::new(SyncMutex::default);
+
+/// Returns how failed requests are currently retried
+#[must_use]
+#[allow(clippy::expect_used)]
+pub fn retry_policy() -> RetryPolicy {
+    *RETRY_POLICY
+        .lock()
+        .expect("retry policy lock is not poisoned")
+}
+
+/// Changes how failed requests are retried, taking effect for the next request
+#[allow(clippy::expect_used)]
+pub fn set_retry_policy(policy: RetryPolicy) {
+    *RETRY_POLICY
+        .lock()
+        .expect("retry policy lock is not poisoned") = policy;
+}
+
+/// Limits requests to the API are held to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimits {
+    /// requests that can be made at once before waiting
+    pub burst: u16,
+    /// requests per second once the burst is used up
+    pub per_second: u16,
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        Self {
+            burst: BURST_LIMIT,
+            per_second: RATE_LIMIT,
+        }
+    }
+}
+
+impl RateLimits {
+    /// Limits set in the config file or environment, if either limit is set,
+    /// with the documented limit standing in for the other
+    #[must_use]
+    pub fn from_settings(settings: &Settings) -> Option<Self> {
+        let (burst, per_second) = (settings.rate_limit_burst, settings.rate_limit_per_second);
+        if burst.is_none() && per_second.is_none() {
+            return None;
+        }
+        let defaults = Self::default();
+        Some(Self {
+            burst: burst.unwrap_or(defaults.burst),
+            per_second: per_second.unwrap_or(defaults.per_second),
+        })
+    }
+
+    /// Reads the limits the server reports in a response's headers, if it sent them
+    fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        Some(Self {
+            burst: header(headers, "x-ratelimit-limit-burst")?,
+            per_second: header(headers, "x-ratelimit-limit-per-second")?,
+        })
+    }
+
+    /// The lower of each of two sets of limits
+    fn min(self, other: Self) -> Self {
+        Self {
+            burst: self.burst.min(other.burst),
+            per_second: self.per_second.min(other.per_second),
+        }
+    }
+
+    /// Time to wait before a used permit can be reused
+    fn interval(self) -> Duration {
+        Duration::from_millis(1000_u16.saturating_div(self.per_second).into())
+    }
+}
+
+/// How urgently a request should be sent, earlier variants going first
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Display, EnumIter, EnumCount,
+)]
+pub enum Priority {
+    /// Actions the user asked for
+    #[strum(serialize = "interactive")]
+    Interactive,
+    /// Refreshing data
+    #[strum(serialize = "background")]
+    Background,
+    /// Ships running on their own, like trade routes
+    #[strum(serialize = "automation")]
+    Automation,
+}
+
+tokio::task_local! {
+    /// Priority of requests made by the current task
+    static PRIORITY: Priority;
+}
+
+/// Runs a future with all of its requests made at the given priority.
+/// Requests made outside of this are [`Priority::Background`].
+pub async fn with_priority<F: Future>(priority: Priority, future: F) -> F::Output {
+    PRIORITY.scope(priority, future).await
+}
+
+tokio::task_local! {
+    /// Times the current task's last request was retried
+    static RETRIES: Cell<usize>;
+}
+
+/// Runs a future, keeping track of how many times its requests are retried, see [`last_retries`]
+pub async fn tracking_retries<F: Future>(future: F) -> F::Output {
+    RETRIES.scope(Cell::new(0), future).await
+}
+
+/// Times the last request made within [`tracking_retries`] was retried, or 0 outside of it
+#[must_use]
+pub fn last_retries() -> usize {
+    RETRIES.try_with(Cell::get).unwrap_or(0)
+}
+
+/// Parses a header's value, if present
+fn header<T: FromStr>(headers: &HeaderMap, name: &str) -> Option<T> {
+    headers.get(name)?.to_str().ok()?.parse().ok()
+}
+
+/// Reads the time the server's limits reset from a response's headers
+fn reset_time(headers: &HeaderMap) -> Option<DateTime<Utc>> {
+    let reset = headers.get("x-ratelimit-reset")?.to_str().ok()?;
+    DateTime::parse_from_rfc3339(reset)
+        .ok()
+        .map(|reset| reset.with_timezone(&Utc))
+}
+
+/// A request waiting for a permit
+struct Waiter {
+    queued_at: Instant,
+    sender: oneshot::Sender<()>,
+}
+
+/// Hands out permits to make requests, highest priority first
+struct Scheduler {
+    /// limits the user set, which requests never go over
+    configured: Option<RateLimits>,
+    /// limits the server last reported
+    reported: Option<RateLimits>,
+    /// limits requests are currently held to
+    limits: RateLimits,
+    /// permits in use or waiting to be returned
+    in_use: u16,
+    /// requests the server has left before its limits reset,
+    /// as it last said less those made since
+    remaining: Option<u16>,
+    /// when the server last said its limits reset
+    reset: Option<DateTime<Utc>>,
+    /// waiting requests, indexed by priority
+    queues: [VecDeque<Waiter>; Priority::COUNT],
+}
+
+impl Scheduler {
+    fn new(configured: Option<RateLimits>) -> Self {
+        Self {
+            configured,
+            reported: None,
+            limits: configured.unwrap_or_default(),
+            in_use: 0,
+            remaining: None,
+            reset: None,
+            queues: Default::default(),
+        }
     }
+
+    /// Holds requests to the limits the user set and the server reported, whichever are lower
+    fn update_limits(&mut self) {
+        let limits = match (self.configured, self.reported) {
+            (Some(configured), Some(reported)) => configured.min(reported),
+            (Some(limits), None) | (None, Some(limits)) => limits,
+            (None, None) => RateLimits::default(),
+        };
+        if limits != self.limits {
+            info!(
+                "Rate limit is now {} at once, {} per second",
+                limits.burst, limits.per_second
+            );
+            self.limits = limits;
+        }
+        self.grant();
+    }
+
+    /// Keeps track of the limits and remaining requests the server reports
+    fn observe(&mut self, headers: &HeaderMap) {
+        if let Some(reset) = reset_time(headers) {
+            self.reset = Some(reset);
+        }
+        if let Some(remaining) = header::<u16>(headers, "x-ratelimit-remaining") {
+            self.remaining = Some(remaining);
+            // Check back once the server's limits reset, in case nothing else wakes the queue
+            if let (0, Some(reset)) = (remaining, self.reset) {
+                let wait = (reset - Utc::now()).to_std().unwrap_or(Duration::ZERO);
+                tokio::spawn(async move {
+                    sleep(wait).await;
+                    scheduler().grant();
+                });
+            }
+        }
+        self.reported = RateLimits::from_headers(headers).or(self.reported);
+        self.update_limits();
+    }
+
+    /// Permits that can be given out straight away
+    fn available(&mut self) -> u16 {
+        // Once the server's limits reset, what it last said is out of date
+        if self.reset.is_some_and(|reset| reset <= Utc::now()) {
+            self.remaining = None;
+        }
+        let free = self.limits.burst.saturating_sub(self.in_use);
+        self.remaining.map_or(free, |remaining| free.min(remaining))
+    }
+
+    /// Gives available permits to waiting requests
+    fn grant(&mut self) {
+        for priority in 0..Priority::COUNT {
+            while self.available() > 0 {
+                let Some(waiter) = self.queues[priority].pop_front() else {
+                    break;
+                };
+                // Requests that were cancelled while waiting don't need a permit
+                if waiter.sender.send(()).is_ok() {
+                    self.in_use += 1;
+                    self.remaining = self.remaining.map(|r| r.saturating_sub(1));
+                }
+            }
+        }
+    }
+
+    fn release(&mut self) {
+        self.in_use = self.in_use.saturating_sub(1);
+        self.grant();
+    }
+}
+
+/// A permit must be acquired from this before any API request.
+static SCHEDULER: Lazy<SyncMutex<Scheduler>> =
+    Lazy::new(|| SyncMutex::new(Scheduler::new(RateLimits::from_settings(&SETTINGS))));
+
+#[allow(clippy::expect_used)]
+fn scheduler() -> std::sync::MutexGuard<'static, Scheduler> {
+    SCHEDULER.lock().expect("scheduler lock is not poisoned")
+}
+
+/// Returns the limits requests are currently held to
+#[must_use]
+pub fn limits() -> RateLimits {
+    scheduler().limits
+}
+
+/// Sets the most requests are allowed, taking effect for the next request.
+/// Lower limits reported by the server still apply.
+pub fn set_limits(limits: RateLimits) {
+    let mut scheduler = scheduler();
+    scheduler.configured = Some(limits);
+    scheduler.update_limits();
 }
 
-/// This mutex must be locked before a permit is returned to the pool.
+/// State of the bucket of permits requests are made with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BucketState {
+    /// limits requests are currently held to
+    pub limits: RateLimits,
+    /// limits the server last reported, if it has
+    pub reported: Option<RateLimits>,
+    /// permits that can be used straight away
+    pub available: u16,
+    /// requests the server has left before its limits reset, as it last said less those made since
+    pub remaining: Option<u16>,
+    /// when the server last said its limits reset
+    pub reset: Option<DateTime<Utc>>,
+}
+
+/// Returns the current state of the bucket of permits
+#[must_use]
+pub fn bucket_state() -> BucketState {
+    let mut scheduler = scheduler();
+    BucketState {
+        limits: scheduler.limits,
+        reported: scheduler.reported,
+        available: scheduler.available(),
+        remaining: scheduler.remaining,
+        reset: scheduler.reset,
+    }
+}
+
+/// Requests waiting for a permit
+#[derive(Debug, Clone, Default)]
+pub struct QueueStatus {
+    /// number of requests being sent, including waiting to retry
+    pub in_flight: usize,
+    /// number of waiting requests of each priority, highest priority first
+    pub queued: Vec<(Priority, usize)>,
+    /// how long the longest waiting request has waited
+    pub longest_wait: Duration,
+}
+
+impl QueueStatus {
+    /// Number of waiting requests of all priorities
+    #[must_use]
+    pub fn total(&self) -> usize {
+        self.queued.iter().map(|(_, count)| count).sum()
+    }
+}
+
+/// Returns the requests currently waiting for a permit
+#[must_use]
+pub fn queue_status() -> QueueStatus {
+    let scheduler = scheduler();
+    let mut status = QueueStatus {
+        in_flight: IN_FLIGHT.load(Ordering::Relaxed),
+        ..QueueStatus::default()
+    };
+    for (priority, queue) in Priority::iter().zip(&scheduler.queues) {
+        let waiting = queue.iter().filter(|w| !w.sender.is_closed());
+        status.queued.push((priority, waiting.clone().count()));
+        if let Some(oldest) = waiting.map(|w| w.queued_at).min() {
+            status.longest_wait = status.longest_wait.max(oldest.elapsed());
+        }
+    }
+    status
+}
+
+/// Leave to make a request, returned once the request is done or cancelled
+struct Permit;
+
+impl Permit {
+    /// Waits for a permit, letting requests of higher priority go first
+    async fn acquire(priority: Priority) -> Self {
+        let (sender, receiver) = oneshot::channel();
+        {
+            let mut scheduler = scheduler();
+            scheduler.queues[priority as usize].push_back(Waiter {
+                queued_at: Instant::now(),
+                sender,
+            });
+            scheduler.grant();
+        }
+        let mut pending = PendingPermit(Some(receiver));
+        if let Some(receiver) = pending.0.as_mut() {
+            #[allow(clippy::expect_used)]
+            receiver
+                .await
+                .expect("waiters are only dropped once granted");
+        }
+        pending.0 = None;
+        Self
+    }
+}
+
+impl Drop for Permit {
+    /// Return permit to the pool after an appropriate timeout.
+    fn drop(&mut self) {
+        tokio::spawn(async move {
+            let guard = RETURN.lock().await;
+            sleep(limits().interval()).await;
+            scheduler().release();
+            drop(guard);
+        });
+    }
+}
+
+/// A permit being waited for, returned straight away if granted after the wait was cancelled
+struct PendingPermit(Option<oneshot::Receiver<()>>);
+
+impl Drop for PendingPermit {
+    fn drop(&mut self) {
+        if let Some(mut receiver) = self.0.take() {
+            receiver.close();
+            if receiver.try_recv().is_ok() {
+                scheduler().release();
+            }
+        }
+    }
+}
+
+/// This mutex must be locked before a permit is returned.
 /// Locking ensures that waiting periods are sequential.
-static RETURN: Mutex<ReturnPermit> = Mutex::const_new(ReturnPermit);
+static RETURN: Mutex<()> = Mutex::const_new(());
+
+/// Number of requests currently being sent, including waiting to retry
+static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts a request as in flight until dropped
+struct InFlight;
+
+impl InFlight {
+    fn start() -> Self {
+        IN_FLIGHT.fetch_add(1, Ordering::Relaxed);
+        Self
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        IN_FLIGHT.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Whether a request only reads data, so sending it again can't take an action twice
+fn is_read(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD)
+}
+
+/// Whether a failed request is worth trying again, such as after a timeout or dropped connection.
+/// Requests that change the game are only retried if they never reached the server,
+/// so that an action the server did take isn't taken twice.
+fn is_transient(method: &Method, error: &reqwest_middleware::Error) -> bool {
+    match error {
+        reqwest_middleware::Error::Reqwest(e) if is_read(method) => {
+            e.is_timeout() || e.is_connect() || e.is_request()
+        }
+        reqwest_middleware::Error::Reqwest(e) => e.is_connect(),
+        reqwest_middleware::Error::Middleware(_) => false,
+    }
+}
 
 /// Middleware to enforce rate-limiting for the SpaceTraders API.
 #[derive(Default)]
 pub struct Middleware;
 
-#[async_trait::async_trait]
-impl reqwest_middleware::Middleware for Middleware {
-    async fn handle(
-        &self,
+impl Middleware {
+    /// Sends a request once a permit is available, retrying a limited number of times if it fails
+    async fn send(
         mut reqest: Request,
         extensions: &mut Extensions,
         mut next: reqwest_middleware::Next<'_>,
     ) -> reqwest_middleware::Result<Response> {
         // Acquire a permit, yield if burst limit attained.
-        #[allow(clippy::expect_used)]
-        let permit = REQUEST_SEMAPHORE
-            .acquire()
-            .await
-            .expect("sempahore is not closed");
+        let priority = PRIORITY.try_with(|p| *p).unwrap_or(Priority::Background);
+        let permit = Permit::acquire(priority).await;
+        let in_flight = InFlight::start();
 
+        let policy = retry_policy();
+        let method = reqest.method().clone();
         let mut retry_info;
-        let mut server_error_retry_count: usize = 0;
+        let mut retries: usize = 0;
         let result = loop {
             retry_info = reqest
                 .try_clone()
                 .map(|cloned_req| (cloned_req, next.clone()));
 
-            match (retry_info, next.run(reqest, extensions).await) {
+            let response = next.run(reqest, extensions).await;
+            if let Ok(resp) = &response {
+                scheduler().observe(resp.headers());
+            }
+            let can_retry = retries < policy.max_retries;
+
+            match (retry_info, response) {
                 // If this request isn't retryable, return the response no matter what it is.
                 (None, resp) => break resp,
 
                 // If this request was successful, return the response.
                 (_, Ok(resp)) if resp.status().is_success() => break Ok(resp),
 
-                // On server error, log and retry with exponential backoff.
-                (Some((cloned_req, cloned_next)), Ok(resp)) if resp.status().is_server_error() => {
+                // On server error, log and retry reads with exponential backoff.
+                // The server got actions, so it may have taken them.
+                (Some((cloned_req, cloned_next)), Ok(resp))
+                    if resp.status().is_server_error() && is_read(&method) && can_retry =>
+                {
                     let status = resp.status();
                     let resp_text = resp.text().await.ok();
                     error!(
                         "Server error: {}. This request was previously tried {} times. Response body: {}",
                         status,
-                        server_error_retry_count,
+                        retries,
                         resp_text.as_deref().unwrap_or("<bytes>")
                     );
-                    #[allow(clippy::cast_precision_loss)]
-                    let backoff_exponent =
-                        BACKOFF_EXPONENT_INCREMENT * server_error_retry_count as f64;
-                    sleep(Duration::from_secs_f64(
-                        BACKOFF_CONSTANT_SECONDS * BACKOFF_BASE.powf(backoff_exponent),
-                    ))
-                    .await;
-                    server_error_retry_count += 1;
+                    sleep(policy.backoff(retries)).await;
+                    retries += 1;
                     reqest = cloned_req;
                     next = cloned_next;
                 }
 
                 // If, despite our efforts, we've hit a rate limit, wait for the limits to reset and then retry.
                 (Some((cloned_req, cloned_next)), Ok(resp))
-                    if resp.status() == StatusCode::TOO_MANY_REQUESTS =>
+                    if resp.status() == StatusCode::TOO_MANY_REQUESTS && can_retry =>
                 {
-                    if let Some(reset) = resp
-                        .headers()
-                        .get("x-ratelimit-reset")
-                        .and_then(|h| h.to_str().ok())
-                        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
-                    {
+                    if let Some(reset) = reset_time(resp.headers()) {
                         warn!("Rate limit hit! Waiting and retrying.");
 
                         let delay = reset
@@ -119,6 +568,7 @@ impl reqwest_middleware::Middleware for Middleware {
                             .unwrap_or(Duration::ZERO);
 
                         sleep(delay).await;
+                        retries += 1;
 
                         reqest = cloned_req;
                         next = cloned_next;
@@ -127,18 +577,46 @@ impl reqwest_middleware::Middleware for Middleware {
                     }
                 }
 
+                // If the request never got an answer, retry with exponential backoff.
+                (Some((cloned_req, cloned_next)), Err(e))
+                    if is_transient(&method, &e) && can_retry =>
+                {
+                    warn!(
+                        "Request failed: {e}. This request was previously tried {retries} times."
+                    );
+                    sleep(policy.backoff(retries)).await;
+                    retries += 1;
+                    reqest = cloned_req;
+                    next = cloned_next;
+                }
+
                 // Otherwise, pass on the error.
-                (_, resp) => break resp,
+                (_, resp) => {
+                    if retries > 0 {
+                        error!("Giving up on request after {retries} retries");
+                    }
+                    break resp;
+                }
             }
         };
+        // Not known outside of the runner, e.g. in tests
+        let _ = RETRIES.try_with(|r| r.set(retries));
 
-        // Return permit to the pool after an appropriate timeout.
-        tokio::spawn(async move {
-            let mut return_permit = RETURN.lock().await;
-            return_permit.return_permit(permit).await;
-            drop(return_permit);
-        });
-
+        drop(in_flight);
+        drop(permit);
         result
     }
 }
+
+#[async_trait::async_trait]
+impl reqwest_middleware::Middleware for Middleware {
+    async fn handle(
+        &self,
+        reqest: Request,
+        extensions: &mut Extensions,
+        next: reqwest_middleware::Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        // Dropping the request while it waits or is in flight returns its permit
+        Self::send(reqest, extensions, next).await
+    }
+}
@@ -1,4 +1,6 @@
 use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize};
 use spacedust::{
     apis::{
         contracts_api::{get_contracts, GetContractsError},
@@ -8,7 +10,7 @@ use spacedust::{
             get_system_waypoints, get_systems, GetSystemWaypointsError, GetSystemsError,
         },
     },
-    models::{Contract, Faction, Ship, System, Waypoint},
+    models::{Contract, Cooldown, Faction, Ship, ShipNavStatus, System, Waypoint},
 };
 
 use crate::config::CONFIGURATION;
@@ -82,6 +84,162 @@ impl_list!(
     get_systems => pub async fn list_systems() -> Result<Vec<System>, Error<GetSystemsError>>
 );
 
+/// Send a request to an endpoint that spacedust does not cover, handling it the same way
+/// spacedust's generated functions do
+async fn raw_request<T: DeserializeOwned>(
+    method: reqwest::Method,
+    path: &str,
+) -> Result<T, spacedust::apis::Error<serde_json::Value>> {
+    let uri = format!("{}{path}", CONFIGURATION.base_path);
+    let mut request = CONFIGURATION.client.request(method, uri.as_str());
+    if let Some(user_agent) = &CONFIGURATION.user_agent {
+        request = request.header(reqwest::header::USER_AGENT, user_agent.clone());
+    }
+    if let Some(token) = &CONFIGURATION.bearer_access_token {
+        request = request.bearer_auth(token.clone());
+    }
+    // The API rejects bodiless POSTs without a length
+    request = request.header(reqwest::header::CONTENT_LENGTH, "0");
+
+    let response = CONFIGURATION.client.execute(request.build()?).await?;
+    let status = response.status();
+    let content = response.text().await?;
+    if status.is_client_error() || status.is_server_error() {
+        let entity = serde_json::from_str(&content).ok();
+        return Err(spacedust::apis::Error::ResponseError(
+            spacedust::apis::ResponseContent {
+                status,
+                content,
+                entity,
+            },
+        ));
+    }
+    Ok(serde_json::from_str(&content)?)
+}
+
+#[derive(Deserialize)]
+struct NegotiateContractResponse {
+    data: NegotiateContractResponseData,
+}
+
+#[derive(Deserialize)]
+struct NegotiateContractResponseData {
+    contract: Contract,
+}
+
+/// Negotiate a new contract with the faction whose headquarters a ship is docked at
+///
+/// # Errors
+/// Errors if the request fails, e.g. because the ship is not at a faction headquarters
+pub async fn negotiate_contract(
+    ship_symbol: &str,
+) -> Result<Contract, spacedust::apis::Error<serde_json::Value>> {
+    let path = format!(
+        "/my/ships/{}/negotiate/contract",
+        spacedust::apis::urlencode(ship_symbol)
+    );
+    let response: NegotiateContractResponse = raw_request(reqwest::Method::POST, &path).await?;
+    Ok(response.data.contract)
+}
+
+/// Status of the server, from its root endpoint
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerStatus {
+    /// Description of whether the server is up
+    pub status: String,
+    /// Date the server last reset, e.g. `2023-06-03`
+    pub reset_date: String,
+}
+
+/// Get the status of the server, including when it last reset
+///
+/// # Errors
+/// Errors if the request fails
+pub async fn get_status() -> Result<ServerStatus, spacedust::apis::Error<serde_json::Value>> {
+    raw_request(reqwest::Method::GET, "/").await
+}
+
+/// Parse a timestamp returned by the API
+#[must_use]
+pub fn parse_time(timestamp: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+/// Format a duration as a short countdown, e.g. `1h 02m 03s`
+#[must_use]
+pub fn format_duration(duration: chrono::Duration) -> String {
+    let seconds = duration.num_seconds().max(0);
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{hours}h {minutes:02}m {seconds:02}s")
+    } else if minutes > 0 {
+        format!("{minutes}m {seconds:02}s")
+    } else {
+        format!("{seconds}s")
+    }
+}
+
+/// Get the time left until a ship in transit arrives
+#[must_use]
+pub fn time_until_arrival(ship: &Ship, now: DateTime<Utc>) -> Option<chrono::Duration> {
+    if ship.nav.status != ShipNavStatus::InTransit {
+        return None;
+    }
+    parse_time(&ship.nav.route.arrival).map(|arrival| arrival - now)
+}
+
+/// Get the time left until a ship's cooldown ends, if it is still cooling down
+#[must_use]
+pub fn cooldown_remaining(cooldown: &Cooldown, now: DateTime<Utc>) -> Option<chrono::Duration> {
+    parse_time(&cooldown.expiration)
+        .map(|expiration| expiration - now)
+        .filter(|remaining| *remaining > chrono::Duration::zero())
+}
+
+/// Get the position of a ship within its system at the given time,
+/// interpolating along its route if it is in transit
+#[must_use]
+pub fn ship_position(ship: &Ship, now: DateTime<Utc>) -> (f64, f64) {
+    let route = &ship.nav.route;
+    let destination = (
+        f64::from(route.destination.x),
+        f64::from(route.destination.y),
+    );
+    if ship.nav.status != ShipNavStatus::InTransit {
+        return destination;
+    }
+    let departure = (f64::from(route.departure.x), f64::from(route.departure.y));
+    let (Some(departure_time), Some(arrival_time)) = (
+        parse_time(&route.departure_time),
+        parse_time(&route.arrival),
+    ) else {
+        return destination;
+    };
+
+    let total = (arrival_time - departure_time).num_milliseconds();
+    if total <= 0 {
+        return destination;
+    }
+    #[allow(clippy::cast_precision_loss)]
+    let progress =
+        ((now - departure_time).num_milliseconds() as f64 / total as f64).clamp(0.0, 1.0);
+    (
+        departure.0 + (destination.0 - departure.0) * progress,
+        departure.1 + (destination.1 - departure.1) * progress,
+    )
+}
+
+/// Get the symbol of the system a waypoint is in
+#[must_use]
+pub fn waypoint_system_symbol(waypoint_symbol: &str) -> &str {
+    waypoint_symbol
+        .rsplit_once('-')
+        .map_or(waypoint_symbol, |(system_symbol, _)| system_symbol)
+}
+
 #[must_use]
 pub fn contract_type_to_string(contract_type: &spacedust::models::contract::RHashType) -> &str {
     match contract_type {
@@ -90,3 +248,65 @@ pub fn contract_type_to_string(contract_type: &spacedust::models::contract::RHas
         spacedust::models::contract::RHashType::Procurement => "Procurement",
     }
 }
+
+#[must_use]
+pub fn supply_to_string(supply: spacedust::models::market_trade_good::Supply) -> &'static str {
+    match supply {
+        spacedust::models::market_trade_good::Supply::Scarce => "Scarce",
+        spacedust::models::market_trade_good::Supply::Limited => "Limited",
+        spacedust::models::market_trade_good::Supply::Moderate => "Moderate",
+        spacedust::models::market_trade_good::Supply::Abundant => "Abundant",
+    }
+}
+
+#[must_use]
+pub fn survey_size_to_string(size: spacedust::models::survey::Size) -> &'static str {
+    match size {
+        spacedust::models::survey::Size::Small => "Small",
+        spacedust::models::survey::Size::Moderate => "Moderate",
+        spacedust::models::survey::Size::Large => "Large",
+    }
+}
+
+#[must_use]
+pub fn produce_to_string(produce: spacedust::models::ship_refine_request::Produce) -> &'static str {
+    match produce {
+        spacedust::models::ship_refine_request::Produce::Iron => "IRON",
+        spacedust::models::ship_refine_request::Produce::Copper => "COPPER",
+        spacedust::models::ship_refine_request::Produce::Silver => "SILVER",
+        spacedust::models::ship_refine_request::Produce::Gold => "GOLD",
+        spacedust::models::ship_refine_request::Produce::Aluminum => "ALUMINUM",
+        spacedust::models::ship_refine_request::Produce::Platinum => "PLATINUM",
+        spacedust::models::ship_refine_request::Produce::Uranite => "URANITE",
+        spacedust::models::ship_refine_request::Produce::Meritium => "MERITIUM",
+        spacedust::models::ship_refine_request::Produce::Fuel => "FUEL",
+    }
+}
+
+#[must_use]
+pub fn starting_faction_to_string(
+    faction: spacedust::models::register_request::Faction,
+) -> &'static str {
+    match faction {
+        spacedust::models::register_request::Faction::Cosmic => "COSMIC",
+        spacedust::models::register_request::Faction::Void => "VOID",
+        spacedust::models::register_request::Faction::Galactic => "GALACTIC",
+        spacedust::models::register_request::Faction::Quantum => "QUANTUM",
+        spacedust::models::register_request::Faction::Dominion => "DOMINION",
+    }
+}
+
+/// Starting faction with the given symbol, if registering accepts it
+#[must_use]
+pub fn starting_faction_from_str(
+    symbol: &str,
+) -> Option<spacedust::models::register_request::Faction> {
+    match symbol {
+        "COSMIC" => Some(spacedust::models::register_request::Faction::Cosmic),
+        "VOID" => Some(spacedust::models::register_request::Faction::Void),
+        "GALACTIC" => Some(spacedust::models::register_request::Faction::Galactic),
+        "QUANTUM" => Some(spacedust::models::register_request::Faction::Quantum),
+        "DOMINION" => Some(spacedust::models::register_request::Faction::Dominion),
+        _ => None,
+    }
+}
@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+
+use spacedust::models::{Ship, ShipNavFlightMode};
+
+/// Fuel price assumed when no market we know of sells fuel
+const DEFAULT_FUEL_PRICE: i64 = 100;
+
+/// Units of a ship's fuel tank one unit of FUEL from a market fills.
+/// Assumed to be one, so a market's fuel price is the cost of each unit the ship burns.
+const TANK_UNITS_PER_FUEL: i64 = 1;
+
+/// Seconds added to every trip for docking and departure
+const TRAVEL_BASE_SECONDS: i64 = 15;
+
+/// Shortest cooldown after a jump, in seconds
+const JUMP_MIN_SECONDS: i64 = 60;
+
+/// A point in a system
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    pub symbol: String,
+    pub x: i32,
+    pub y: i32,
+}
+
+/// Last recorded prices of a good at a market
+#[derive(Debug, Clone)]
+pub struct GoodPrice {
+    pub symbol: String,
+    pub trade_volume: i32,
+    pub purchase_price: i32,
+    pub sell_price: i32,
+}
+
+/// A market whose prices have been recorded
+#[derive(Debug, Clone)]
+pub struct KnownMarket {
+    pub location: Location,
+    pub system_symbol: String,
+    pub trade_goods: Vec<GoodPrice>,
+}
+
+/// A jump gate link from the ship's system to another system
+#[derive(Debug, Clone, PartialEq)]
+pub struct Jump {
+    /// jump gate in the ship's system
+    pub from_gate: Location,
+    /// system jumped to
+    pub system_symbol: String,
+    /// jump gate ships arrive at in the other system
+    pub to_gate: Location,
+    /// distance between the systems
+    pub distance: i32,
+}
+
+/// Recorded markets around a system, for finding trade routes
+#[derive(Debug, Clone, Default)]
+pub struct TradeData {
+    /// system the data was loaded for
+    pub system_symbol: String,
+    /// markets in the system and in systems it has jump gate links to
+    pub markets: Vec<KnownMarket>,
+    /// jump gate links to other systems whose markets are known
+    pub jumps: Vec<Jump>,
+}
+
+/// Buying a good at one market and selling it at another
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradeRoute {
+    pub good: String,
+    pub buy_at: Location,
+    pub buy_price: i32,
+    /// most units the buy market trades at once
+    pub buy_volume: i32,
+    pub sell_at: Location,
+    pub sell_price: i32,
+    /// most units the sell market trades at once
+    pub sell_volume: i32,
+    /// jump needed to get from the buy market to the sell market, if they are in different systems
+    pub jump: Option<Jump>,
+    pub units: i32,
+    pub flight_mode: ShipNavFlightMode,
+    /// whether the buy market sells fuel
+    pub refuel_at_buy: bool,
+    /// whether the sell market sells fuel
+    pub refuel_at_sell: bool,
+    /// fuel used for the whole trip, including getting to the buy market
+    pub fuel: i32,
+    /// credits made after paying for the goods and fuel
+    pub profit: i64,
+    /// estimated length of the whole trip, in seconds
+    pub seconds: i64,
+}
+
+impl TradeRoute {
+    /// Estimated profit per hour spent on the trip
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn credits_per_hour(&self) -> f64 {
+        self.profit as f64 * 3600.0 / self.seconds.max(1) as f64
+    }
+}
+
+/// Straight-line distance between two locations, rounded like the API does
+#[must_use]
+pub fn distance(from: &Location, to: &Location) -> i32 {
+    let (dx, dy) = (f64::from(to.x - from.x), f64::from(to.y - from.y));
+    #[allow(clippy::cast_possible_truncation)]
+    let distance = dx.hypot(dy).round() as i32;
+    distance
+}
+
+/// Estimated seconds to travel a distance at an engine speed.
+/// These are the formulas the API used at the time of writing, so may drift.
+#[must_use]
+pub fn travel_seconds(distance: i32, speed: f32, mode: ShipNavFlightMode) -> i64 {
+    if distance == 0 {
+        return 0;
+    }
+    let multiplier = match mode {
+        ShipNavFlightMode::Cruise => 15.0,
+        ShipNavFlightMode::Burn => 7.5,
+        ShipNavFlightMode::Drift => 150.0,
+        ShipNavFlightMode::Stealth => 30.0,
+    };
+    #[allow(clippy::cast_possible_truncation)]
+    let seconds =
+        (f64::from(distance.max(1)) * multiplier / f64::from(speed.max(1.0))).round() as i64;
+    seconds + TRAVEL_BASE_SECONDS
+}
+
+/// Estimated fuel used to travel a distance
+#[must_use]
+pub fn travel_fuel(distance: i32, mode: ShipNavFlightMode) -> i32 {
+    if distance == 0 {
+        return 0;
+    }
+    match mode {
+        ShipNavFlightMode::Cruise | ShipNavFlightMode::Stealth => distance,
+        ShipNavFlightMode::Burn => distance * 2,
+        ShipNavFlightMode::Drift => 1,
+    }
+}
+
+/// Estimated cooldown after jumping a distance between systems, in seconds
+#[must_use]
+pub fn jump_seconds(distance: i32) -> i64 {
+    (i64::from(distance) / 10).max(JUMP_MIN_SECONDS)
+}
+
+/// Price of fuel at a market, if it sells fuel
+fn fuel_price(market: &KnownMarket) -> Option<i64> {
+    market
+        .trade_goods
+        .iter()
+        .find(|g| g.symbol == "FUEL")
+        .map(|g| i64::from(g.purchase_price))
+}
+
+/// Credits spent replacing the fuel a trip burns, with `to_buy` burned getting to the buy
+/// market and `after_buy` burned after it. Fuel is paid for at the next market on the way
+/// that sells it, as that's where the tank is topped up, or at `average_price` if none does.
+fn fuel_cost(
+    to_buy: i32,
+    after_buy: i32,
+    buy_price: Option<i64>,
+    sell_price: Option<i64>,
+    average_price: i64,
+) -> i64 {
+    let cost = |fuel: i32, price: i64| {
+        let units = (i64::from(fuel) + TANK_UNITS_PER_FUEL - 1) / TANK_UNITS_PER_FUEL;
+        units * price
+    };
+    let after_buy_price = sell_price.unwrap_or(average_price);
+    match buy_price {
+        Some(buy_price) => cost(to_buy, buy_price) + cost(after_buy, after_buy_price),
+        None => cost(to_buy + after_buy, after_buy_price),
+    }
+}
+
+/// Whether a ship can fly legs in turn without running dry, starting with the fuel it has now
+/// and filling its tank at the end of the first leg if `refuel_at_buy`
+fn has_fuel_for(ship: &Ship, legs: &[i32], refuel_at_buy: bool, mode: ShipNavFlightMode) -> bool {
+    // Ships without fuel tanks don't use fuel
+    if ship.fuel.capacity == 0 {
+        return true;
+    }
+    let mut fuel = ship.fuel.current;
+    for (i, &leg) in legs.iter().enumerate() {
+        if i == 1 && refuel_at_buy {
+            fuel = ship.fuel.capacity;
+        }
+        fuel -= travel_fuel(leg, mode);
+        if fuel < 0 {
+            return false;
+        }
+    }
+    true
+}
+
+/// Finds every profitable route for a ship between the recorded markets,
+/// buying in the ship's system, most credits per hour first
+#[must_use]
+pub fn find_routes(ship: &Ship, data: &TradeData, mode: ShipNavFlightMode) -> Vec<TradeRoute> {
+    let destination = &ship.nav.route.destination;
+    let start = Location {
+        symbol: destination.symbol.clone(),
+        x: destination.x,
+        y: destination.y,
+    };
+    let units = ship.cargo.capacity - ship.cargo.units;
+    if units <= 0 {
+        return Vec::new();
+    }
+
+    let fuel_prices: Vec<i64> = data.markets.iter().filter_map(fuel_price).collect();
+    let average_fuel_price = match i64::try_from(fuel_prices.len()) {
+        Ok(count) if count > 0 => fuel_prices.iter().sum::<i64>() / count,
+        _ => DEFAULT_FUEL_PRICE,
+    };
+
+    let jumps: HashMap<&str, &Jump> = data
+        .jumps
+        .iter()
+        .map(|j| (j.system_symbol.as_str(), j))
+        .collect();
+
+    let mut routes = Vec::new();
+    let buy_markets = data
+        .markets
+        .iter()
+        .filter(|m| m.system_symbol == data.system_symbol);
+    for buy_market in buy_markets {
+        for sell_market in &data.markets {
+            if sell_market.location == buy_market.location {
+                continue;
+            }
+            let jump = if sell_market.system_symbol == data.system_symbol {
+                None
+            } else if let Some(&jump) = jumps.get(sell_market.system_symbol.as_str()) {
+                Some(jump)
+            } else {
+                continue;
+            };
+
+            // Legs flown within a system, starting with the one to the buy market.
+            // Jumps use no fuel
+            let legs = match jump {
+                None => vec![
+                    distance(&start, &buy_market.location),
+                    distance(&buy_market.location, &sell_market.location),
+                ],
+                Some(jump) => vec![
+                    distance(&start, &buy_market.location),
+                    distance(&buy_market.location, &jump.from_gate),
+                    distance(&jump.to_gate, &sell_market.location),
+                ],
+            };
+            let refuel_at_buy = fuel_price(buy_market).is_some();
+            if !has_fuel_for(ship, &legs, refuel_at_buy, mode) {
+                continue;
+            }
+            let (to_buy, after_buy) = if ship.fuel.capacity > 0 {
+                let mut fuel = legs.iter().map(|&d| travel_fuel(d, mode));
+                (fuel.next().unwrap_or(0), fuel.sum())
+            } else {
+                (0, 0)
+            };
+            let fuel = to_buy + after_buy;
+            let seconds = legs
+                .iter()
+                .map(|&d| travel_seconds(d, ship.engine.speed, mode))
+                .sum::<i64>()
+                + jump.map_or(0, |j| jump_seconds(j.distance));
+            let fuel_cost = fuel_cost(
+                to_buy,
+                after_buy,
+                fuel_price(buy_market),
+                fuel_price(sell_market),
+                average_fuel_price,
+            );
+
+            for buy in &buy_market.trade_goods {
+                let Some(sell) = sell_market
+                    .trade_goods
+                    .iter()
+                    .find(|g| g.symbol == buy.symbol)
+                else {
+                    continue;
+                };
+                let profit =
+                    i64::from(units) * i64::from(sell.sell_price - buy.purchase_price) - fuel_cost;
+                if profit <= 0 {
+                    continue;
+                }
+                routes.push(TradeRoute {
+                    good: buy.symbol.clone(),
+                    buy_at: buy_market.location.clone(),
+                    buy_price: buy.purchase_price,
+                    buy_volume: buy.trade_volume,
+                    sell_at: sell_market.location.clone(),
+                    sell_price: sell.sell_price,
+                    sell_volume: sell.trade_volume,
+                    jump: jump.cloned(),
+                    units,
+                    flight_mode: mode,
+                    refuel_at_buy,
+                    refuel_at_sell: fuel_price(sell_market).is_some(),
+                    fuel,
+                    profit,
+                    seconds,
+                });
+            }
+        }
+    }
+
+    routes.sort_by(|a, b| b.credits_per_hour().total_cmp(&a.credits_per_hour()));
+    routes
+}
@@ -1,3 +1,6 @@
+use std::cmp::Ordering;
+
+use chrono::{Local, Utc};
 use once_cell::sync::Lazy;
 use spacedust::models::Contract;
 use strum::IntoEnumIterator;
@@ -6,13 +9,15 @@ use tui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Span, Spans},
-    widgets::{Block, Borders, List, ListItem, Paragraph, Row, Table, Tabs},
+    widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Row, Table, Tabs},
     Frame,
 };
 
 use crate::{
-    app::{App, Tab},
-    st_util,
+    app::{App, Section, Severity, Tab},
+    config::SETTINGS,
+    io::runner,
+    rate_limit, st_util,
 };
 
 static BASE_STYLE: Lazy<Style> = Lazy::new(|| Style::default().fg(Color::White));
@@ -45,6 +50,13 @@ macro_rules! key_value {
     };
 }
 
+mod fleet;
+mod map;
+mod market;
+mod shipyard;
+mod systems;
+mod trade;
+
 /// Renders the user interface widgets.
 pub fn render<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>) {
     // -------------------------------------------------------
@@ -59,7 +71,14 @@ pub fn render<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>) {
         .direction(Direction::Vertical)
         .vertical_margin(1)
         .horizontal_margin(2)
-        .constraints([Constraint::Length(3), Constraint::Min(2)].as_ref())
+        .constraints(
+            [
+                Constraint::Length(3),
+                Constraint::Min(2),
+                Constraint::Length(status_bar_height(app)),
+            ]
+            .as_ref(),
+        )
         .split(screen);
     frame.render_widget(BASE_BLOCK.clone(), screen);
 
@@ -81,10 +100,18 @@ pub fn render<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>) {
         })
         .collect();
 
+    // Name the agent and server when there's a choice, so it's clear which are in use
+    let mut menu_title = "Menu".to_owned();
+    if let Some(profile) = &app.profile {
+        menu_title = format!("{menu_title} - {}", profile.symbol);
+    }
+    if let Some(server) = &SETTINGS.server {
+        menu_title = format!("{menu_title} ({server})");
+    }
     frame.render_widget(
         Tabs::new(tabs)
             .select(Tab::iter().position(|x| x == app.state.tab).unwrap_or(0))
-            .block(BASE_BLOCK.clone().title("Menu"))
+            .block(BASE_BLOCK.clone().title(menu_title))
             .style(*TAB_STYLE)
             .highlight_style(*TAB_SELECTED_STYLE)
             .divider(Span::raw("|")),
@@ -96,9 +123,364 @@ pub fn render<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>) {
     // -------------------------------------------------------
     match app.state.tab {
         Tab::Agent => render_agent_tab(app, frame, chunks[1]),
-        Tab::Systems => render_systems_tab(app, frame, chunks[1]),
-        Tab::Fleet => render_fleet_tab(app, frame, chunks[1]),
+        Tab::Systems => systems::render_systems_tab(app, frame, chunks[1]),
+        Tab::Fleet => fleet::render_fleet_tab(app, frame, chunks[1]),
+        Tab::Market => market::render_market_tab(app, frame, chunks[1]),
+        Tab::Shipyard => shipyard::render_shipyard_tab(app, frame, chunks[1]),
+        Tab::Trade => trade::render_trade_tab(app, frame, chunks[1]),
     }
+
+    // -------------------------------------------------------
+    //                      Status Bar
+    // -------------------------------------------------------
+    render_status_bar(app, frame, chunks[2]);
+
+    // -------------------------------------------------------
+    //                       Popups
+    // -------------------------------------------------------
+    render_notifications(app, frame, screen);
+    render_prompt(app, frame, screen);
+    render_picker(app, frame, screen);
+    render_confirm(app, frame, screen);
+}
+
+fn severity_style(severity: Severity) -> Style {
+    match severity {
+        Severity::Info => VALUE_STYLE.fg(Color::Cyan),
+        Severity::Warning => VALUE_STYLE.fg(Color::Yellow),
+        Severity::Error => VALUE_STYLE.fg(Color::Red),
+    }
+}
+
+/// Number of rows the status bar needs: one per visible toast, or one for the hint
+fn status_bar_height(app: &App) -> u16 {
+    let visible = app
+        .state
+        .toasts
+        .iter()
+        .filter(|t| t.shown_at.is_some())
+        .count();
+    #[allow(clippy::cast_possible_truncation)]
+    let height = visible.max(1) as u16;
+    height
+}
+
+/// Title of a block showing a [`Section`], noting when it is still showing cached data
+fn section_title(app: &App, section: Section, title: &str) -> String {
+    app.state.stale_since.get(&section).map_or_else(
+        || title.to_owned(),
+        |since| {
+            format!(
+                "{title} (cached, stale since {})",
+                since.with_timezone(&Local).format("%b %d %H:%M")
+            )
+        },
+    )
+}
+
+fn render_status_bar<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>, chunk: Rect) {
+    let toasts = &app.state.toasts;
+    let queued = toasts.iter().filter(|t| t.shown_at.is_none()).count();
+
+    let mut lines: Vec<Spans> = toasts
+        .iter()
+        .filter(|t| t.shown_at.is_some())
+        .map(|toast| {
+            let notification = &toast.notification;
+            Spans::from(vec![
+                Span::styled(
+                    notification
+                        .time
+                        .with_timezone(&Local)
+                        .format(" %H:%M:%S ")
+                        .to_string(),
+                    *KEY_STYLE,
+                ),
+                Span::styled(
+                    format!("{}: {}", notification.severity, notification.message),
+                    severity_style(notification.severity),
+                ),
+            ])
+        })
+        .collect();
+    let mut hint = Spans::from(vec![
+        Span::styled(" !: ", *KEY_STYLE),
+        Span::styled(
+            format!("Notifications ({})", app.state.notifications.len()),
+            VALUE_STYLE.fg(Color::DarkGray),
+        ),
+        Span::styled("  a: ", *KEY_STYLE),
+        Span::styled(
+            format!("Agents ({})", app.profiles.len()),
+            VALUE_STYLE.fg(Color::DarkGray),
+        ),
+        Span::styled("  l: ", *KEY_STYLE),
+        Span::styled(rate_limit_summary(), VALUE_STYLE.fg(Color::DarkGray)),
+    ]);
+    let queue = rate_limit::queue_status();
+    if queue.in_flight + queue.total() > 0 {
+        let mut requests = format!("  Requests: {} in flight", queue.in_flight);
+        if queue.total() > 0 {
+            let queued: Vec<String> = queue
+                .queued
+                .iter()
+                .filter(|(_, count)| *count > 0)
+                .map(|(priority, count)| format!("{count} {priority}"))
+                .collect();
+            let wait = chrono::Duration::from_std(queue.longest_wait)
+                .unwrap_or_else(|_| chrono::Duration::zero());
+            requests = format!(
+                "{requests}, queued {}, waiting {}",
+                queued.join(", "),
+                st_util::format_duration(wait)
+            );
+        }
+        hint.0.push(Span::styled(requests, *VALUE_STYLE));
+    }
+    if let Some(activity) = activity_summary() {
+        hint.0.extend([
+            Span::styled(activity, *VALUE_STYLE),
+            Span::styled("  x: ", *KEY_STYLE),
+            Span::styled("Cancel", VALUE_STYLE.fg(Color::DarkGray)),
+        ]);
+    }
+    if app.state.offline {
+        hint.0.push(Span::styled(
+            "  Offline, showing cached data",
+            severity_style(Severity::Warning),
+        ));
+    }
+    match lines.last_mut() {
+        Some(last) => {
+            if queued > 0 {
+                last.0
+                    .push(Span::styled(format!("  (+{queued} more)"), *VALUE_STYLE));
+            }
+            last.0.push(Span::raw(" "));
+            last.0.extend(hint.0);
+        }
+        None => lines.push(hint),
+    }
+
+    frame.render_widget(Paragraph::new(lines), chunk);
+}
+
+/// Lists the events being worked on, with how long the slow ones have taken, and how many are waiting
+fn activity_summary() -> Option<String> {
+    /// Most running events listed by name before the rest are just counted
+    const MAX_LISTED: usize = 3;
+
+    let activity = runner::activity();
+    let (running, waiting): (Vec<_>, Vec<_>) = activity
+        .iter()
+        .partition(|status| status.started_at.is_some());
+    if running.is_empty() && waiting.is_empty() {
+        return None;
+    }
+
+    let mut listed: Vec<String> = running
+        .iter()
+        .take(MAX_LISTED)
+        .map(|status| {
+            let elapsed = status.elapsed();
+            if elapsed.as_secs() >= 1 {
+                let elapsed = chrono::Duration::from_std(elapsed)
+                    .unwrap_or_else(|_| chrono::Duration::zero());
+                format!("{} ({})", status.event, st_util::format_duration(elapsed))
+            } else {
+                status.event.to_string()
+            }
+        })
+        .collect();
+    if running.len() > MAX_LISTED {
+        listed.push(format!("{} more", running.len() - MAX_LISTED));
+    }
+    if !waiting.is_empty() {
+        listed.push(format!("{} waiting", waiting.len()));
+    }
+    Some(format!("  Working on: {}", listed.join(", ")))
+}
+
+/// Describes how many requests can be made, and when the server's limits reset if it has none left
+fn rate_limit_summary() -> String {
+    let bucket = rate_limit::bucket_state();
+    let summary = format!(
+        "Rate limit {}/{} free, {}/s",
+        bucket.available, bucket.limits.burst, bucket.limits.per_second
+    );
+    match (bucket.remaining, bucket.reset) {
+        (Some(0), Some(reset)) if reset > Utc::now() => format!(
+            "{summary}, server resets in {}",
+            st_util::format_duration(reset - Utc::now())
+        ),
+        _ => summary,
+    }
+}
+
+fn render_notifications<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>, chunk: Rect) {
+    if !app.state.show_notifications {
+        return;
+    }
+
+    let area = centered_rect(
+        chunk.width.saturating_mul(4) / 5,
+        chunk.height.saturating_mul(4) / 5,
+        chunk,
+    );
+
+    // Newest first
+    let items: Vec<ListItem> = app
+        .state
+        .notifications
+        .iter()
+        .rev()
+        .skip(app.state.notifications_scroll)
+        .map(|notification| {
+            let mut lines = vec![Spans::from(vec![
+                Span::styled(
+                    notification
+                        .time
+                        .with_timezone(&Local)
+                        .format(" %Y-%m-%d %H:%M:%S ")
+                        .to_string(),
+                    *KEY_STYLE,
+                ),
+                Span::styled(
+                    format!("{}: {}", notification.severity, notification.message),
+                    severity_style(notification.severity),
+                ),
+            ])];
+            lines.extend(
+                notification
+                    .details
+                    .iter()
+                    .map(|line| Spans::from(Span::styled(format!("   {line}"), *VALUE_STYLE))),
+            );
+            ListItem::new(lines)
+        })
+        .collect();
+    let title = format!(
+        "Notifications ({}) - Up/Down: Scroll, Esc: Close",
+        app.state.notifications.len()
+    );
+    let list = List::new(items)
+        .style(*LIST_STYLE)
+        .block(BASE_BLOCK.clone().title(title));
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(list, area);
+}
+
+/// Returns a rectangle of the given size centered in `chunk`, clamped to fit inside it.
+fn centered_rect(width: u16, height: u16, chunk: Rect) -> Rect {
+    let width = width.min(chunk.width);
+    let height = height.min(chunk.height);
+    Rect::new(
+        chunk.x + (chunk.width - width) / 2,
+        chunk.y + (chunk.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+fn render_picker<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>, chunk: Rect) {
+    let Some(picker) = &mut app.state.picker else {
+        return;
+    };
+
+    #[allow(clippy::cast_possible_truncation)]
+    let width = picker
+        .items
+        .iter()
+        .map(|item| item.label.len())
+        .chain([picker.title.len()])
+        .max()
+        .unwrap_or(0) as u16
+        + 4; // +4 for border + padding
+    #[allow(clippy::cast_possible_truncation)]
+    let height = (picker.items.len() as u16).max(1) + 2; // +2 for border
+    let area = centered_rect(width.max(30), height, chunk);
+
+    let list_items: Vec<ListItem> = picker
+        .items
+        .iter()
+        .map(|item| ListItem::new(format!(" {}", item.label)))
+        .collect();
+    let list = List::new(list_items)
+        .style(*LIST_STYLE)
+        .highlight_style(*LIST_SELECTED_STYLE)
+        .block(BASE_BLOCK.clone().title(picker.title.as_str()));
+
+    frame.render_widget(Clear, area);
+    frame.render_stateful_widget(list, area, &mut picker.list_state);
+}
+
+fn render_confirm<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>, chunk: Rect) {
+    let Some(confirm) = &app.state.confirm else {
+        return;
+    };
+
+    let mut lines: Vec<Spans> = confirm
+        .message
+        .iter()
+        .map(|line| Spans::from(Span::styled(format!(" {line}"), *VALUE_STYLE)))
+        .collect();
+    if let Some(change) = confirm.credit_change {
+        let credits = i64::from(app.state.agent.credits);
+        let style = match change.cmp(&0) {
+            Ordering::Less if credits + change < 0 => VALUE_STYLE.fg(Color::Red),
+            Ordering::Less => VALUE_STYLE.fg(Color::Yellow),
+            Ordering::Equal => *VALUE_STYLE,
+            Ordering::Greater => VALUE_STYLE.fg(Color::Green),
+        };
+        lines.push(Spans::from(vec![
+            Span::styled(" Credits: ", *KEY_STYLE),
+            Span::styled(
+                format!("{credits} -> {} ({change:+})", credits + change),
+                style,
+            ),
+        ]));
+    }
+    lines.push(Spans::default());
+    lines.push(Spans::from(vec![
+        Span::styled(" y: ", *KEY_STYLE),
+        Span::styled("Confirm ", *VALUE_STYLE),
+        Span::styled(" n: ", *KEY_STYLE),
+        Span::styled("Cancel", *VALUE_STYLE),
+    ]));
+
+    #[allow(clippy::cast_possible_truncation)]
+    let width = lines
+        .iter()
+        .map(Spans::width)
+        .chain([confirm.title.len()])
+        .max()
+        .unwrap_or(0) as u16
+        + 4; // +4 for border + padding
+    #[allow(clippy::cast_possible_truncation)]
+    let height = lines.len() as u16 + 2; // +2 for border
+    let area = centered_rect(width.max(30), height, chunk);
+
+    let paragraph = Paragraph::new(lines).block(BASE_BLOCK.clone().title(confirm.title.as_str()));
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
+
+fn render_prompt<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>, chunk: Rect) {
+    let Some(prompt) = &app.state.prompt else {
+        return;
+    };
+
+    let area = centered_rect(50, 3, chunk);
+    let input = Paragraph::new(Spans::from(vec![
+        Span::styled(format!(" {}", prompt.input), *VALUE_STYLE),
+        Span::styled("_", VALUE_STYLE.add_modifier(Modifier::SLOW_BLINK)),
+    ]))
+    .block(BASE_BLOCK.clone().title(prompt.title.as_str()));
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(input, area);
 }
 
 fn render_agent_tab<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>, chunk: Rect) {
@@ -128,7 +510,11 @@ fn render_agent_block<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>, chunk
         key_value!("Headquarters", &agent.headquarters),
         key_value!("Credits", agent.credits.to_string()),
     ])
-    .block(BASE_BLOCK.clone().title("Me"));
+    .block(
+        BASE_BLOCK
+            .clone()
+            .title(section_title(app, Section::Agent, "Me")),
+    );
 
     frame.render_widget(info, chunk);
 }
@@ -147,7 +533,9 @@ fn render_contracts_block<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>, c
         .margin(1)
         .split(chunk);
 
-    let border = BASE_BLOCK.clone().title("Contracts");
+    let border = BASE_BLOCK
+        .clone()
+        .title(section_title(app, Section::Contracts, "Contracts"));
     frame.render_widget(border, chunk);
 
     let list_items: Vec<ListItem> = contracts
@@ -184,23 +572,31 @@ fn render_contract<B: Backend>(
         )
         .split(chunk);
 
+    let deliveries_complete = selected_contract
+        .terms
+        .deliver
+        .iter()
+        .flatten()
+        .all(|d| d.units_fulfilled >= d.units_required);
     let action = if !selected_contract.accepted {
         Span::styled("Accept", *VALUE_STYLE)
-    } else if let Some(delivers) = &selected_contract.terms.deliver {
-        if delivers
-            .iter()
-            .all(|d| d.units_fulfilled >= d.units_required)
-        {
-            Span::styled("Fulfil", *VALUE_STYLE)
-        } else {
-            Span::styled("None", VALUE_STYLE.fg(Color::DarkGray))
-        }
+    } else if !selected_contract.fulfilled && deliveries_complete {
+        Span::styled("Fulfil", *VALUE_STYLE)
     } else {
         Span::styled("None", VALUE_STYLE.fg(Color::DarkGray))
     };
+    let deliver = if selected_contract.accepted && !selected_contract.fulfilled {
+        *VALUE_STYLE
+    } else {
+        VALUE_STYLE.fg(Color::DarkGray)
+    };
     let controls = Paragraph::new(Spans::from(vec![
         Span::styled("Enter: ", *KEY_STYLE),
         action,
+        Span::styled("  d: ", *KEY_STYLE),
+        Span::styled("Deliver", deliver),
+        Span::styled("  n: ", *KEY_STYLE),
+        Span::styled("Negotiate new contract", *VALUE_STYLE),
     ]))
     .block(BASE_BLOCK.clone().borders(Borders::BOTTOM));
     frame.render_widget(controls, chunks[0]);
@@ -300,6 +696,3 @@ fn render_factions_block<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>, ch
 
     frame.render_widget(table, chunk);
 }
-
-fn render_systems_tab<B: Backend>(_app: &mut App, _frame: &mut Frame<'_, B>, _chunk: Rect) {}
-fn render_fleet_tab<B: Backend>(_app: &mut App, _frame: &mut Frame<'_, B>, _chunk: Rect) {}
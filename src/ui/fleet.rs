@@ -0,0 +1,360 @@
+use chrono::{Local, Utc};
+use spacedust::models::Ship;
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout, Rect},
+    text::{Span, Spans},
+    widgets::{Borders, List, ListItem, Paragraph, Row, Table},
+    Frame,
+};
+
+use crate::{
+    app::{App, Section},
+    st_util,
+};
+
+use super::{
+    section_title, BASE_BLOCK, BASE_STYLE, HEADER_STYLE, KEY_STYLE, LIST_SELECTED_STYLE,
+    LIST_STYLE, VALUE_STYLE,
+};
+
+pub(super) fn render_fleet_tab<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>, chunk: Rect) {
+    #[allow(clippy::cast_possible_truncation)]
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Max(app.state.ships.len() as u16 + 3), // +3 for border + list header
+                Constraint::Length(3),
+                Constraint::Min(14),
+            ]
+            .as_ref(),
+        )
+        .split(chunk);
+
+    render_ships_block(app, frame, chunks[0]);
+    render_fleet_controls(frame, chunks[1]);
+    if let Some(index) = app.state.ships_list_state.selected() {
+        render_ship(app, frame, chunks[2], &app.state.ships[index]);
+    } else {
+        frame.render_widget(BASE_BLOCK.clone().title("Ship"), chunks[2]);
+    }
+}
+
+fn render_fleet_controls<B: Backend>(frame: &mut Frame<'_, B>, chunk: Rect) {
+    let controls: [&[(&str, &str)]; 2] = [
+        &[
+            ("o", "Orbit"),
+            ("d", "Dock"),
+            ("n", "Navigate"),
+            ("f", "Flight mode"),
+            ("m", "Market"),
+            ("y", "Shipyard"),
+            ("r", "Refresh"),
+        ],
+        &[
+            ("s", "Survey"),
+            ("e", "Extract"),
+            ("j", "Jettison"),
+            ("p", "Refine"),
+            ("PgUp/PgDn", "Scroll activity"),
+        ],
+    ];
+    let lines: Vec<Spans> = controls
+        .iter()
+        .map(|line| {
+            let spans: Vec<Span> = line
+                .iter()
+                .flat_map(|(key, action)| {
+                    [
+                        Span::styled(format!(" {key}: "), *KEY_STYLE),
+                        Span::styled(format!("{action} "), *VALUE_STYLE),
+                    ]
+                })
+                .collect();
+            Spans::from(spans)
+        })
+        .collect();
+    let paragraph = Paragraph::new(lines).block(BASE_BLOCK.clone().borders(Borders::BOTTOM));
+    frame.render_widget(paragraph, chunk);
+}
+
+/// Nav status of a ship, with a countdown if it is in transit
+fn ship_status(ship: &Ship) -> String {
+    match st_util::time_until_arrival(ship, Utc::now()) {
+        Some(remaining) => format!(
+            "{} ({})",
+            ship.nav.status.to_string(),
+            st_util::format_duration(remaining)
+        ),
+        None => ship.nav.status.to_string(),
+    }
+}
+
+/// Time left on a ship's cooldown, if any
+fn ship_cooldown(app: &App, ship: &Ship) -> String {
+    app.state
+        .cooldowns
+        .get(&ship.symbol)
+        .and_then(|c| st_util::cooldown_remaining(c, Utc::now()))
+        .map_or_else(String::new, st_util::format_duration)
+}
+
+/// Columns of the ship list, in display order.
+fn ship_columns(app: &App, ship: &Ship) -> [String; 8] {
+    [
+        ship.symbol.clone(),
+        ship.registration.role.to_string(),
+        ship.frame.name.clone(),
+        ship_status(ship),
+        ship.nav.waypoint_symbol.clone(),
+        format!("{}/{}", ship.fuel.current, ship.fuel.capacity),
+        format!("{}/{}", ship.cargo.units, ship.cargo.capacity),
+        ship_cooldown(app, ship),
+    ]
+}
+
+fn render_ships_block<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>, chunk: Rect) {
+    const HEADERS: [&str; 8] = [
+        "Symbol", "Role", "Frame", "Status", "Location", "Fuel", "Cargo", "Cooldown",
+    ];
+
+    let rows: Vec<[String; 8]> = app
+        .state
+        .ships
+        .iter()
+        .map(|ship| ship_columns(app, ship))
+        .collect();
+
+    // Calculate maximum lengths of different columns
+    let widths = rows.iter().fold(HEADERS.map(str::len), |mut widths, row| {
+        for (width, column) in widths.iter_mut().zip(row) {
+            *width = (*width).max(column.len());
+        }
+        widths
+    });
+    let format_row = |columns: &[&str]| {
+        columns
+            .iter()
+            .zip(widths)
+            .map(|(column, width)| format!("{column:<width$}"))
+            .collect::<Vec<String>>()
+            .join("  ")
+    };
+
+    let border = BASE_BLOCK
+        .clone()
+        .title(section_title(app, Section::Ships, "Fleet"));
+    let inner = border.inner(chunk);
+    frame.render_widget(border, chunk);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(0)].as_ref())
+        .split(inner);
+
+    let header = Paragraph::new(Span::styled(format_row(&HEADERS), *HEADER_STYLE));
+    frame.render_widget(header, chunks[0]);
+
+    let list_items: Vec<ListItem> = rows
+        .iter()
+        .map(|row| ListItem::new(format_row(&row.each_ref().map(String::as_str))))
+        .collect();
+    let list = List::new(list_items)
+        .style(*LIST_STYLE)
+        .highlight_style(*LIST_SELECTED_STYLE);
+
+    frame.render_stateful_widget(list, chunks[1], &mut app.state.ships_list_state);
+}
+
+fn render_ship<B: Backend>(app: &App, frame: &mut Frame<'_, B>, chunk: Rect, ship: &Ship) {
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(
+            [
+                Constraint::Percentage(40),
+                Constraint::Percentage(30),
+                Constraint::Percentage(30),
+            ]
+            .as_ref(),
+        )
+        .split(chunk);
+
+    let left = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(11), Constraint::Length(7)].as_ref())
+        .split(chunks[0]);
+    let middle = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Percentage(30),
+                Constraint::Percentage(30),
+                Constraint::Percentage(40),
+            ]
+            .as_ref(),
+        )
+        .split(chunks[1]);
+    let right = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)].as_ref())
+        .split(chunks[2]);
+
+    let details = Paragraph::new(vec![
+        key_value!("Name", &ship.registration.name),
+        key_value!(
+            "Faction",
+            ship.registration
+                .faction_symbol
+                .as_deref()
+                .unwrap_or("None")
+        ),
+        key_value!("Frame", &ship.frame.name),
+        key_value!("Reactor", &ship.reactor.name),
+        key_value!("Engine", &ship.engine.name),
+        key_value!("Flight Mode", ship.nav.flight_mode.to_string()),
+        key_value!(
+            "Fuel",
+            format!("{}/{}", ship.fuel.current, ship.fuel.capacity)
+        ),
+        key_value!(
+            "Crew",
+            format!(
+                "{}/{} (requires {})",
+                ship.crew.current, ship.crew.capacity, ship.crew.required
+            )
+        ),
+        key_value!("Morale", ship.crew.morale.to_string()),
+        key_value!("Wages", ship.crew.wages.to_string()),
+    ])
+    .block(BASE_BLOCK.clone().title(ship.symbol.as_str()));
+    frame.render_widget(details, left[0]);
+
+    let route = &ship.nav.route;
+    let route_info = Paragraph::new(vec![
+        key_value!("Status", ship_status(ship)),
+        key_value!("Departure", &route.departure.symbol),
+        key_value!("Destination", &route.destination.symbol),
+        key_value!("Arrival", &route.arrival),
+        key_value!("Cooldown", ship_cooldown(app, ship)),
+    ])
+    .block(BASE_BLOCK.clone().title("Route"));
+    frame.render_widget(route_info, left[1]);
+
+    render_equipment(frame, &middle[..2], ship);
+
+    let cargo_rows =
+        ship.cargo.inventory.iter().map(|item| {
+            Row::new(vec![item.symbol.clone(), item.units.to_string()]).style(*BASE_STYLE)
+        });
+    let cargo_table = Table::new(cargo_rows)
+        .header(Row::new(vec!["Good", "Units"]).style(*HEADER_STYLE))
+        .widths([Constraint::Percentage(75), Constraint::Percentage(25)].as_ref())
+        .column_spacing(2)
+        .block(BASE_BLOCK.clone().title(format!(
+            "Cargo ({}/{})",
+            ship.cargo.units, ship.cargo.capacity
+        )));
+    frame.render_widget(cargo_table, right[0]);
+
+    render_surveys(app, frame, middle[2], ship);
+    render_activity_log(app, frame, right[1], ship);
+}
+
+/// Lists the modules and mounts of a ship in the two given chunks
+fn render_equipment<B: Backend>(frame: &mut Frame<'_, B>, chunks: &[Rect], ship: &Ship) {
+    let modules: Vec<ListItem> = ship
+        .modules
+        .iter()
+        .map(|m| ListItem::new(m.name.clone()))
+        .collect();
+    frame.render_widget(
+        List::new(modules)
+            .style(*LIST_STYLE)
+            .block(BASE_BLOCK.clone().title("Modules")),
+        chunks[0],
+    );
+
+    let mounts: Vec<ListItem> = ship
+        .mounts
+        .iter()
+        .map(|m| {
+            ListItem::new(match m.strength {
+                Some(strength) => format!("{} ({strength})", m.name),
+                None => m.name.clone(),
+            })
+        })
+        .collect();
+    frame.render_widget(
+        List::new(mounts)
+            .style(*LIST_STYLE)
+            .block(BASE_BLOCK.clone().title("Mounts")),
+        chunks[1],
+    );
+}
+
+/// Lists the surveys of the waypoint a ship is at
+fn render_surveys<B: Backend>(app: &App, frame: &mut Frame<'_, B>, chunk: Rect, ship: &Ship) {
+    let now = Utc::now();
+    let surveys: Vec<ListItem> = app
+        .surveys_at(&ship.nav.waypoint_symbol)
+        .map(|survey| {
+            let expires = st_util::parse_time(&survey.expiration)
+                .map_or(String::new(), |e| st_util::format_duration(e - now));
+            let deposits: Vec<&str> = survey.deposits.iter().map(|d| d.symbol.as_str()).collect();
+            ListItem::new(format!(
+                "{} ({expires}): {}",
+                st_util::survey_size_to_string(survey.size),
+                deposits.join(", ")
+            ))
+        })
+        .collect();
+    frame.render_widget(
+        List::new(surveys).style(*LIST_STYLE).block(
+            BASE_BLOCK
+                .clone()
+                .title(format!("Surveys at {}", ship.nav.waypoint_symbol)),
+        ),
+        chunk,
+    );
+}
+
+/// Shows the most recent entries of a ship's activity log, scrolled back if requested
+fn render_activity_log<B: Backend>(app: &App, frame: &mut Frame<'_, B>, chunk: Rect, ship: &Ship) {
+    let log = app
+        .state
+        .activity_logs
+        .get(&ship.symbol)
+        .map_or(&[][..], Vec::as_slice);
+    let height = usize::from(chunk.height.saturating_sub(2)); // -2 for border
+    let end = log.len() - app.state.activity_scroll.min(log.len());
+    let start = end.saturating_sub(height);
+
+    let entries: Vec<ListItem> = log[start..end]
+        .iter()
+        .map(|entry| {
+            ListItem::new(Spans::from(vec![
+                Span::styled(
+                    entry
+                        .time
+                        .with_timezone(&Local)
+                        .format("%H:%M:%S ")
+                        .to_string(),
+                    *KEY_STYLE,
+                ),
+                Span::styled(entry.message.as_str(), *VALUE_STYLE),
+            ]))
+        })
+        .collect();
+    let title = if end < log.len() {
+        format!("Activity ({} newer)", log.len() - end)
+    } else {
+        "Activity".to_owned()
+    };
+    frame.render_widget(
+        List::new(entries)
+            .style(*LIST_STYLE)
+            .block(BASE_BLOCK.clone().title(title)),
+        chunk,
+    );
+}
@@ -0,0 +1,203 @@
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use spacedust::models::WaypointType;
+use tui::{
+    backend::Backend,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    symbols::Marker,
+    text::Span,
+    widgets::canvas::{Canvas, Context, Points},
+    Frame,
+};
+
+use crate::{app::App, st_util};
+
+use super::{BASE_BLOCK, BASE_STYLE};
+
+static SHIP_STYLE: Lazy<Style> =
+    Lazy::new(|| BASE_STYLE.fg(Color::Cyan).add_modifier(Modifier::BOLD));
+
+/// Colors that factions are assigned from on the galaxy map
+const FACTION_COLORS: [Color; 6] = [
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::LightCyan,
+];
+
+/// Glyph used to plot each type of waypoint
+fn waypoint_glyph(waypoint_type: WaypointType) -> &'static str {
+    match waypoint_type {
+        WaypointType::Planet => "O",
+        WaypointType::GasGiant => "@",
+        WaypointType::Moon => "o",
+        WaypointType::OrbitalStation => "#",
+        WaypointType::JumpGate => "%",
+        WaypointType::AsteroidField => "*",
+        WaypointType::Nebula => "~",
+        WaypointType::DebrisField => ":",
+        WaypointType::GravityWell => "X",
+    }
+}
+
+/// Color of a faction on the galaxy map, stable across runs.
+/// Picked by an FNV-1a hash of the symbol, which unlike the standard hasher never changes.
+fn faction_color(faction_symbol: &str) -> Color {
+    let hash = faction_symbol
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+    #[allow(clippy::cast_possible_truncation)]
+    FACTION_COLORS[(hash % FACTION_COLORS.len() as u64) as usize]
+}
+
+pub(super) fn render_map<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>, chunk: Rect) {
+    let map = &app.state.map;
+    let title = format!(
+        "{}{}",
+        if map.galaxy {
+            "Galaxy".to_owned()
+        } else {
+            format!("Map of {}", app.state.system_symbol)
+        },
+        if map.focused { " [focused]" } else { "" }
+    );
+    let block = BASE_BLOCK.clone().title(title);
+    let inner = block.inner(chunk);
+    if inner.width == 0 || inner.height == 0 {
+        frame.render_widget(block, chunk);
+        return;
+    }
+
+    // Terminal cells are roughly twice as tall as they are wide, so scale the
+    // bounds to keep distances on the map proportional.
+    let extent = app.map_extent() / map.zoom;
+    let aspect = f64::from(inner.width) / (2.0 * f64::from(inner.height));
+    let (half_width, half_height) = if aspect >= 1.0 {
+        (extent * aspect, extent)
+    } else {
+        (extent, extent / aspect)
+    };
+    let (cx, cy) = map.center;
+
+    let canvas = Canvas::default()
+        .block(block)
+        .marker(Marker::Braille)
+        .x_bounds([cx - half_width, cx + half_width])
+        // The API's y axis points down the screen
+        .y_bounds([-cy - half_height, -cy + half_height]);
+
+    let galaxy = map.galaxy;
+    let app = &*app;
+    frame.render_widget(
+        canvas.paint(|ctx| {
+            if galaxy {
+                paint_galaxy(app, ctx);
+            } else {
+                paint_system(app, ctx);
+            }
+        }),
+        chunk,
+    );
+}
+
+fn paint_system(app: &App, ctx: &mut Context) {
+    // The system's star sits at the origin
+    ctx.print(
+        0.0,
+        0.0,
+        Span::styled("+", Style::default().fg(Color::Yellow)),
+    );
+
+    let selected = app
+        .state
+        .waypoints_table_state
+        .selected()
+        .and_then(|index| app.state.waypoints.get(index));
+
+    // Orbitals share coordinates with their parent, so draw parents last to keep them visible
+    let mut waypoints: Vec<_> = app.state.waypoints.iter().collect();
+    waypoints.sort_by_key(|w| w.orbitals.len());
+    for waypoint in waypoints {
+        ctx.print(
+            f64::from(waypoint.x),
+            -f64::from(waypoint.y),
+            Span::styled(waypoint_glyph(waypoint.r#type), *BASE_STYLE),
+        );
+    }
+
+    let now = Utc::now();
+    for ship in app
+        .state
+        .ships
+        .iter()
+        .filter(|s| s.nav.system_symbol == app.state.system_symbol)
+    {
+        let (x, y) = st_util::ship_position(ship, now);
+        ctx.print(x, -y, Span::styled("^", *SHIP_STYLE));
+    }
+
+    if let Some(waypoint) = selected {
+        ctx.print(
+            f64::from(waypoint.x),
+            -f64::from(waypoint.y),
+            Span::styled(
+                format!("{} {}", waypoint_glyph(waypoint.r#type), waypoint.symbol),
+                BASE_STYLE.add_modifier(Modifier::REVERSED),
+            ),
+        );
+    }
+}
+
+fn paint_galaxy(app: &App, ctx: &mut Context) {
+    // Group systems by color so each group is drawn in one pass
+    let mut groups: Vec<(Color, Vec<(f64, f64)>)> = Vec::new();
+    for system in &app.state.systems {
+        let color = system
+            .factions
+            .first()
+            .map_or(Color::DarkGray, |f| faction_color(&f.symbol));
+        let point = (f64::from(system.x), -f64::from(system.y));
+        match groups.iter_mut().find(|(c, _)| *c == color) {
+            Some((_, points)) => points.push(point),
+            None => groups.push((color, vec![point])),
+        }
+    }
+    for (color, coords) in &groups {
+        ctx.draw(&Points {
+            coords,
+            color: *color,
+        });
+    }
+
+    // Systems our ships are in
+    for ship in &app.state.ships {
+        if let Some(system) = app
+            .state
+            .systems
+            .iter()
+            .find(|s| s.symbol == ship.nav.system_symbol)
+        {
+            ctx.print(
+                f64::from(system.x),
+                -f64::from(system.y),
+                Span::styled("^", *SHIP_STYLE),
+            );
+        }
+    }
+
+    if let Some(system) = app.current_system() {
+        ctx.print(
+            f64::from(system.x),
+            -f64::from(system.y),
+            Span::styled(
+                format!("+ {}", system.symbol),
+                BASE_STYLE.add_modifier(Modifier::REVERSED),
+            ),
+        );
+    }
+}
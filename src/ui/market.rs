@@ -0,0 +1,283 @@
+use chrono::{Local, Utc};
+use spacedust::models::{Market, Ship, TradeGood};
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::Color,
+    symbols::Marker,
+    text::{Span, Spans},
+    widgets::{Axis, Chart, Dataset, GraphType, Paragraph, Row, Table},
+    Frame,
+};
+
+use crate::{
+    app::{App, PRICE_HISTORY_DAYS},
+    db_util::PricePoint,
+    st_util,
+};
+
+use super::{BASE_BLOCK, BASE_STYLE, HEADER_STYLE, KEY_STYLE, LIST_SELECTED_STYLE, VALUE_STYLE};
+
+pub(super) fn render_market_tab<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>, chunk: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(6),
+                Constraint::Min(0),
+                Constraint::Length(14),
+            ]
+            .as_ref(),
+        )
+        .split(chunk);
+
+    render_market_block(app, frame, chunks[0]);
+    render_goods_block(app, frame, chunks[1]);
+    render_price_chart(app, frame, chunks[2]);
+}
+
+fn render_market_block<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>, chunk: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+        .margin(1)
+        .split(chunk);
+
+    let title = if app.state.market_symbol.is_empty() {
+        "Market".to_owned()
+    } else {
+        format!("Market at {}", app.state.market_symbol)
+    };
+    frame.render_widget(BASE_BLOCK.clone().title(title), chunk);
+
+    let info = if app.state.market_symbol.is_empty() {
+        vec![key_value!(
+            "No market selected",
+            "Enter on the systems page or m on the fleet page"
+        )]
+    } else {
+        let ship = app.market_ship();
+        vec![
+            key_value!("Credits", app.state.agent.credits.to_string()),
+            key_value!(
+                "Trading ship",
+                ship.map_or_else(|| "None docked here".to_owned(), |s| s.symbol.clone())
+            ),
+            key_value!(
+                "Cargo",
+                ship.map_or_else(String::new, |s| format!(
+                    "{}/{}",
+                    s.cargo.units, s.cargo.capacity
+                ))
+            ),
+        ]
+    };
+    frame.render_widget(Paragraph::new(info), chunks[0]);
+
+    let controls = Paragraph::new(vec![
+        key_value!("b", "Buy selected good"),
+        key_value!("s", "Sell selected good"),
+        key_value!("r", "Refresh"),
+    ]);
+    frame.render_widget(controls, chunks[1]);
+}
+
+/// Whether a market imports, exports or exchanges a good
+fn good_category(market: &Market, symbol: &str) -> &'static str {
+    let contains = |goods: &[TradeGood]| goods.iter().any(|g| g.symbol.to_string() == symbol);
+    if contains(&market.imports) {
+        "Import"
+    } else if contains(&market.exports) {
+        "Export"
+    } else if contains(&market.exchange) {
+        "Exchange"
+    } else {
+        ""
+    }
+}
+
+/// Units of a good held by the trading ship
+fn held_units(ship: Option<&Ship>, symbol: &str) -> i32 {
+    ship.and_then(|s| s.cargo.inventory.iter().find(|i| i.symbol == symbol))
+        .map_or(0, |i| i.units)
+}
+
+fn render_goods_block<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>, chunk: Rect) {
+    const HEADERS: [&str; 7] = [
+        "Symbol", "Category", "Supply", "Volume", "Buy", "Sell", "Held",
+    ];
+
+    let Some(market) = &app.state.market else {
+        let message = if app.state.market_symbol.is_empty() {
+            ""
+        } else {
+            "Loading..."
+        };
+        let paragraph = Paragraph::new(Span::styled(message, *VALUE_STYLE))
+            .block(BASE_BLOCK.clone().title("Goods"));
+        frame.render_widget(paragraph, chunk);
+        return;
+    };
+
+    let ship = app.market_ship();
+    let rows: Vec<[String; 7]> = if let Some(trade_goods) = &market.trade_goods {
+        trade_goods
+            .iter()
+            .map(|good| {
+                [
+                    good.symbol.clone(),
+                    good_category(market, &good.symbol).to_owned(),
+                    st_util::supply_to_string(good.supply).to_owned(),
+                    good.trade_volume.to_string(),
+                    good.purchase_price.to_string(),
+                    good.sell_price.to_string(),
+                    held_units(ship, &good.symbol).to_string(),
+                ]
+            })
+            .collect()
+    } else {
+        // Prices are only visible while a ship is present
+        [
+            (&market.imports, "Import"),
+            (&market.exports, "Export"),
+            (&market.exchange, "Exchange"),
+        ]
+        .into_iter()
+        .flat_map(|(goods, category)| {
+            goods.iter().map(move |good| {
+                let symbol = good.symbol.to_string();
+                let held = held_units(ship, &symbol).to_string();
+                [
+                    symbol,
+                    category.to_owned(),
+                    String::new(),
+                    String::new(),
+                    String::new(),
+                    String::new(),
+                    held,
+                ]
+            })
+        })
+        .collect()
+    };
+
+    // Calculate maximum lengths of different columns
+    #[allow(clippy::cast_possible_truncation)]
+    let widths = rows
+        .iter()
+        .fold(HEADERS.map(|h| h.len() as u16), |mut widths, row| {
+            for (width, column) in widths.iter_mut().zip(row) {
+                *width = (*width).max(column.len() as u16);
+            }
+            widths
+        });
+    let table_widths = widths.map(Constraint::Length);
+
+    let title = if market.trade_goods.is_some() {
+        "Goods".to_owned()
+    } else {
+        "Goods (dock a ship here to see prices)".to_owned()
+    };
+    let table = Table::new(rows.into_iter().map(|row| Row::new(row).style(*BASE_STYLE)))
+        .header(Row::new(HEADERS).style(*HEADER_STYLE))
+        .widths(table_widths.as_ref())
+        .column_spacing(2)
+        .highlight_style(*LIST_SELECTED_STYLE)
+        .block(BASE_BLOCK.clone().title(title));
+
+    frame.render_stateful_widget(table, chunk, &mut app.state.market_table_state);
+}
+
+/// Chart of the recorded buy and sell prices of the selected good
+fn render_price_chart<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>, chunk: Rect) {
+    let symbol = app.selected_market_good();
+    let title = symbol.as_ref().map_or_else(
+        || "Price history".to_owned(),
+        |symbol| format!("{symbol} price history ({PRICE_HISTORY_DAYS} days)"),
+    );
+    let block = BASE_BLOCK.clone().title(title);
+
+    let history = symbol
+        .and_then(|symbol| app.state.market_history.get(&symbol))
+        .filter(|history| !history.is_empty());
+    let Some(history) = history else {
+        let paragraph = Paragraph::new(Span::styled(
+            "No prices recorded yet, dock a ship here to record them",
+            *VALUE_STYLE,
+        ))
+        .block(block);
+        frame.render_widget(paragraph, chunk);
+        return;
+    };
+
+    // Points are placed by hours before now
+    let now = Utc::now();
+    #[allow(clippy::cast_precision_loss)]
+    let points = |price: fn(&PricePoint) -> i32| -> Vec<(f64, f64)> {
+        history
+            .iter()
+            .map(|p| {
+                let hours = (p.fetched_at - now).num_seconds() as f64 / 3600.0;
+                (hours, f64::from(price(p)))
+            })
+            .collect()
+    };
+    let buy = points(|p| p.purchase_price);
+    let sell = points(|p| p.sell_price);
+
+    let oldest = &history[0];
+    let x_min = buy[0].0.min(-1.0);
+    let (y_min, y_max) = buy
+        .iter()
+        .chain(&sell)
+        .fold((f64::MAX, f64::MIN), |(min, max), &(_, y)| {
+            (min.min(y), max.max(y))
+        });
+    // Leave some room so flat lines aren't drawn on the edge
+    let margin = ((y_max - y_min) * 0.1).max(1.0);
+    let (y_min, y_max) = ((y_min - margin).max(0.0), y_max + margin);
+
+    let datasets = vec![
+        Dataset::default()
+            .name("Buy")
+            .marker(Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(VALUE_STYLE.fg(Color::Red))
+            .data(&buy),
+        Dataset::default()
+            .name("Sell")
+            .marker(Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(VALUE_STYLE.fg(Color::Green))
+            .data(&sell),
+    ];
+    let chart = Chart::new(datasets)
+        .block(block)
+        .hidden_legend_constraints((Constraint::Ratio(1, 4), Constraint::Ratio(1, 2)))
+        .x_axis(
+            Axis::default()
+                .style(*BASE_STYLE)
+                .bounds([x_min, 0.0])
+                .labels(vec![
+                    Span::styled(
+                        oldest
+                            .fetched_at
+                            .with_timezone(&Local)
+                            .format("%b %d %H:%M")
+                            .to_string(),
+                        *VALUE_STYLE,
+                    ),
+                    Span::styled("now", *VALUE_STYLE),
+                ]),
+        )
+        .y_axis(
+            Axis::default()
+                .style(*BASE_STYLE)
+                .bounds([y_min, y_max])
+                .labels(vec![
+                    Span::styled(format!("{y_min:.0}"), *VALUE_STYLE),
+                    Span::styled(format!("{y_max:.0}"), *VALUE_STYLE),
+                ]),
+        );
+    frame.render_widget(chart, chunk);
+}
@@ -0,0 +1,173 @@
+use spacedust::models::ShipyardShip;
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout, Rect},
+    text::{Span, Spans},
+    widgets::{List, ListItem, Paragraph, Row, Table, Wrap},
+    Frame,
+};
+
+use crate::app::App;
+
+use super::{
+    BASE_BLOCK, BASE_STYLE, HEADER_STYLE, KEY_STYLE, LIST_SELECTED_STYLE, LIST_STYLE, VALUE_STYLE,
+};
+
+pub(super) fn render_shipyard_tab<B: Backend>(
+    app: &mut App,
+    frame: &mut Frame<'_, B>,
+    chunk: Rect,
+) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(6), Constraint::Min(0)].as_ref())
+        .split(chunk);
+
+    let bottom = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)].as_ref())
+        .split(chunks[1]);
+
+    render_shipyard_block(app, frame, chunks[0]);
+    render_ships_block(app, frame, bottom[0]);
+    if let Some(ship) = app.selected_shipyard_ship() {
+        render_shipyard_ship(frame, bottom[1], ship);
+    } else {
+        frame.render_widget(BASE_BLOCK.clone().title("Ship"), bottom[1]);
+    }
+}
+
+fn render_shipyard_block<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>, chunk: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+        .margin(1)
+        .split(chunk);
+
+    let title = if app.state.shipyard_symbol.is_empty() {
+        "Shipyard".to_owned()
+    } else {
+        format!("Shipyard at {}", app.state.shipyard_symbol)
+    };
+    frame.render_widget(BASE_BLOCK.clone().title(title), chunk);
+
+    let info = if app.state.shipyard_symbol.is_empty() {
+        vec![key_value!(
+            "No shipyard selected",
+            "y on the systems or fleet page"
+        )]
+    } else {
+        let ships_here = app
+            .state
+            .ships
+            .iter()
+            .filter(|s| s.nav.waypoint_symbol == app.state.shipyard_symbol)
+            .count();
+        vec![
+            key_value!("Credits", app.state.agent.credits.to_string()),
+            key_value!("Our ships here", ships_here.to_string()),
+        ]
+    };
+    frame.render_widget(Paragraph::new(info), chunks[0]);
+
+    let controls = Paragraph::new(vec![
+        key_value!("b", "Buy selected ship"),
+        key_value!("r", "Refresh"),
+    ]);
+    frame.render_widget(controls, chunks[1]);
+}
+
+fn render_ships_block<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>, chunk: Rect) {
+    let Some(shipyard) = &app.state.shipyard else {
+        let message = if app.state.shipyard_symbol.is_empty() {
+            ""
+        } else {
+            "Loading..."
+        };
+        let paragraph = Paragraph::new(Span::styled(message, *VALUE_STYLE))
+            .block(BASE_BLOCK.clone().title("Ships for sale"));
+        frame.render_widget(paragraph, chunk);
+        return;
+    };
+
+    // Prices are only visible while one of our ships is present
+    let (rows, title): (Vec<[String; 2]>, _) = if let Some(ships) = &shipyard.ships {
+        let rows = ships
+            .iter()
+            .map(|ship| [ship.name.clone(), ship.purchase_price.to_string()])
+            .collect();
+        (rows, "Ships for sale")
+    } else {
+        let rows = shipyard
+            .ship_types
+            .iter()
+            .map(|t| {
+                [
+                    t.r#type.map_or_else(String::new, |t| t.to_string()),
+                    String::new(),
+                ]
+            })
+            .collect();
+        (rows, "Ships for sale (bring a ship here to see prices)")
+    };
+
+    let table = Table::new(rows.into_iter().map(|row| Row::new(row).style(*BASE_STYLE)))
+        .header(Row::new(["Ship", "Price"]).style(*HEADER_STYLE))
+        .widths([Constraint::Percentage(70), Constraint::Percentage(30)].as_ref())
+        .column_spacing(2)
+        .highlight_style(*LIST_SELECTED_STYLE)
+        .block(BASE_BLOCK.clone().title(title));
+
+    frame.render_stateful_widget(table, chunk, &mut app.state.shipyard_table_state);
+}
+
+fn render_shipyard_ship<B: Backend>(frame: &mut Frame<'_, B>, chunk: Rect, ship: &ShipyardShip) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(9), Constraint::Min(0)].as_ref())
+        .split(chunk);
+    let lists = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+        .split(chunks[1]);
+
+    let details = Paragraph::new(vec![
+        key_value!(
+            "Type",
+            ship.r#type.map_or_else(String::new, |t| t.to_string())
+        ),
+        key_value!("Price", ship.purchase_price.to_string()),
+        key_value!("Frame", &ship.frame.name),
+        key_value!("Reactor", &ship.reactor.name),
+        key_value!("Engine", &ship.engine.name),
+        key_value!("Speed", ship.engine.speed.to_string()),
+        key_value!("Description", &ship.description),
+    ])
+    .wrap(Wrap { trim: false })
+    .block(BASE_BLOCK.clone().title(ship.name.as_str()));
+    frame.render_widget(details, chunks[0]);
+
+    let modules: Vec<ListItem> = ship
+        .modules
+        .iter()
+        .map(|m| ListItem::new(m.name.clone()))
+        .collect();
+    frame.render_widget(
+        List::new(modules)
+            .style(*LIST_STYLE)
+            .block(BASE_BLOCK.clone().title("Modules")),
+        lists[0],
+    );
+
+    let mounts: Vec<ListItem> = ship
+        .mounts
+        .iter()
+        .map(|m| ListItem::new(m.name.clone()))
+        .collect();
+    frame.render_widget(
+        List::new(mounts)
+            .style(*LIST_STYLE)
+            .block(BASE_BLOCK.clone().title("Mounts")),
+        lists[1],
+    );
+}
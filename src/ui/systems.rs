@@ -0,0 +1,219 @@
+use spacedust::models::Waypoint;
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout, Rect},
+    text::{Span, Spans},
+    widgets::{Paragraph, Row, Table, Wrap},
+    Frame,
+};
+
+use crate::app::{App, Section, WaypointSort};
+
+use super::{
+    map, section_title, BASE_BLOCK, BASE_STYLE, HEADER_STYLE, KEY_STYLE, LIST_SELECTED_STYLE,
+    VALUE_STYLE,
+};
+
+pub(super) fn render_systems_tab<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>, chunk: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(6),
+                Constraint::Min(0),
+                Constraint::Length(8),
+            ]
+            .as_ref(),
+        )
+        .split(chunk);
+
+    let middle = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(55), Constraint::Percentage(45)].as_ref())
+        .split(chunks[1]);
+
+    render_system_block(app, frame, chunks[0]);
+    render_waypoints_block(app, frame, middle[0]);
+    map::render_map(app, frame, middle[1]);
+
+    let selected = app
+        .state
+        .waypoints_table_state
+        .selected()
+        .and_then(|index| app.state.waypoints.get(index));
+    if let Some(waypoint) = selected {
+        render_waypoint_traits(frame, chunks[2], waypoint);
+    } else {
+        frame.render_widget(BASE_BLOCK.clone().title("Traits"), chunks[2]);
+    }
+}
+
+fn render_system_block<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>, chunk: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+        .margin(1)
+        .split(chunk);
+
+    let border = BASE_BLOCK
+        .clone()
+        .title(format!("System {}", app.state.system_symbol));
+    frame.render_widget(border, chunk);
+
+    let info = if let Some(system) = app.current_system() {
+        vec![
+            key_value!("Sector", &system.sector_symbol),
+            key_value!("Type", system.r#type.to_string()),
+            key_value!("Coordinates", format!("({}, {})", system.x, system.y)),
+            key_value!(
+                "Factions",
+                system
+                    .factions
+                    .iter()
+                    .map(|f| f.symbol.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(", ")
+            ),
+        ]
+    } else {
+        vec![key_value!("Loading", "...")]
+    };
+    frame.render_widget(Paragraph::new(info), chunks[0]);
+
+    let sort = format!(
+        "{} ({})",
+        app.state.waypoints_sort,
+        if app.state.waypoints_sort_descending {
+            "descending"
+        } else {
+            "ascending"
+        }
+    );
+    let controls = if app.state.map.focused {
+        Paragraph::new(vec![
+            key_value!("Arrows/+/-", "Pan and zoom map"),
+            key_value!("v", "Toggle galaxy map"),
+            key_value!("0", "Reset map"),
+            key_value!(
+                "m",
+                if app.state.map.galaxy {
+                    "Unfocus map, Enter: View system at center"
+                } else {
+                    "Unfocus map"
+                }
+            ),
+        ])
+    } else {
+        Paragraph::new(vec![
+            key_value!("g/h", "Go to system, Headquarters system"),
+            key_value!("Enter/y", "Market, Shipyard at waypoint"),
+            key_value!("s/S", format!("Sort by: {sort}")),
+            key_value!("m/r", "Focus map, Refresh"),
+        ])
+    };
+    frame.render_widget(controls, chunks[1]);
+}
+
+/// Columns of the waypoints table, in display order.
+fn waypoint_columns(waypoint: &Waypoint) -> [String; 6] {
+    [
+        waypoint.symbol.clone(),
+        waypoint.r#type.to_string(),
+        format!("({}, {})", waypoint.x, waypoint.y),
+        waypoint
+            .faction
+            .as_ref()
+            .map_or_else(String::new, |f| f.symbol.clone()),
+        waypoint
+            .orbitals
+            .iter()
+            .map(|o| o.symbol.as_str())
+            .collect::<Vec<&str>>()
+            .join(", "),
+        waypoint
+            .traits
+            .iter()
+            .map(|t| t.name.as_str())
+            .collect::<Vec<&str>>()
+            .join(", "),
+    ]
+}
+
+fn render_waypoints_block<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>, chunk: Rect) {
+    const HEADERS: [&str; 6] = [
+        "Symbol",
+        "Type",
+        "Coordinates",
+        "Faction",
+        "Orbitals",
+        "Traits",
+    ];
+
+    let rows: Vec<[String; 6]> = app.state.waypoints.iter().map(waypoint_columns).collect();
+
+    // Calculate maximum lengths of the fixed-width columns
+    #[allow(clippy::cast_possible_truncation)]
+    let widths = rows
+        .iter()
+        .fold(HEADERS.map(|h| h.len() as u16), |mut widths, row| {
+            for (width, column) in widths.iter_mut().zip(row) {
+                *width = (*width).max(column.len() as u16);
+            }
+            widths
+        });
+
+    let sort_column = match app.state.waypoints_sort {
+        WaypointSort::Symbol => 0,
+        WaypointSort::Type => 1,
+        WaypointSort::Distance => 2,
+        WaypointSort::Faction => 3,
+    };
+    let arrow = if app.state.waypoints_sort_descending {
+        "▼"
+    } else {
+        "▲"
+    };
+    let header = HEADERS.iter().enumerate().map(|(i, h)| {
+        if i == sort_column {
+            format!("{h} {arrow}")
+        } else {
+            (*h).to_owned()
+        }
+    });
+
+    let table_widths = [
+        Constraint::Length(widths[0]),
+        Constraint::Length(widths[1]),
+        Constraint::Length(widths[2]),
+        Constraint::Length(widths[3]),
+        Constraint::Length(widths[4].min(30)),
+        Constraint::Percentage(100),
+    ];
+
+    let table = Table::new(rows.into_iter().map(|row| Row::new(row).style(*BASE_STYLE)))
+        .header(Row::new(header).style(*HEADER_STYLE))
+        .widths(table_widths.as_ref())
+        .column_spacing(2)
+        .highlight_style(*LIST_SELECTED_STYLE)
+        .block(
+            BASE_BLOCK
+                .clone()
+                .title(section_title(app, Section::Waypoints, "Waypoints")),
+        );
+
+    frame.render_stateful_widget(table, chunk, &mut app.state.waypoints_table_state);
+}
+
+fn render_waypoint_traits<B: Backend>(frame: &mut Frame<'_, B>, chunk: Rect, waypoint: &Waypoint) {
+    let traits: Vec<Spans> = waypoint
+        .traits
+        .iter()
+        .map(|t| key_value!(&t.name, &t.description))
+        .collect();
+    let paragraph = Paragraph::new(traits).wrap(Wrap { trim: true }).block(
+        BASE_BLOCK
+            .clone()
+            .title(format!("{} Traits", waypoint.symbol)),
+    );
+    frame.render_widget(paragraph, chunk);
+}
@@ -0,0 +1,128 @@
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout, Rect},
+    text::{Span, Spans},
+    widgets::{Paragraph, Row, Table},
+    Frame,
+};
+
+use crate::{app::App, st_util};
+
+use super::{BASE_BLOCK, BASE_STYLE, HEADER_STYLE, KEY_STYLE, LIST_SELECTED_STYLE, VALUE_STYLE};
+
+pub(super) fn render_trade_tab<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>, chunk: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(6), Constraint::Min(0)].as_ref())
+        .split(chunk);
+
+    render_trade_block(app, frame, chunks[0]);
+    render_routes_block(app, frame, chunks[1]);
+}
+
+fn render_trade_block<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>, chunk: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+        .margin(1)
+        .split(chunk);
+
+    let title = if app.state.trade_ship.is_empty() {
+        "Trade".to_owned()
+    } else {
+        format!("Trade with {}", app.state.trade_ship)
+    };
+    frame.render_widget(BASE_BLOCK.clone().title(title), chunk);
+
+    let info = if let Some(ship) = app.ship(&app.state.trade_ship) {
+        let (systems, markets) = app
+            .state
+            .trade_data
+            .as_ref()
+            .map_or((0, 0), |data| (data.jumps.len() + 1, data.markets.len()));
+        vec![
+            key_value!("Location", ship.nav.waypoint_symbol.clone()),
+            key_value!(
+                "Free cargo",
+                (ship.cargo.capacity - ship.cargo.units).to_string()
+            ),
+            key_value!("Flight mode", app.state.trade_flight_mode.to_string()),
+            key_value!("Known markets", format!("{markets} in {systems} systems")),
+        ]
+    } else {
+        vec![key_value!(
+            "No ship selected",
+            "6 with a ship selected on the fleet page"
+        )]
+    };
+    frame.render_widget(Paragraph::new(info), chunks[0]);
+
+    let controls = Paragraph::new(vec![
+        key_value!("Enter", "Run selected route"),
+        key_value!("f", "Change flight mode"),
+        key_value!("r", "Refresh"),
+    ]);
+    frame.render_widget(controls, chunks[1]);
+}
+
+fn render_routes_block<B: Backend>(app: &mut App, frame: &mut Frame<'_, B>, chunk: Rect) {
+    const HEADERS: [&str; 9] = [
+        "Good", "Buy at", "Buy", "Sell at", "Sell", "Units", "Profit", "Time", "Cr/h",
+    ];
+
+    if app.state.trade_routes.is_empty() {
+        let message = match &app.state.trade_data {
+            _ if app.state.trade_ship.is_empty() => "",
+            None => "Loading...",
+            Some(_) => "No profitable routes, visit more markets to record their prices",
+        };
+        let paragraph = Paragraph::new(Span::styled(message, *VALUE_STYLE))
+            .block(BASE_BLOCK.clone().title("Routes"));
+        frame.render_widget(paragraph, chunk);
+        return;
+    }
+
+    let rows: Vec<[String; 9]> = app
+        .state
+        .trade_routes
+        .iter()
+        .map(|route| {
+            let sell_at = match &route.jump {
+                Some(jump) => format!("{} via {}", route.sell_at.symbol, jump.system_symbol),
+                None => route.sell_at.symbol.clone(),
+            };
+            [
+                route.good.clone(),
+                route.buy_at.symbol.clone(),
+                route.buy_price.to_string(),
+                sell_at,
+                route.sell_price.to_string(),
+                route.units.to_string(),
+                route.profit.to_string(),
+                st_util::format_duration(chrono::Duration::seconds(route.seconds)),
+                format!("{:.0}", route.credits_per_hour()),
+            ]
+        })
+        .collect();
+
+    // Calculate maximum lengths of different columns
+    #[allow(clippy::cast_possible_truncation)]
+    let widths = rows
+        .iter()
+        .fold(HEADERS.map(|h| h.len() as u16), |mut widths, row| {
+            for (width, column) in widths.iter_mut().zip(row) {
+                *width = (*width).max(column.len() as u16);
+            }
+            widths
+        });
+    let table_widths = widths.map(Constraint::Length);
+
+    let table = Table::new(rows.into_iter().map(|row| Row::new(row).style(*BASE_STYLE)))
+        .header(Row::new(HEADERS).style(*HEADER_STYLE))
+        .widths(table_widths.as_ref())
+        .column_spacing(2)
+        .highlight_style(*LIST_SELECTED_STYLE)
+        .block(BASE_BLOCK.clone().title("Routes"));
+
+    frame.render_stateful_widget(table, chunk, &mut app.state.trade_table_state);
+}
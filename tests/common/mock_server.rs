@@ -0,0 +1,545 @@
+//! A mock of the subset of the SpaceTraders v2 API the TUI uses, serving whatever state a test sets up
+
+use std::{
+    collections::{HashMap, VecDeque},
+    convert::Infallible,
+    net::{SocketAddr, TcpListener},
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use serde::Serialize;
+use serde_json::{json, Value};
+use spacedust::models::{
+    market_transaction, Agent, Contract, Cooldown, Faction, Market, MarketTransaction, Ship,
+    ShipCargoItem, ShipNavFlightMode, ShipNavStatus, System, Waypoint, WaypointType,
+};
+
+/// Page size used when a request doesn't give one, as the real API does
+const DEFAULT_PAGE_SIZE: usize = 10;
+
+/// A response sent in place of handling a matching request
+#[derive(Debug, Clone)]
+pub struct Scripted {
+    /// path the response is for, e.g. `/my/agent`
+    pub path: String,
+    pub status: StatusCode,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+    /// how long to wait before responding
+    pub delay: Duration,
+}
+
+impl Scripted {
+    /// A response with a status and a SpaceTraders error body
+    #[must_use]
+    pub fn error(path: &str, status: StatusCode, code: i64, message: &str) -> Self {
+        Self {
+            path: path.to_owned(),
+            status,
+            headers: Vec::new(),
+            body: json!({ "error": { "message": message, "code": code } }).to_string(),
+            delay: Duration::ZERO,
+        }
+    }
+
+    #[must_use]
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    #[must_use]
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+}
+
+/// Game state the mock server serves and changes as requests come in
+#[derive(Debug, Default)]
+pub struct MockState {
+    pub agent: Agent,
+    pub ships: Vec<Ship>,
+    pub contracts: Vec<Contract>,
+    pub factions: Vec<Faction>,
+    pub systems: Vec<System>,
+    pub waypoints: Vec<Waypoint>,
+    /// markets by waypoint symbol
+    pub markets: HashMap<String, Market>,
+    /// date the server last reset, as given by its status
+    pub reset_date: String,
+    /// responses sent instead of handling requests, the first matching a request being used up
+    pub scripted: VecDeque<Scripted>,
+    /// headers added to every response that isn't scripted
+    pub headers: Vec<(String, String)>,
+    /// requests received, as `METHOD /path?query`
+    pub requests: Vec<String>,
+    /// bearer token each request was made with, in the same order as `requests`
+    pub tokens: Vec<Option<String>>,
+}
+
+impl MockState {
+    /// Number of requests received for a path, ignoring the query
+    #[must_use]
+    pub fn requests_to(&self, path: &str) -> usize {
+        self.requests
+            .iter()
+            .filter(|r| r.split_once(' ').map(|(_, p)| p.split('?').next()) == Some(Some(path)))
+            .count()
+    }
+
+    fn ship_mut(&mut self, symbol: &str) -> Option<&mut Ship> {
+        self.ships.iter_mut().find(|s| s.symbol == symbol)
+    }
+}
+
+/// A running mock server, which lives as long as the runtime it was started on
+pub struct MockServer {
+    /// base URL to send requests to
+    pub url: String,
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockServer {
+    /// Starts a server on a free local port with empty state
+    ///
+    /// # Panics
+    /// Panics if no port is free
+    #[must_use]
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("a local port is free");
+        let addr: SocketAddr = listener.local_addr().expect("listener has an address");
+        let state = Arc::new(Mutex::new(MockState::default()));
+
+        let service_state = state.clone();
+        let make_service = make_service_fn(move |_| {
+            let state = service_state.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let state = state.clone();
+                    async move { Ok::<_, Infallible>(respond(&state, request).await) }
+                }))
+            }
+        });
+        let server = Server::from_tcp(listener)
+            .expect("listener can be served")
+            .serve(make_service);
+        tokio::spawn(server);
+
+        Self {
+            url: format!("http://{addr}"),
+            state,
+        }
+    }
+
+    /// Locks the server's state to set it up or check what it received
+    pub fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Replaces the server's state, forgetting any requests received
+    pub fn reset(&self, state: MockState) {
+        *self.state() = state;
+    }
+
+    /// Sends a response in place of handling the next matching request
+    pub fn script(&self, response: Scripted) {
+        self.state().scripted.push_back(response);
+    }
+}
+
+async fn respond(state: &Mutex<MockState>, request: Request<Body>) -> Response<Body> {
+    let (parts, body) = request.into_parts();
+    let body = hyper::body::to_bytes(body).await.unwrap_or_default();
+    let body: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+    let path = parts.uri.path().to_owned();
+    let query = parts.uri.query().unwrap_or_default().to_owned();
+
+    let (scripted, headers) = {
+        let mut state = state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        state
+            .requests
+            .push(format!("{} {}", parts.method, parts.uri));
+        let token = parts
+            .headers
+            .get(hyper::header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(ToOwned::to_owned);
+        state.tokens.push(token);
+        let index = state.scripted.iter().position(|s| s.path == path);
+        (
+            index.and_then(|index| state.scripted.remove(index)),
+            state.headers.clone(),
+        )
+    };
+    if let Some(scripted) = scripted {
+        tokio::time::sleep(scripted.delay).await;
+        return build(scripted.status, &scripted.headers, scripted.body);
+    }
+
+    let (status, body) = {
+        let mut state = state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        route(&mut state, &parts.method, &path, &query, &body)
+    };
+    build(status, &headers, body.to_string())
+}
+
+fn build(status: StatusCode, headers: &[(String, String)], body: String) -> Response<Body> {
+    let mut response = Response::builder()
+        .status(status)
+        .header("content-type", "application/json");
+    for (name, value) in headers {
+        response = response.header(name, value);
+    }
+    response
+        .body(Body::from(body))
+        .unwrap_or_else(|_| Response::new(Body::empty()))
+}
+
+fn data(value: impl Serialize) -> (StatusCode, Value) {
+    (StatusCode::OK, json!({ "data": value }))
+}
+
+fn error(status: StatusCode, code: i64, message: &str) -> (StatusCode, Value) {
+    (
+        status,
+        json!({ "error": { "message": message, "code": code } }),
+    )
+}
+
+fn not_found() -> (StatusCode, Value) {
+    error(StatusCode::NOT_FOUND, 404, "Not found")
+}
+
+/// Responds with one page of a list, as the API's list endpoints do
+fn page<T: Serialize>(items: &[T], query: &str) -> (StatusCode, Value) {
+    let param = |name: &str| {
+        query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .and_then(|(_, value)| value.parse::<usize>().ok())
+    };
+    let page = param("page").unwrap_or(1).max(1);
+    let limit = param("limit").unwrap_or(DEFAULT_PAGE_SIZE).max(1);
+    let data: Vec<&T> = items.iter().skip((page - 1) * limit).take(limit).collect();
+    (
+        StatusCode::OK,
+        json!({
+            "data": data,
+            "meta": { "total": items.len(), "page": page, "limit": limit },
+        }),
+    )
+}
+
+fn route(
+    state: &mut MockState,
+    method: &Method,
+    path: &str,
+    query: &str,
+    body: &Value,
+) -> (StatusCode, Value) {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match (method, segments.as_slice()) {
+        (&Method::GET, [""]) => (
+            StatusCode::OK,
+            json!({
+                "status": "SpaceTraders is currently online and available to play",
+                "version": "v2",
+                "resetDate": state.reset_date,
+            }),
+        ),
+        (&Method::POST, ["register"]) => register(state, body),
+        (&Method::GET, ["my", "agent"]) => data(&state.agent),
+        (&Method::GET, ["my", "ships"]) => page(&state.ships, query),
+        (&Method::GET, ["my", "contracts"]) => page(&state.contracts, query),
+        (&Method::POST, ["my", "contracts", id, "accept"]) => accept_contract(state, id),
+        (&Method::GET, ["factions"]) => page(&state.factions, query),
+        (&Method::GET, ["systems"]) => page(&state.systems, query),
+        (&Method::GET, ["systems", symbol]) => state
+            .systems
+            .iter()
+            .find(|s| s.symbol == *symbol)
+            .map_or_else(not_found, data),
+        (&Method::GET, ["systems", symbol, "waypoints"]) => {
+            let waypoints: Vec<&Waypoint> = state
+                .waypoints
+                .iter()
+                .filter(|w| w.system_symbol == *symbol)
+                .collect();
+            page(&waypoints, query)
+        }
+        (&Method::GET, ["systems", _, "waypoints", symbol, "market"]) => {
+            state.markets.get(*symbol).map_or_else(not_found, data)
+        }
+        (method, ["my", "ships", symbol, action]) => {
+            ship_action(state, method, symbol, action, body)
+        }
+        _ => not_found(),
+    }
+}
+
+/// Registers an agent in place of the current one, starting with a ship and a contract
+fn register(state: &mut MockState, body: &Value) -> (StatusCode, Value) {
+    let symbol = body["symbol"].as_str().unwrap_or_default().to_uppercase();
+    if symbol == state.agent.symbol {
+        return error(
+            StatusCode::CONFLICT,
+            4111,
+            "Agent symbol has already been claimed",
+        );
+    }
+    let faction = body["faction"].as_str().unwrap_or_default().to_owned();
+
+    state.agent = Agent {
+        account_id: "account".to_owned(),
+        symbol: symbol.clone(),
+        headquarters: "X1-TS-A1".to_owned(),
+        credits: 100_000,
+    };
+    let ship = super::ship(&format!("{symbol}-1"));
+    state.ships = vec![ship.clone()];
+    let contract = Contract {
+        id: format!("{symbol}-contract"),
+        faction_symbol: faction.clone(),
+        ..Contract::default()
+    };
+    state.contracts = vec![contract.clone()];
+    // Shaped like a JWT, with the agent's symbol as its identifier
+    let claims = URL_SAFE_NO_PAD.encode(json!({ "identifier": symbol }).to_string());
+    data(json!({
+        "agent": state.agent,
+        "contract": contract,
+        "faction": Faction { symbol: faction, ..Faction::default() },
+        "ship": ship,
+        "token": format!("header.{claims}.signature"),
+    }))
+}
+
+fn accept_contract(state: &mut MockState, id: &str) -> (StatusCode, Value) {
+    let Some(contract) = state.contracts.iter_mut().find(|c| c.id == id) else {
+        return not_found();
+    };
+    if contract.accepted {
+        return error(StatusCode::BAD_REQUEST, 4501, "Contract already accepted");
+    }
+    contract.accepted = true;
+    state.agent.credits += contract.terms.payment.on_accepted;
+    let contract = contract.clone();
+    data(json!({ "agent": state.agent, "contract": contract }))
+}
+
+fn ship_action(
+    state: &mut MockState,
+    method: &Method,
+    symbol: &str,
+    action: &str,
+    body: &Value,
+) -> (StatusCode, Value) {
+    let Some(ship) = state.ship_mut(symbol) else {
+        return not_found();
+    };
+    match (method, action) {
+        (&Method::GET, "nav") => data(&ship.nav),
+        (&Method::PATCH, "nav") => {
+            let mode = serde_json::from_value::<ShipNavFlightMode>(body["flightMode"].clone());
+            if let Ok(mode) = mode {
+                ship.nav.flight_mode = mode;
+            }
+            data(&ship.nav)
+        }
+        (&Method::POST, "orbit") => {
+            ship.nav.status = ShipNavStatus::InOrbit;
+            data(json!({ "nav": ship.nav }))
+        }
+        (&Method::POST, "dock") => {
+            ship.nav.status = ShipNavStatus::Docked;
+            data(json!({ "nav": ship.nav }))
+        }
+        (&Method::POST, "navigate") => {
+            let destination = body["waypointSymbol"]
+                .as_str()
+                .unwrap_or_default()
+                .to_owned();
+            navigate(state, symbol, &destination)
+        }
+        (&Method::POST, "jump") => {
+            let system = body["systemSymbol"].as_str().unwrap_or_default().to_owned();
+            jump(state, symbol, &system)
+        }
+        (&Method::POST, "purchase" | "sell") => {
+            let good = body["symbol"].as_str().unwrap_or_default().to_owned();
+            let units = body["units"].as_i64().unwrap_or_default();
+            let units = i32::try_from(units).unwrap_or_default();
+            trade(state, symbol, &good, units, action == "purchase")
+        }
+        _ => not_found(),
+    }
+}
+
+/// Moves a ship straight to its destination, arriving immediately
+fn navigate(state: &mut MockState, symbol: &str, destination: &str) -> (StatusCode, Value) {
+    let Some(waypoint) = state
+        .waypoints
+        .iter()
+        .find(|w| w.symbol == destination)
+        .cloned()
+    else {
+        return error(StatusCode::NOT_FOUND, 404, "Waypoint not found");
+    };
+    let Some(ship) = state.ship_mut(symbol) else {
+        return not_found();
+    };
+    if ship.nav.status != ShipNavStatus::InOrbit {
+        return error(StatusCode::BAD_REQUEST, 4236, "Ship is not in orbit");
+    }
+
+    let route = &mut ship.nav.route;
+    route.departure.clone_from(&route.destination);
+    route.destination.symbol.clone_from(&waypoint.symbol);
+    route.destination.r#type = waypoint.r#type;
+    route
+        .destination
+        .system_symbol
+        .clone_from(&waypoint.system_symbol);
+    route.destination.x = waypoint.x;
+    route.destination.y = waypoint.y;
+    let now = Utc::now().to_rfc3339();
+    route.departure_time.clone_from(&now);
+    route.arrival = now;
+    let (dx, dy) = (
+        f64::from(route.destination.x - route.departure.x),
+        f64::from(route.destination.y - route.departure.y),
+    );
+    #[allow(clippy::cast_possible_truncation)]
+    let distance = dx.hypot(dy).round() as i32;
+    ship.fuel.current = (ship.fuel.current - distance).max(0);
+    ship.nav.waypoint_symbol = waypoint.symbol;
+    ship.nav.status = ShipNavStatus::InTransit;
+    data(json!({ "fuel": ship.fuel, "nav": ship.nav }))
+}
+
+/// Jumps a ship to the jump gate of another system, with no cooldown
+fn jump(state: &mut MockState, symbol: &str, system: &str) -> (StatusCode, Value) {
+    let Some(gate) = state
+        .waypoints
+        .iter()
+        .find(|w| w.system_symbol == system && w.r#type == WaypointType::JumpGate)
+        .cloned()
+    else {
+        return error(StatusCode::NOT_FOUND, 404, "System has no jump gate");
+    };
+    let Some(ship) = state.ship_mut(symbol) else {
+        return not_found();
+    };
+    if ship.nav.status != ShipNavStatus::InOrbit {
+        return error(StatusCode::BAD_REQUEST, 4236, "Ship is not in orbit");
+    }
+
+    let route = &mut ship.nav.route;
+    route.departure.clone_from(&route.destination);
+    route.destination.symbol.clone_from(&gate.symbol);
+    route.destination.r#type = gate.r#type;
+    route
+        .destination
+        .system_symbol
+        .clone_from(&gate.system_symbol);
+    route.destination.x = gate.x;
+    route.destination.y = gate.y;
+    ship.nav.system_symbol = gate.system_symbol;
+    ship.nav.waypoint_symbol = gate.symbol;
+    let cooldown = Cooldown::new(symbol.to_owned(), 0, 0, Utc::now().to_rfc3339());
+    data(json!({ "cooldown": cooldown, "nav": ship.nav }))
+}
+
+/// Buys or sells cargo at the market a ship is docked at
+fn trade(
+    state: &mut MockState,
+    symbol: &str,
+    good: &str,
+    units: i32,
+    purchase: bool,
+) -> (StatusCode, Value) {
+    let Some(ship) = state.ships.iter_mut().find(|s| s.symbol == symbol) else {
+        return not_found();
+    };
+    if ship.nav.status != ShipNavStatus::Docked {
+        return error(StatusCode::BAD_REQUEST, 4244, "Ship is not docked");
+    }
+    let Some(price) = state
+        .markets
+        .get(&ship.nav.waypoint_symbol)
+        .and_then(|m| m.trade_goods.as_ref())
+        .and_then(|goods| goods.iter().find(|g| g.symbol == good))
+        .map(|g| {
+            if purchase {
+                g.purchase_price
+            } else {
+                g.sell_price
+            }
+        })
+    else {
+        return error(StatusCode::BAD_REQUEST, 4602, "Good is not traded here");
+    };
+
+    let cargo = &mut ship.cargo;
+    let held = cargo.inventory.iter().position(|i| i.symbol == good);
+    if purchase {
+        if cargo.units + units > cargo.capacity {
+            return error(StatusCode::BAD_REQUEST, 4228, "Not enough cargo space");
+        }
+        match held {
+            Some(index) => cargo.inventory[index].units += units,
+            None => cargo.inventory.push(ShipCargoItem::new(
+                good.to_owned(),
+                good.to_owned(),
+                String::new(),
+                units,
+            )),
+        }
+        cargo.units += units;
+        state.agent.credits -= price * units;
+    } else {
+        let Some(index) = held.filter(|&index| cargo.inventory[index].units >= units) else {
+            return error(StatusCode::BAD_REQUEST, 4219, "Not enough cargo to sell");
+        };
+        cargo.inventory[index].units -= units;
+        cargo.inventory.retain(|i| i.units > 0);
+        cargo.units -= units;
+        state.agent.credits += price * units;
+    }
+
+    let transaction = MarketTransaction {
+        waypoint_symbol: ship.nav.waypoint_symbol.clone(),
+        ship_symbol: symbol.to_owned(),
+        trade_symbol: good.to_owned(),
+        r#type: if purchase {
+            market_transaction::RHashType::Purchase
+        } else {
+            market_transaction::RHashType::Sell
+        },
+        units,
+        price_per_unit: price,
+        total_price: price * units,
+        timestamp: Utc::now().to_rfc3339(),
+    };
+    let cargo = ship.cargo.clone();
+    (
+        StatusCode::CREATED,
+        json!({ "data": { "agent": state.agent, "cargo": cargo, "transaction": transaction } }),
+    )
+}
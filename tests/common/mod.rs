@@ -0,0 +1,169 @@
+//! Shared setup for the integration tests, which run against a local [`MockServer`]
+
+// Each test binary only uses some of the helpers
+#![allow(dead_code)]
+
+pub mod mock_server;
+
+use std::{
+    env,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use once_cell::sync::Lazy;
+use spacedust::models::{
+    market_trade_good, Agent, Market, MarketTradeGood, Ship, ShipNavStatus, Waypoint, WaypointType,
+};
+use spacetraders_tui::{
+    app::{App, AppHandle},
+    db_util,
+    io::{runner, AgentEvent},
+    rate_limit::{self, RateLimits, RetryPolicy},
+};
+use tokio::{runtime::Runtime, sync::mpsc};
+
+#[allow(unused_imports)]
+pub use mock_server::{MockServer, MockState, Scripted};
+
+/// Runtime shared by every test, since the rate limiter and database pool outlive any one test
+static RUNTIME: Lazy<Runtime> = Lazy::new(|| {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("runtime can be built")
+});
+
+/// Tests share the server and global state, so run one at a time
+static LOCK: Mutex<()> = Mutex::new(());
+
+static SERVER: Lazy<MockServer> = Lazy::new(|| {
+    let server = RUNTIME.block_on(async { MockServer::start() });
+    let database = env::temp_dir().join(format!("spacetraders-test-{}.db", std::process::id()));
+    env::set_var("SPACETRADERS_URL", &server.url);
+    env::set_var("TOKEN", "test-token");
+    env::set_var(
+        "DATABASE_URL",
+        format!("sqlite://{}?mode=rwc", database.display()),
+    );
+    RUNTIME.block_on(db_util::setup_database());
+    server
+});
+
+/// Runs a test against the mock server, which starts with empty state
+pub fn run<F: Future>(test: impl FnOnce(&'static MockServer) -> F) -> F::Output {
+    let _guard = LOCK
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    let server = &*SERVER;
+    server.reset(MockState::default());
+    // Requests shouldn't wait on anything but the behaviour under test
+    rate_limit::set_limits(RateLimits {
+        burst: 100,
+        per_second: 100,
+    });
+    rate_limit::set_retry_policy(RetryPolicy {
+        max_retries: 3,
+        backoff_seconds: 0.01,
+        max_backoff_seconds: 0.05,
+    });
+    RUNTIME.block_on(test(server))
+}
+
+/// An app whose updates are applied as they arrive, like the UI loop does,
+/// kept behind a mutex so tests can look at it in between
+#[must_use]
+pub fn app() -> (Arc<tokio::sync::Mutex<App>>, AppHandle) {
+    let (sender, _) = mpsc::channel(100);
+    owned_app(App::new(sender))
+}
+
+/// An app whose IO events are run as they are dispatched, like in the real app,
+/// and a sender for the same events to check on them with [`settle`]
+#[must_use]
+pub fn running_app() -> (Arc<tokio::sync::Mutex<App>>, mpsc::Sender<AgentEvent>) {
+    let (sender, receiver) = mpsc::channel(100);
+    let (app, handle) = owned_app(App::new(sender.clone()));
+    tokio::spawn(runner::run(handle, receiver));
+    (app, sender)
+}
+
+fn owned_app(app: App) -> (Arc<tokio::sync::Mutex<App>>, AppHandle) {
+    let app = Arc::new(tokio::sync::Mutex::new(app));
+    let (handle, mut updates) = AppHandle::new();
+    let owner = app.clone();
+    tokio::spawn(async move {
+        while let Some(update) = updates.recv().await {
+            update(&mut *owner.lock().await);
+        }
+    });
+    (app, handle)
+}
+
+/// Waits until every event sent to the runner has been received and finished
+pub async fn settle(sender: &mpsc::Sender<AgentEvent>) {
+    while sender.capacity() < sender.max_capacity() || !runner::activity().is_empty() {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+#[must_use]
+pub fn agent() -> Agent {
+    Agent {
+        account_id: "account".to_owned(),
+        symbol: "TEST".to_owned(),
+        headquarters: "X1-TS-A1".to_owned(),
+        credits: 10_000,
+    }
+}
+
+#[must_use]
+pub fn waypoint(symbol: &str, x: i32, y: i32) -> Waypoint {
+    Waypoint {
+        symbol: symbol.to_owned(),
+        r#type: WaypointType::Planet,
+        system_symbol: "X1-TS".to_owned(),
+        x,
+        y,
+        ..Waypoint::default()
+    }
+}
+
+/// A ship docked at the first waypoint of the test system
+#[must_use]
+pub fn ship(symbol: &str) -> Ship {
+    let mut ship = Ship {
+        symbol: symbol.to_owned(),
+        ..Ship::default()
+    };
+    let waypoint = waypoint("X1-TS-A1", 0, 0);
+    ship.nav.system_symbol.clone_from(&waypoint.system_symbol);
+    ship.nav.waypoint_symbol.clone_from(&waypoint.symbol);
+    ship.nav.status = ShipNavStatus::Docked;
+    ship.nav.route.destination.symbol = waypoint.symbol;
+    ship.nav.route.destination.system_symbol = waypoint.system_symbol;
+    ship.cargo.capacity = 30;
+    ship.fuel.capacity = 100;
+    ship.fuel.current = 100;
+    ship
+}
+
+/// A market at the first waypoint of the test system selling iron ore and fuel
+#[must_use]
+pub fn market() -> Market {
+    let good = |symbol: &str, purchase_price, sell_price| {
+        MarketTradeGood::new(
+            symbol.to_owned(),
+            10,
+            market_trade_good::Supply::Moderate,
+            purchase_price,
+            sell_price,
+        )
+    };
+    Market {
+        symbol: "X1-TS-A1".to_owned(),
+        trade_goods: Some(vec![good("IRON_ORE", 20, 15), good("FUEL", 2, 1)]),
+        ..Market::default()
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+
+use spacetraders_tui::config::{ConfigFile, ServerConfig, Settings};
+
+fn config_file() -> ConfigFile {
+    serde_json::from_str(
+        r#"{
+            "token": "default-token",
+            "database_url": "sqlite://default.db",
+            "rate_limit_burst": 5,
+            "servers": {
+                "live": { "token": "live-token" },
+                "local": {
+                    "url": "http://localhost:8080",
+                    "token": "local-token",
+                    "database_url": "sqlite://local.db"
+                }
+            }
+        }"#,
+    )
+    .expect("config file parses")
+}
+
+fn resolve(file: ConfigFile, vars: &[(&str, &str)]) -> anyhow::Result<Settings> {
+    let vars: HashMap<_, _> = vars.iter().copied().collect();
+    Settings::resolve(file, |name| vars.get(name).map(|v| (*v).to_owned()))
+}
+
+#[test]
+fn no_server_uses_top_level_settings() {
+    let settings = resolve(config_file(), &[]).expect("settings resolve");
+
+    assert_eq!(settings.server, None);
+    assert_eq!(settings.url, None);
+    assert_eq!(settings.token.as_deref(), Some("default-token"));
+    assert_eq!(
+        settings.database_url.as_deref(),
+        Some("sqlite://default.db")
+    );
+    assert_eq!(settings.rate_limit_burst, Some(5));
+    assert_eq!(settings.rate_limit_per_second, None);
+}
+
+#[test]
+fn chosen_server_overrides_top_level_settings() {
+    let file = ConfigFile {
+        server: Some("local".to_owned()),
+        ..config_file()
+    };
+
+    let settings = resolve(file, &[]).expect("settings resolve");
+
+    assert_eq!(settings.server.as_deref(), Some("local"));
+    assert_eq!(settings.url.as_deref(), Some("http://localhost:8080"));
+    assert_eq!(settings.token.as_deref(), Some("local-token"));
+    assert_eq!(settings.database_url.as_deref(), Some("sqlite://local.db"));
+}
+
+#[test]
+fn server_settings_left_out_fall_back_to_top_level() {
+    let settings =
+        resolve(config_file(), &[("SPACETRADERS_SERVER", "live")]).expect("settings resolve");
+
+    assert_eq!(settings.server.as_deref(), Some("live"));
+    assert_eq!(settings.token.as_deref(), Some("live-token"));
+    assert_eq!(
+        settings.database_url.as_deref(),
+        Some("sqlite://default.db")
+    );
+}
+
+#[test]
+fn environment_overrides_config_file() {
+    let settings = resolve(
+        config_file(),
+        &[
+            ("SPACETRADERS_SERVER", "local"),
+            ("SPACETRADERS_URL", "http://staging:9000"),
+            ("TOKEN", "env-token"),
+            ("RATE_LIMIT_BURST", "2"),
+            ("LOG_FILE", "other.log"),
+        ],
+    )
+    .expect("settings resolve");
+
+    assert_eq!(settings.url.as_deref(), Some("http://staging:9000"));
+    assert_eq!(settings.token.as_deref(), Some("env-token"));
+    assert_eq!(settings.database_url.as_deref(), Some("sqlite://local.db"));
+    assert_eq!(settings.rate_limit_burst, Some(2));
+    assert_eq!(settings.log_file.as_deref(), Some("other.log"));
+}
+
+#[test]
+fn unknown_server_is_an_error() {
+    let error = resolve(config_file(), &[("SPACETRADERS_SERVER", "staging")])
+        .expect_err("server isn't listed");
+
+    assert_eq!(
+        error.to_string(),
+        "Unknown server staging, expected one of: live, local"
+    );
+}
+
+#[test]
+fn invalid_settings_are_errors() {
+    assert!(serde_json::from_str::<ConfigFile>(r#"{ "tokn": "typo" }"#).is_err());
+    assert!(resolve(ConfigFile::default(), &[("RATE_LIMIT_BURST", "lots")]).is_err());
+    assert_eq!(
+        serde_json::from_str::<ServerConfig>("{}").expect("empty server parses"),
+        ServerConfig::default()
+    );
+}
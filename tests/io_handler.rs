@@ -0,0 +1,173 @@
+mod common;
+
+use std::collections::HashMap;
+
+use spacedust::models::{ShipNavFlightMode, ShipNavStatus};
+use spacetraders_tui::{
+    api_error::ApiError, app::AppHandle, db_util, io::handler::IoHandler, io::IoEvent,
+};
+
+use common::MockState;
+
+/// State with the test agent, one docked ship, two waypoints and a market
+fn docked_ship_state() -> MockState {
+    MockState {
+        agent: common::agent(),
+        ships: vec![common::ship("TEST-1")],
+        waypoints: vec![
+            common::waypoint("X1-TS-A1", 0, 0),
+            common::waypoint("X1-TS-B2", 3, 4),
+        ],
+        markets: HashMap::from([("X1-TS-A1".to_owned(), common::market())]),
+        ..MockState::default()
+    }
+}
+
+#[test]
+fn update_agent_sets_and_records_agent() {
+    common::run(|server| async move {
+        server.reset(docked_ship_state());
+        let (app, handle) = common::app();
+        let mut handler = IoHandler::new(handle);
+
+        handler
+            .handle_io_event(IoEvent::UpdateAgent)
+            .await
+            .expect("agent is updated");
+
+        assert_eq!(app.lock().await.state.agent, common::agent());
+        let cache = db_util::load_cache("TEST")
+            .await
+            .expect("cache loads")
+            .expect("agent was recorded");
+        assert_eq!(cache.agent.data, common::agent());
+    });
+}
+
+#[test]
+fn ship_actions_update_ship_state() {
+    common::run(|server| async move {
+        server.reset(docked_ship_state());
+        let (app, handle) = common::app();
+        let mut handler = IoHandler::new(handle);
+
+        handler
+            .handle_io_event(IoEvent::UpdateShips)
+            .await
+            .expect("ships are updated");
+        handler
+            .handle_io_event(IoEvent::OrbitShip("TEST-1".to_owned()))
+            .await
+            .expect("ship orbits");
+        assert_eq!(
+            app.lock().await.ship("TEST-1").map(|s| s.nav.status),
+            Some(ShipNavStatus::InOrbit)
+        );
+
+        handler
+            .handle_io_event(IoEvent::SetFlightMode {
+                ship: "TEST-1".to_owned(),
+                mode: ShipNavFlightMode::Burn,
+            })
+            .await
+            .expect("flight mode is set");
+        handler
+            .handle_io_event(IoEvent::NavigateShip {
+                ship: "TEST-1".to_owned(),
+                waypoint: "X1-TS-B2".to_owned(),
+            })
+            .await
+            .expect("ship navigates");
+
+        let app = app.lock().await;
+        let ship = app.ship("TEST-1").expect("ship is known");
+        assert_eq!(ship.nav.status, ShipNavStatus::InTransit);
+        assert_eq!(ship.nav.flight_mode, ShipNavFlightMode::Burn);
+        assert_eq!(ship.nav.route.destination.symbol, "X1-TS-B2");
+        assert_eq!(ship.fuel.current, 95);
+    });
+}
+
+#[test]
+fn purchase_cargo_updates_agent_cargo_and_market() {
+    common::run(|server| async move {
+        server.reset(docked_ship_state());
+        let (app, handle) = common::app();
+        let mut handler = IoHandler::new(handle);
+        handler
+            .handle_io_event(IoEvent::UpdateShips)
+            .await
+            .expect("ships are updated");
+        app.lock().await.state.market_symbol = "X1-TS-A1".to_owned();
+
+        handler
+            .handle_io_event(IoEvent::PurchaseCargo {
+                ship: "TEST-1".to_owned(),
+                good: "IRON_ORE".to_owned(),
+                units: 5,
+            })
+            .await
+            .expect("cargo is bought");
+
+        let app = app.lock().await;
+        assert_eq!(app.state.agent.credits, 10_000 - 5 * 20);
+        let cargo = &app.ship("TEST-1").expect("ship is known").cargo;
+        assert_eq!(cargo.units, 5);
+        assert_eq!(cargo.inventory[0].symbol, "IRON_ORE");
+        // Prices move after every trade, so the market is fetched again
+        assert!(app.state.market.is_some());
+        assert_eq!(
+            server
+                .state()
+                .requests_to("/systems/X1-TS/waypoints/X1-TS-A1/market"),
+            1
+        );
+    });
+}
+
+#[test]
+fn rejected_action_is_an_api_error() {
+    common::run(|server| async move {
+        server.reset(docked_ship_state());
+        let (app, handle) = common::app();
+        let mut handler = IoHandler::new(handle);
+        handler
+            .handle_io_event(IoEvent::UpdateShips)
+            .await
+            .expect("ships are updated");
+
+        // Ships have to be in orbit to navigate
+        let error = handler
+            .handle_io_event(IoEvent::NavigateShip {
+                ship: "TEST-1".to_owned(),
+                waypoint: "X1-TS-B2".to_owned(),
+            })
+            .await
+            .expect_err("docked ship can't navigate");
+
+        let error = error
+            .downcast_ref::<ApiError>()
+            .expect("error is from the API");
+        assert_eq!(error.code, Some(4236));
+        assert_eq!(error.message, "Ship is not in orbit");
+        assert!(!error.unreachable);
+        assert_eq!(
+            app.lock().await.ship("TEST-1").map(|s| s.nav.status),
+            Some(ShipNavStatus::Docked)
+        );
+    });
+}
+
+#[test]
+fn handler_stops_once_the_app_closes() {
+    common::run(|server| async move {
+        server.reset(docked_ship_state());
+        let (updates, receiver) = AppHandle::new();
+        drop(receiver);
+        let mut handler = IoHandler::new(updates);
+
+        let result = handler.handle_io_event(IoEvent::UpdateAgent).await;
+
+        assert!(result.is_err());
+    });
+}
@@ -0,0 +1,342 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use reqwest::StatusCode;
+use spacedust::models::{Market, ShipNavFlightMode, ShipNavStatus, Waypoint, WaypointType};
+use spacetraders_tui::{
+    io::{runner, IoEvent},
+    rate_limit,
+    trade::{Jump, Location, TradeRoute},
+};
+
+use common::{MockState, Scripted};
+
+fn ship_state() -> MockState {
+    MockState {
+        agent: common::agent(),
+        ships: vec![common::ship("TEST-1")],
+        waypoints: vec![
+            common::waypoint("X1-TS-A1", 0, 0),
+            common::waypoint("X1-TS-B2", 3, 4),
+        ],
+        ..MockState::default()
+    }
+}
+
+#[test]
+fn slow_refresh_does_not_block_actions() {
+    common::run(|server| async move {
+        server.reset(ship_state());
+        let (app, events) = common::running_app();
+        app.lock().await.dispatch(IoEvent::UpdateShips).await;
+        common::settle(&events).await;
+        server.script(
+            Scripted::error("/factions", StatusCode::NOT_FOUND, 404, "Slow")
+                .delay(Duration::from_secs(2)),
+        );
+
+        let start = Instant::now();
+        let mut app_lock = app.lock().await;
+        app_lock.dispatch(IoEvent::UpdateFactions).await;
+        app_lock
+            .dispatch(IoEvent::OrbitShip("TEST-1".to_owned()))
+            .await;
+        drop(app_lock);
+        while app.lock().await.ship("TEST-1").map(|s| s.nav.status) != Some(ShipNavStatus::InOrbit)
+        {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        assert!(start.elapsed() < Duration::from_secs(1));
+        let activity = runner::activity();
+        assert_eq!(activity.len(), 1);
+        assert_eq!(activity[0].event, IoEvent::UpdateFactions);
+        assert!(activity[0].started_at.is_some());
+        common::settle(&events).await;
+    });
+}
+
+#[test]
+fn events_for_a_ship_run_in_order() {
+    common::run(|server| async move {
+        server.reset(ship_state());
+        let (app, events) = common::running_app();
+        app.lock().await.dispatch(IoEvent::UpdateShips).await;
+        common::settle(&events).await;
+
+        // Navigating fails unless the orbit before it has finished
+        let mut app_lock = app.lock().await;
+        app_lock
+            .dispatch(IoEvent::OrbitShip("TEST-1".to_owned()))
+            .await;
+        app_lock
+            .dispatch(IoEvent::NavigateShip {
+                ship: "TEST-1".to_owned(),
+                waypoint: "X1-TS-B2".to_owned(),
+            })
+            .await;
+        drop(app_lock);
+        common::settle(&events).await;
+
+        let app = app.lock().await;
+        assert!(app.state.notifications.is_empty());
+        assert_eq!(
+            app.ship("TEST-1").map(|s| s.nav.status),
+            Some(ShipNavStatus::InTransit)
+        );
+    });
+}
+
+#[test]
+fn duplicate_waiting_refreshes_are_coalesced() {
+    common::run(|server| async move {
+        server.reset(ship_state());
+        let (app, events) = common::running_app();
+        app.lock().await.dispatch(IoEvent::UpdateShips).await;
+        common::settle(&events).await;
+        server.script(
+            Scripted::error("/my/ships/TEST-1/orbit", StatusCode::NOT_FOUND, 404, "Slow")
+                .delay(Duration::from_millis(300)),
+        );
+
+        let mut app_lock = app.lock().await;
+        app_lock
+            .dispatch(IoEvent::OrbitShip("TEST-1".to_owned()))
+            .await;
+        for _ in 0..3 {
+            app_lock
+                .dispatch(IoEvent::UpdateShipNav("TEST-1".to_owned()))
+                .await;
+        }
+        drop(app_lock);
+        while events.capacity() < events.max_capacity() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(runner::activity().len(), 2);
+        common::settle(&events).await;
+
+        assert_eq!(server.state().requests_to("/my/ships/TEST-1/nav"), 1);
+    });
+}
+
+#[test]
+fn events_can_be_cancelled_one_at_a_time() {
+    common::run(|server| async move {
+        server.reset(ship_state());
+        let (app, events) = common::running_app();
+        app.lock().await.dispatch(IoEvent::UpdateShips).await;
+        common::settle(&events).await;
+        server.script(
+            Scripted::error("/my/ships/TEST-1/orbit", StatusCode::NOT_FOUND, 404, "Slow")
+                .delay(Duration::from_secs(10)),
+        );
+
+        let mut app_lock = app.lock().await;
+        app_lock
+            .dispatch(IoEvent::OrbitShip("TEST-1".to_owned()))
+            .await;
+        app_lock
+            .dispatch(IoEvent::DockShip("TEST-1".to_owned()))
+            .await;
+        drop(app_lock);
+        while rate_limit::queue_status().in_flight == 0 || runner::activity().len() < 2 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let activity = runner::activity();
+
+        // The dock is waiting behind the orbit, so it's dropped straight away
+        let dock = activity[1].id;
+        assert_eq!(
+            runner::cancel(dock),
+            Some(IoEvent::DockShip("TEST-1".to_owned()))
+        );
+        let activity = runner::activity();
+        assert_eq!(activity.len(), 1);
+        assert_eq!(activity[0].event, IoEvent::OrbitShip("TEST-1".to_owned()));
+
+        let start = Instant::now();
+        runner::cancel(activity[0].id);
+        common::settle(&events).await;
+
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!(runner::cancel(dock), None);
+        assert_eq!(rate_limit::queue_status().in_flight, 0);
+        assert_eq!(server.state().requests_to("/my/ships/TEST-1/dock"), 0);
+        let app = app.lock().await;
+        assert!(app.state.notifications.is_empty());
+        assert_eq!(
+            app.ship("TEST-1").map(|s| s.nav.status),
+            Some(ShipNavStatus::Docked)
+        );
+    });
+}
+
+#[test]
+fn trade_routes_hold_their_ship_until_cancelled() {
+    common::run(|server| async move {
+        server.reset(ship_state());
+        let (app, events) = common::running_app();
+        app.lock().await.dispatch(IoEvent::UpdateShips).await;
+        common::settle(&events).await;
+        server.script(
+            Scripted::error(
+                "/my/ships/TEST-1/purchase",
+                StatusCode::NOT_FOUND,
+                404,
+                "Slow",
+            )
+            .delay(Duration::from_secs(10)),
+        );
+
+        let location = |symbol: &str, x, y| Location {
+            symbol: symbol.to_owned(),
+            x,
+            y,
+        };
+        let route = TradeRoute {
+            good: "IRON_ORE".to_owned(),
+            buy_at: location("X1-TS-A1", 0, 0),
+            buy_price: 10,
+            buy_volume: 10,
+            sell_at: location("X1-TS-B2", 3, 4),
+            sell_price: 20,
+            sell_volume: 10,
+            jump: None,
+            units: 10,
+            flight_mode: ShipNavFlightMode::Cruise,
+            refuel_at_buy: false,
+            refuel_at_sell: false,
+            fuel: 5,
+            profit: 100,
+            seconds: 60,
+        };
+        let mut app_lock = app.lock().await;
+        app_lock
+            .dispatch(IoEvent::RunTradeRoute {
+                ship: "TEST-1".to_owned(),
+                route,
+            })
+            .await;
+        app_lock
+            .dispatch(IoEvent::OrbitShip("TEST-1".to_owned()))
+            .await;
+        drop(app_lock);
+        while server.state().requests_to("/my/ships/TEST-1/purchase") == 0
+            || runner::activity().len() < 2
+        {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        // The orbit waits for the route rather than racing it
+        let activity = runner::activity();
+        assert_eq!(activity.len(), 2);
+        assert!(activity[0].started_at.is_some());
+        assert!(activity[1].started_at.is_none());
+
+        runner::cancel(activity[0].id);
+        common::settle(&events).await;
+
+        let app = app.lock().await;
+        assert!(app.state.notifications.is_empty());
+        assert_eq!(
+            app.ship("TEST-1").map(|s| s.nav.status),
+            Some(ShipNavStatus::InOrbit)
+        );
+    });
+}
+
+#[test]
+fn trade_routes_buying_at_the_jump_gate_leave_orbit_to_jump() {
+    common::run(|server| async move {
+        let mut state = ship_state();
+        let gate = Waypoint {
+            r#type: WaypointType::JumpGate,
+            system_symbol: "X1-JS".to_owned(),
+            ..common::waypoint("X1-JS-G1", 0, 0)
+        };
+        let sell_market = Market {
+            symbol: gate.symbol.clone(),
+            ..common::market()
+        };
+        state
+            .markets
+            .insert("X1-TS-A1".to_owned(), common::market());
+        state.markets.insert(gate.symbol.clone(), sell_market);
+        state.waypoints.push(gate);
+        server.reset(state);
+        let (app, events) = common::running_app();
+        app.lock().await.dispatch(IoEvent::UpdateShips).await;
+        common::settle(&events).await;
+
+        let location = |symbol: &str| Location {
+            symbol: symbol.to_owned(),
+            x: 0,
+            y: 0,
+        };
+        let route = TradeRoute {
+            good: "IRON_ORE".to_owned(),
+            buy_at: location("X1-TS-A1"),
+            buy_price: 20,
+            buy_volume: 10,
+            sell_at: location("X1-JS-G1"),
+            sell_price: 15,
+            sell_volume: 10,
+            jump: Some(Jump {
+                from_gate: location("X1-TS-A1"),
+                system_symbol: "X1-JS".to_owned(),
+                to_gate: location("X1-JS-G1"),
+                distance: 10,
+            }),
+            units: 10,
+            flight_mode: ShipNavFlightMode::Cruise,
+            refuel_at_buy: false,
+            refuel_at_sell: false,
+            fuel: 0,
+            profit: 0,
+            seconds: 60,
+        };
+        app.lock()
+            .await
+            .dispatch(IoEvent::RunTradeRoute {
+                ship: "TEST-1".to_owned(),
+                route,
+            })
+            .await;
+        common::settle(&events).await;
+
+        let app = app.lock().await;
+        assert!(app.state.notifications.is_empty());
+        assert_eq!(server.state().requests_to("/my/ships/TEST-1/jump"), 1);
+        let ship = app.ship("TEST-1").expect("ship is known");
+        assert_eq!(ship.nav.waypoint_symbol, "X1-JS-G1");
+        assert_eq!(ship.nav.status, ShipNavStatus::Docked);
+        assert!(ship.cargo.inventory.is_empty());
+    });
+}
+
+#[test]
+fn failed_actions_do_not_go_offline() {
+    common::run(|server| async move {
+        server.reset(ship_state());
+        let (app, events) = common::running_app();
+        app.lock().await.dispatch(IoEvent::UpdateShips).await;
+        common::settle(&events).await;
+        server.script(Scripted::error(
+            "/my/ships/TEST-1/orbit",
+            StatusCode::BAD_GATEWAY,
+            500,
+            "Bad gateway",
+        ));
+
+        app.lock()
+            .await
+            .dispatch(IoEvent::OrbitShip("TEST-1".to_owned()))
+            .await;
+        common::settle(&events).await;
+
+        let app = app.lock().await;
+        assert_eq!(app.state.notifications.len(), 1);
+        assert!(!app.state.offline);
+    });
+}
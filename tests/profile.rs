@@ -0,0 +1,192 @@
+mod common;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use spacedust::models::{Contract, Faction};
+use spacetraders_tui::{
+    config::get_global_db_pool,
+    db_util,
+    io::{handler::IoHandler, IoEvent},
+    profile::{self, Profile},
+};
+use sqlx::Row;
+
+use common::MockState;
+
+/// A token shaped like the ones the server issues, for the given agent
+fn token(symbol: &str) -> String {
+    let claims = URL_SAFE_NO_PAD.encode(format!(r#"{{"identifier":"{symbol}"}}"#));
+    format!("header.{claims}.signature")
+}
+
+fn profile(symbol: &str) -> Profile {
+    Profile {
+        symbol: symbol.to_owned(),
+        token: token(symbol),
+    }
+}
+
+#[test]
+fn agent_symbol_reads_the_token_identifier() {
+    assert_eq!(
+        profile::agent_symbol(&token("ALPHA")).as_deref(),
+        Some("ALPHA")
+    );
+    assert_eq!(profile::agent_symbol("test-token"), None);
+}
+
+#[test]
+fn requests_are_made_as_the_shown_agent() {
+    common::run(|server| async move {
+        server.reset(MockState {
+            agent: common::agent(),
+            ..MockState::default()
+        });
+        let (app, events) = common::running_app();
+        let mut app_lock = app.lock().await;
+        app_lock.profiles = vec![profile("ALPHA"), profile("BETA")];
+        app_lock.profile = Some(profile("BETA"));
+        app_lock.dispatch(IoEvent::UpdateAgent).await;
+        drop(app_lock);
+        common::settle(&events).await;
+
+        assert_eq!(server.state().tokens, vec![Some(token("BETA"))]);
+    });
+}
+
+#[test]
+fn results_for_another_agent_are_kept_for_it() {
+    common::run(|_| async move {
+        let (app, handle) = common::app();
+        let mut app_lock = app.lock().await;
+        app_lock.profiles = vec![profile("ALPHA"), profile("BETA")];
+        app_lock.profile = Some(profile("ALPHA"));
+        drop(app_lock);
+
+        handle
+            .for_agent(Some("BETA"))
+            .update(|app| app.state.agent.credits = 5)
+            .await
+            .expect("app is open");
+        let mut app = app.lock().await;
+        assert_eq!(app.state.agent.credits, 0);
+
+        app.switch_agent("BETA").await;
+        assert_eq!(app.state.agent.credits, 5);
+        app.switch_agent("ALPHA").await;
+        assert_eq!(app.state.agent.credits, 0);
+    });
+}
+
+#[test]
+fn registering_shows_the_new_agent_and_records_its_token() {
+    common::run(|server| async move {
+        let faction = |symbol: &str| {
+            Faction::new(
+                symbol.to_owned(),
+                format!("{symbol} faction"),
+                String::new(),
+                "X1-HQ-A1".to_owned(),
+                Vec::new(),
+            )
+        };
+        server.reset(MockState {
+            agent: common::agent(),
+            factions: vec![faction("COSMIC"), faction("ASTRO"), faction("VOID")],
+            ..MockState::default()
+        });
+        let (app, events) = common::running_app();
+        let mut app_lock = app.lock().await;
+        app_lock.profiles = vec![profile("ALPHA")];
+        app_lock.profile = Some(profile("ALPHA"));
+        app_lock.register_agent().await;
+        drop(app_lock);
+        common::settle(&events).await;
+
+        let mut app_lock = app.lock().await;
+        let picker = app_lock
+            .state
+            .picker
+            .as_ref()
+            .expect("faction picker is open");
+        // Only the factions the server lists which registering accepts
+        let choices: Vec<_> = picker.items.iter().map(|i| i.value.as_str()).collect();
+        assert_eq!(choices, ["COSMIC", "VOID"]);
+        app_lock.submit_picker().await;
+        app_lock
+            .state
+            .prompt
+            .as_mut()
+            .expect("call sign prompt is open")
+            .input = "newbie".to_owned();
+        app_lock.submit_prompt().await;
+        drop(app_lock);
+        common::settle(&events).await;
+
+        let app = app.lock().await;
+        assert_eq!(app.profile, Some(profile("NEWBIE")));
+        assert_eq!(
+            app.profiles.iter().map(|p| &p.symbol).collect::<Vec<_>>(),
+            ["ALPHA", "NEWBIE"]
+        );
+        assert_eq!(app.state.agent.symbol, "NEWBIE");
+        assert_eq!(app.state.ships[0].symbol, "NEWBIE-1");
+        assert_eq!(app.state.contracts[0].faction_symbol, "COSMIC");
+        let tokens = db_util::load_agent_tokens().await.expect("tokens load");
+        assert!(tokens.contains(&("NEWBIE".to_owned(), token("NEWBIE"))));
+    });
+}
+
+#[test]
+fn contracts_are_recorded_for_the_agent_that_fetched_them() {
+    common::run(|server| async move {
+        let contract = Contract {
+            id: "CONTRACT-BETA".to_owned(),
+            ..Contract::default()
+        };
+        server.reset(MockState {
+            agent: common::agent(),
+            contracts: vec![contract],
+            ..MockState::default()
+        });
+        let (app, handle) = common::app();
+        let mut app_lock = app.lock().await;
+        app_lock.profiles = vec![profile("ALPHA"), profile("BETA")];
+        app_lock.profile = Some(profile("ALPHA"));
+        app_lock.state.agent.symbol = "ALPHA".to_owned();
+        drop(app_lock);
+
+        IoHandler::new(handle.for_agent(Some("BETA")))
+            .handle_io_event(IoEvent::UpdateContracts)
+            .await
+            .expect("contracts are fetched");
+
+        let agent_symbol: String =
+            sqlx::query("SELECT agent_symbol FROM contracts WHERE id = 'CONTRACT-BETA'")
+                .fetch_one(get_global_db_pool().await)
+                .await
+                .expect("contract is recorded")
+                .get("agent_symbol");
+        assert_eq!(agent_symbol, "BETA");
+    });
+}
+
+#[test]
+fn agent_shown_without_a_profile_is_kept_when_switching() {
+    common::run(|_| async move {
+        let (app, _) = common::app();
+        let mut app = app.lock().await;
+        app.profiles = vec![profile("ALPHA")];
+        app.state.agent.symbol = "TEST".to_owned();
+        app.state.agent.credits = 5;
+
+        app.switch_agent("ALPHA").await;
+        assert!(app.profiles.iter().any(|p| p.symbol == "TEST"));
+        app.switch_agent("TEST").await;
+
+        assert_eq!(
+            app.profile.as_ref().map(|p| p.symbol.as_str()),
+            Some("TEST")
+        );
+        assert_eq!(app.state.agent.credits, 5);
+    });
+}
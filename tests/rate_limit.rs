@@ -0,0 +1,180 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use reqwest::StatusCode;
+use spacedust::apis::{agents_api::get_my_agent, fleet_api::orbit_ship, Error};
+use spacetraders_tui::{
+    api_error::ApiError,
+    config::CONFIGURATION,
+    rate_limit::{self, RateLimits},
+};
+
+use common::{MockState, Scripted};
+
+fn agent_state() -> MockState {
+    MockState {
+        agent: common::agent(),
+        ..MockState::default()
+    }
+}
+
+#[test]
+fn server_errors_are_retried() {
+    common::run(|server| async move {
+        server.reset(agent_state());
+        for _ in 0..2 {
+            server.script(Scripted::error(
+                "/my/agent",
+                StatusCode::BAD_GATEWAY,
+                500,
+                "Bad gateway",
+            ));
+        }
+
+        let agent = get_my_agent(&CONFIGURATION)
+            .await
+            .expect("agent is fetched");
+
+        assert_eq!(*agent.data, common::agent());
+        assert_eq!(server.state().requests_to("/my/agent"), 3);
+    });
+}
+
+#[test]
+fn server_errors_give_up_after_max_retries() {
+    common::run(|server| async move {
+        server.reset(agent_state());
+        for _ in 0..10 {
+            server.script(Scripted::error(
+                "/my/agent",
+                StatusCode::INTERNAL_SERVER_ERROR,
+                500,
+                "Something broke",
+            ));
+        }
+
+        let error = rate_limit::tracking_retries(async {
+            get_my_agent(&CONFIGURATION)
+                .await
+                .map_err(ApiError::from)
+                .expect_err("request gives up")
+        })
+        .await;
+
+        assert_eq!(error.status, Some(StatusCode::INTERNAL_SERVER_ERROR));
+        assert_eq!(error.retries, 3);
+        // The first attempt and three retries
+        assert_eq!(server.state().requests_to("/my/agent"), 4);
+    });
+}
+
+#[test]
+fn server_errors_on_actions_are_not_retried() {
+    common::run(|server| async move {
+        server.reset(MockState {
+            ships: vec![common::ship("TEST-1")],
+            ..agent_state()
+        });
+        server.script(Scripted::error(
+            "/my/ships/TEST-1/orbit",
+            StatusCode::BAD_GATEWAY,
+            500,
+            "Bad gateway",
+        ));
+
+        let result = orbit_ship(&CONFIGURATION, "TEST-1", 0).await;
+
+        match result {
+            Err(Error::ResponseError(response)) => {
+                assert_eq!(response.status, StatusCode::BAD_GATEWAY);
+            }
+            other => panic!("expected an error response, got {other:?}"),
+        }
+        assert_eq!(server.state().requests_to("/my/ships/TEST-1/orbit"), 1);
+    });
+}
+
+#[test]
+fn rate_limited_requests_wait_for_reset() {
+    common::run(|server| async move {
+        server.reset(agent_state());
+        let reset = Utc::now() + chrono::Duration::milliseconds(300);
+        server.script(
+            Scripted::error(
+                "/my/agent",
+                StatusCode::TOO_MANY_REQUESTS,
+                429,
+                "Rate limit exceeded",
+            )
+            .header("x-ratelimit-reset", &reset.to_rfc3339()),
+        );
+
+        let start = Instant::now();
+        let agent = get_my_agent(&CONFIGURATION)
+            .await
+            .expect("agent is fetched");
+
+        assert_eq!(*agent.data, common::agent());
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        assert_eq!(server.state().requests_to("/my/agent"), 2);
+    });
+}
+
+#[test]
+fn rate_limited_requests_without_reset_are_not_retried() {
+    common::run(|server| async move {
+        server.reset(agent_state());
+        server.script(Scripted::error(
+            "/my/agent",
+            StatusCode::TOO_MANY_REQUESTS,
+            429,
+            "Rate limit exceeded",
+        ));
+
+        let error = ApiError::from(
+            get_my_agent(&CONFIGURATION)
+                .await
+                .expect_err("request fails"),
+        );
+
+        assert_eq!(error.status, Some(StatusCode::TOO_MANY_REQUESTS));
+        assert_eq!(error.code, Some(429));
+        assert_eq!(server.state().requests_to("/my/agent"), 1);
+    });
+}
+
+#[test]
+fn reported_limits_are_followed() {
+    common::run(|server| async move {
+        server.reset(MockState {
+            headers: vec![
+                ("x-ratelimit-limit-burst".to_owned(), "7".to_owned()),
+                ("x-ratelimit-limit-per-second".to_owned(), "3".to_owned()),
+            ],
+            ..agent_state()
+        });
+
+        get_my_agent(&CONFIGURATION)
+            .await
+            .expect("agent is fetched");
+
+        let bucket = rate_limit::bucket_state();
+        let reported = RateLimits {
+            burst: 7,
+            per_second: 3,
+        };
+        assert_eq!(bucket.reported, Some(reported));
+        assert_eq!(bucket.limits, reported);
+
+        // Put back limits that won't slow down other tests
+        server.state().headers = vec![
+            ("x-ratelimit-limit-burst".to_owned(), "100".to_owned()),
+            ("x-ratelimit-limit-per-second".to_owned(), "100".to_owned()),
+        ];
+        get_my_agent(&CONFIGURATION)
+            .await
+            .expect("agent is fetched");
+    });
+}
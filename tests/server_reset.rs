@@ -0,0 +1,127 @@
+mod common;
+
+use spacetraders_tui::{
+    app::PickerAction, config::get_global_db_pool, db_util, io::IoEvent, profile::Profile,
+};
+use sqlx::Row;
+
+use common::MockState;
+
+fn test_profile() -> Profile {
+    Profile {
+        symbol: "TEST".to_owned(),
+        token: "test-token".to_owned(),
+    }
+}
+
+#[test]
+fn unchanged_reset_fetches_the_agent() {
+    common::run(|server| async move {
+        let recorded = db_util::load_reset_date()
+            .await
+            .expect("reset date loads")
+            .unwrap_or_else(|| "2023-06-03".to_owned());
+        db_util::save_reset_date(&recorded)
+            .await
+            .expect("reset date is recorded");
+        server.reset(MockState {
+            agent: common::agent(),
+            reset_date: recorded,
+            ..MockState::default()
+        });
+        let (app, events) = common::running_app();
+        let mut app_lock = app.lock().await;
+        app_lock.profile = Some(test_profile());
+        app_lock.dispatch(IoEvent::CheckServerStatus).await;
+        drop(app_lock);
+        common::settle(&events).await;
+
+        let app = app.lock().await;
+        assert!(app.state.confirm.is_none());
+        assert_eq!(app.state.agent.symbol, "TEST");
+        let state = server.state();
+        assert_eq!(state.requests_to("/my/contracts"), 1);
+        assert_eq!(state.requests_to("/factions"), 1);
+        assert_eq!(state.requests_to("/my/ships"), 1);
+    });
+}
+
+#[test]
+fn reset_is_archived_once_confirmed() {
+    common::run(|server| async move {
+        db_util::save_reset_date("2023-06-03")
+            .await
+            .expect("reset date is recorded");
+        db_util::save_agent(&common::agent())
+            .await
+            .expect("agent is recorded");
+        server.reset(MockState {
+            agent: common::agent(),
+            reset_date: "2023-06-17".to_owned(),
+            ..MockState::default()
+        });
+        let (app, events) = common::running_app();
+        let mut app_lock = app.lock().await;
+        app_lock.profiles = vec![test_profile()];
+        app_lock.profile = Some(test_profile());
+        app_lock.dispatch(IoEvent::CheckServerStatus).await;
+        drop(app_lock);
+        common::settle(&events).await;
+
+        // Nothing is fetched with a token from before the reset
+        assert_eq!(server.state().requests_to("/my/agent"), 0);
+        let mut app_lock = app.lock().await;
+        let confirm = app_lock
+            .state
+            .confirm
+            .as_ref()
+            .expect("archiving is offered");
+        assert_eq!(
+            confirm.event,
+            IoEvent::ArchiveReset {
+                previous: "2023-06-03".to_owned(),
+                current: "2023-06-17".to_owned(),
+            }
+        );
+        app_lock.submit_confirm().await;
+        drop(app_lock);
+        common::settle(&events).await;
+
+        let mut app_lock = app.lock().await;
+        assert!(app_lock.profile.is_none());
+        assert!(app_lock.profiles.is_empty());
+        let picker = app_lock
+            .state
+            .picker
+            .as_ref()
+            .expect("registering is started");
+        assert_eq!(picker.action, PickerAction::RegisterFaction);
+
+        // The token from the settings is from before the reset too, so isn't used
+        app_lock.state.picker = None;
+        app_lock.dispatch(IoEvent::UpdateAgent).await;
+        drop(app_lock);
+        common::settle(&events).await;
+        assert_eq!(server.state().requests_to("/my/agent"), 0);
+        let app = app.lock().await;
+        let picker = app
+            .state
+            .picker
+            .as_ref()
+            .expect("registering is offered again");
+        assert_eq!(picker.action, PickerAction::RegisterFaction);
+
+        let reset_date = db_util::load_reset_date().await.expect("reset date loads");
+        assert_eq!(reset_date.as_deref(), Some("2023-06-17"));
+        let latest = db_util::load_latest_agent_symbol()
+            .await
+            .expect("agents load");
+        assert_eq!(latest, None);
+        let archived = sqlx::query("SELECT symbol FROM agents_2023_06_03")
+            .fetch_all(get_global_db_pool().await)
+            .await
+            .expect("archived agents load");
+        assert_eq!(archived.len(), 1);
+        assert_eq!(archived[0].get::<String, _>("symbol"), "TEST");
+    });
+}
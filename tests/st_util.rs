@@ -0,0 +1,84 @@
+mod common;
+
+use reqwest::StatusCode;
+use spacedust::apis::Error;
+use spacetraders_tui::st_util;
+
+use common::{MockState, Scripted};
+
+#[test]
+fn list_ships_fetches_every_page() {
+    common::run(|server| async move {
+        let ships: Vec<_> = (1..=45)
+            .map(|i| common::ship(&format!("TEST-{i}")))
+            .collect();
+        server.reset(MockState {
+            ships: ships.clone(),
+            ..MockState::default()
+        });
+
+        let listed = st_util::list_ships().await.expect("ships are listed");
+
+        assert_eq!(listed, ships);
+        let state = server.state();
+        assert_eq!(state.requests_to("/my/ships"), 3);
+        assert!(state
+            .requests
+            .iter()
+            .any(|r| r.ends_with("page=3&limit=20")));
+    });
+}
+
+#[test]
+fn list_stops_after_a_full_last_page() {
+    common::run(|server| async move {
+        let waypoints: Vec<_> = (1..=20)
+            .map(|i| common::waypoint(&format!("X1-TS-A{i}"), i, 0))
+            .collect();
+        server.reset(MockState {
+            waypoints: waypoints.clone(),
+            ..MockState::default()
+        });
+
+        let listed = st_util::list_system_waypoints("X1-TS")
+            .await
+            .expect("waypoints are listed");
+
+        assert_eq!(listed, waypoints);
+        assert_eq!(server.state().requests_to("/systems/X1-TS/waypoints"), 1);
+    });
+}
+
+#[test]
+fn list_of_nothing_is_empty() {
+    common::run(|server| async move {
+        let listed = st_util::list_contracts()
+            .await
+            .expect("contracts are listed");
+
+        assert!(listed.is_empty());
+        assert_eq!(server.state().requests_to("/my/contracts"), 1);
+    });
+}
+
+#[test]
+fn list_passes_on_errors() {
+    common::run(|server| async move {
+        server.script(Scripted::error(
+            "/factions",
+            StatusCode::UNAUTHORIZED,
+            401,
+            "Missing token",
+        ));
+
+        let result = st_util::list_factions().await;
+
+        match result {
+            Err(Error::ResponseError(response)) => {
+                assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+                assert!(response.content.contains("Missing token"));
+            }
+            other => panic!("expected an error response, got {other:?}"),
+        }
+    });
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use spacedust::models::ShipNavFlightMode;
+use spacetraders_tui::trade::{self, GoodPrice, KnownMarket, Location, TradeData};
+
+fn market(symbol: &str, x: i32, goods: &[(&str, i32)]) -> KnownMarket {
+    KnownMarket {
+        location: Location {
+            symbol: symbol.to_owned(),
+            x,
+            y: 0,
+        },
+        system_symbol: "X1-TS".to_owned(),
+        trade_goods: goods
+            .iter()
+            .map(|&(good, price)| GoodPrice {
+                symbol: good.to_owned(),
+                trade_volume: 10,
+                purchase_price: price,
+                sell_price: price,
+            })
+            .collect(),
+    }
+}
+
+/// Iron ore bought 60 units along from the ship and sold 60 units further on
+fn trade_data(buy_market_fuel: bool) -> TradeData {
+    let mut buy_goods = vec![("IRON_ORE", 10)];
+    if buy_market_fuel {
+        buy_goods.push(("FUEL", 1));
+    }
+    TradeData {
+        system_symbol: "X1-TS".to_owned(),
+        markets: vec![
+            market("X1-TS-B2", 60, &buy_goods),
+            market("X1-TS-C3", 120, &[("IRON_ORE", 100)]),
+        ],
+        jumps: Vec::new(),
+    }
+}
+
+#[test]
+fn routes_needing_more_fuel_than_the_ship_has_are_dropped() {
+    let mut ship = common::ship("TEST-1");
+    ship.fuel.current = 50;
+
+    let routes = trade::find_routes(&ship, &trade_data(true), ShipNavFlightMode::Cruise);
+
+    assert!(routes.is_empty());
+}
+
+#[test]
+fn fuel_is_paid_for_where_the_tank_is_topped_up() {
+    let mut ship = common::ship("TEST-1");
+    ship.nav.route.destination.x = 40;
+    let data = TradeData {
+        system_symbol: "X1-TS".to_owned(),
+        markets: vec![
+            market("X1-TS-B2", 60, &[("IRON_ORE", 10), ("FUEL", 1)]),
+            market("X1-TS-C3", 120, &[("IRON_ORE", 100), ("FUEL", 5)]),
+        ],
+        jumps: Vec::new(),
+    };
+
+    let routes = trade::find_routes(&ship, &data, ShipNavFlightMode::Cruise);
+
+    let route = routes
+        .iter()
+        .find(|r| r.good == "IRON_ORE" && r.buy_at.symbol == "X1-TS-B2")
+        .expect("iron ore route is found");
+    // 20 fuel to the buy market bought there, then 60 more bought at the sell market
+    assert_eq!(route.fuel, 80);
+    assert_eq!(route.profit, 30 * 90 - (20 + 60 * 5));
+}
+
+#[test]
+fn routes_refuel_only_where_fuel_is_sold() {
+    let ship = common::ship("TEST-1");
+
+    let with_fuel = trade::find_routes(&ship, &trade_data(true), ShipNavFlightMode::Cruise);
+    let without_fuel = trade::find_routes(&ship, &trade_data(false), ShipNavFlightMode::Cruise);
+
+    // Each leg fits in the tank, but both together don't without filling up in between
+    assert_eq!(with_fuel.len(), 1);
+    assert_eq!(with_fuel[0].good, "IRON_ORE");
+    assert!(with_fuel[0].refuel_at_buy);
+    assert!(without_fuel.is_empty());
+}